@@ -0,0 +1,8 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS file_access (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    file_id INTEGER NOT NULL,
+    address TEXT NOT NULL,
+    access TEXT NOT NULL,
+    created_time TEXT NOT NULL
+);
\ No newline at end of file
@@ -0,0 +1,14 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS signature_requests (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    file_id INTEGER NOT NULL,
+    requester TEXT NOT NULL,
+    signer TEXT NOT NULL,
+    status TEXT NOT NULL,
+    created_time TEXT NOT NULL,
+    updated_time TEXT NOT NULL,
+    expires_at TEXT,
+    verification_hash TEXT
+);
+CREATE INDEX IF NOT EXISTS signature_requests_signer ON signature_requests (signer);
+CREATE INDEX IF NOT EXISTS signature_requests_file_id ON signature_requests (file_id);
\ No newline at end of file
@@ -0,0 +1,8 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS signature_modes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    verification_hash TEXT NOT NULL UNIQUE,
+    mode TEXT NOT NULL,
+    typed_data TEXT NOT NULL,
+    created_time TEXT NOT NULL
+);
\ No newline at end of file
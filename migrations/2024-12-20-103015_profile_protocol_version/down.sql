@@ -0,0 +1,2 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE user_profiles DROP COLUMN protocol_version;
\ No newline at end of file
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE user_profiles ADD COLUMN protocol_version TEXT NOT NULL DEFAULT '1.2';
\ No newline at end of file
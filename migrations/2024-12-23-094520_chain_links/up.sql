@@ -0,0 +1,11 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS chain_links (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    file_id INTEGER NOT NULL,
+    revision_hash TEXT NOT NULL UNIQUE,
+    linked_file_id INTEGER NOT NULL,
+    linked_verification_hash TEXT NOT NULL,
+    created_time TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS chain_links_file_id ON chain_links (file_id);
\ No newline at end of file
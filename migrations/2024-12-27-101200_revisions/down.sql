@@ -0,0 +1,13 @@
+-- This file should undo anything in `up.sql`
+UPDATE pages
+SET page_data = json_set(page_data, '$.pages[0].revisions', (
+    SELECT json_group_object(ordered.verification_hash, json(ordered.revision_data))
+    FROM (
+        SELECT verification_hash, revision_data FROM revisions
+        WHERE revisions.file_id = pages.id
+        ORDER BY position
+    ) AS ordered
+))
+WHERE id IN (SELECT DISTINCT file_id FROM revisions);
+
+DROP TABLE IF EXISTS revisions;
\ No newline at end of file
@@ -0,0 +1,57 @@
+-- Your SQL goes here
+CREATE TABLE IF NOT EXISTS revisions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    file_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    verification_hash TEXT NOT NULL,
+    previous_verification_hash TEXT,
+    kind TEXT NOT NULL,
+    domain_id TEXT NOT NULL,
+    time_stamp TEXT NOT NULL,
+    metadata_hash TEXT NOT NULL,
+    content_hash TEXT NOT NULL,
+    file_hash TEXT,
+    signer TEXT,
+    witness_network TEXT,
+    witness_tx_hash TEXT,
+    revision_data TEXT NOT NULL,
+    UNIQUE (file_id, position)
+);
+
+CREATE INDEX IF NOT EXISTS revisions_verification_hash ON revisions (verification_hash);
+CREATE INDEX IF NOT EXISTS revisions_signer ON revisions (signer);
+CREATE INDEX IF NOT EXISTS revisions_witness_network ON revisions (witness_network);
+
+-- v1.2 chains move their revisions here in chain order, v1.3 chains stay in page_data
+INSERT INTO revisions (
+    file_id, position, verification_hash, previous_verification_hash, kind, domain_id,
+    time_stamp, metadata_hash, content_hash, file_hash, signer, witness_network,
+    witness_tx_hash, revision_data
+)
+SELECT
+    chains.id,
+    ROW_NUMBER() OVER (PARTITION BY chains.id ORDER BY entry.id) - 1,
+    entry.key,
+    json_extract(entry.value, '$.metadata.previous_verification_hash'),
+    CASE
+        WHEN json_type(entry.value, '$.witness') = 'object' THEN 'witness'
+        WHEN json_type(entry.value, '$.signature') = 'object' THEN 'signature'
+        ELSE 'content'
+    END,
+    json_extract(entry.value, '$.metadata.domain_id'),
+    json_extract(entry.value, '$.metadata.time_stamp'),
+    json_extract(entry.value, '$.metadata.metadata_hash'),
+    json_extract(entry.value, '$.content.content_hash'),
+    json_extract(entry.value, '$.content.content.file_hash'),
+    lower(json_extract(entry.value, '$.signature.wallet_address')),
+    json_extract(entry.value, '$.witness.witness_network'),
+    json_extract(entry.value, '$.witness.witness_event_transaction_hash'),
+    entry.value
+FROM (
+    SELECT id, page_data FROM pages
+    WHERE json_valid(page_data) AND json_type(page_data, '$.pages[0].revisions') = 'object'
+) AS chains, json_each(chains.page_data, '$.pages[0].revisions') AS entry;
+
+UPDATE pages
+SET page_data = json_set(page_data, '$.pages[0].revisions', json('{}'))
+WHERE id IN (SELECT DISTINCT file_id FROM revisions);
\ No newline at end of file
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+ALTER TABLE pages ADD COLUMN version INTEGER NOT NULL DEFAULT 0;
\ No newline at end of file
@@ -0,0 +1,2 @@
+-- Your SQL goes here
+CREATE INDEX IF NOT EXISTS pages_owner_created_at ON pages (owner, created_at);
\ No newline at end of file
@@ -0,0 +1,36 @@
+-- Your SQL goes here
+-- the rowid of an entry is the id of its file
+CREATE VIRTUAL TABLE IF NOT EXISTS file_search USING fts5(owner UNINDEXED, name, extension, hashes);
+
+-- v1.2 chains are found by their revision rows, v1.3 chains by their revisions in page_data
+INSERT INTO file_search (rowid, owner, name, extension, hashes)
+SELECT
+    pages.id,
+    pages.owner,
+    pages.name,
+    pages.extension,
+    trim(
+        COALESCE((
+            SELECT group_concat(
+                revisions.verification_hash || ' ' || COALESCE(revisions.file_hash, '') || ' ' ||
+                COALESCE(revisions.signer, '') || ' ' || COALESCE(revisions.witness_tx_hash, ''),
+                ' '
+            )
+            FROM revisions WHERE revisions.file_id = pages.id
+        ), '') || ' ' ||
+        COALESCE((
+            SELECT group_concat(
+                entry.key || ' ' ||
+                COALESCE(json_extract(entry.value, '$.legacy_verification_hash'), '') || ' ' ||
+                COALESCE(json_extract(entry.value, '$.file_hash'), '') || ' ' ||
+                COALESCE(json_extract(entry.value, '$.signature_wallet_address'), '') || ' ' ||
+                COALESCE(json_extract(entry.value, '$.witness_transaction_hash'), ''),
+                ' '
+            )
+            FROM json_each(
+                CASE WHEN json_valid(pages.page_data) THEN pages.page_data ELSE '{}' END,
+                '$.revisions'
+            ) AS entry
+        ), '')
+    )
+FROM pages;
\ No newline at end of file
@@ -0,0 +1,19 @@
+-- This file should undo anything in `up.sql`
+UPDATE pages
+SET page_data = json_set(page_data, '$.revisions', (
+    SELECT json_group_object(ordered.verification_hash, json(ordered.revision_data))
+    FROM (
+        SELECT verification_hash, revision_data FROM revisions
+        WHERE revisions.file_id = pages.id
+        ORDER BY position
+    ) AS ordered
+))
+WHERE json_valid(page_data)
+    AND json_type(page_data, '$.pages') IS NULL
+    AND id IN (SELECT DISTINCT file_id FROM revisions);
+
+DELETE FROM revisions
+WHERE file_id IN (
+    SELECT id FROM pages
+    WHERE json_valid(page_data) AND json_type(page_data, '$.pages') IS NULL
+);
@@ -0,0 +1,58 @@
+-- Your SQL goes here
+-- v1.3 chains move their revisions here too, in chain order from the genesis revision.
+-- Chains that do not form a single line stay whole in page_data
+WITH RECURSIVE entries AS (
+    SELECT pages.id AS file_id, entry.key AS hash, entry.value AS revision
+    FROM pages, json_each(pages.page_data, '$.revisions') AS entry
+    WHERE json_valid(pages.page_data)
+        AND json_type(pages.page_data, '$.pages') IS NULL
+        AND json_type(pages.page_data, '$.revisions') = 'object'
+),
+chain (file_id, position, hash, revision) AS (
+    SELECT file_id, 0, hash, revision FROM entries
+    WHERE COALESCE(json_extract(revision, '$.previous_verification_hash'), '') = ''
+    UNION ALL
+    SELECT entries.file_id, chain.position + 1, entries.hash, entries.revision
+    FROM chain JOIN entries
+        ON entries.file_id = chain.file_id
+        AND json_extract(entries.revision, '$.previous_verification_hash') = chain.hash
+),
+linear AS (
+    SELECT chain.file_id FROM chain
+    GROUP BY chain.file_id
+    HAVING COUNT(*) = COUNT(DISTINCT chain.position)
+        AND COUNT(*) = (SELECT COUNT(*) FROM entries WHERE entries.file_id = chain.file_id)
+)
+INSERT INTO revisions (
+    file_id, position, verification_hash, previous_verification_hash, kind, domain_id,
+    time_stamp, metadata_hash, content_hash, file_hash, signer, witness_network,
+    witness_tx_hash, revision_data
+)
+SELECT
+    chain.file_id,
+    chain.position,
+    chain.hash,
+    NULLIF(json_extract(chain.revision, '$.previous_verification_hash'), ''),
+    CASE json_extract(chain.revision, '$.revision_type')
+        WHEN 'signature' THEN 'signature'
+        WHEN 'witness' THEN 'witness'
+        WHEN 'link' THEN 'link'
+        ELSE 'content'
+    END,
+    '',
+    COALESCE(json_extract(chain.revision, '$.local_timestamp'), ''),
+    '',
+    '',
+    NULLIF(json_extract(chain.revision, '$.file_hash'), ''),
+    NULLIF(lower(json_extract(chain.revision, '$.signature_wallet_address')), ''),
+    NULLIF(json_extract(chain.revision, '$.witness_network'), ''),
+    NULLIF(json_extract(chain.revision, '$.witness_transaction_hash'), ''),
+    chain.revision
+FROM chain
+WHERE chain.file_id IN (SELECT file_id FROM linear);
+
+UPDATE pages
+SET page_data = json_set(page_data, '$.revisions', json('{}'))
+WHERE json_valid(page_data)
+    AND json_type(page_data, '$.pages') IS NULL
+    AND id IN (SELECT DISTINCT file_id FROM revisions);
@@ -0,0 +1,3 @@
+-- This file should undo anything in `up.sql`
+DROP INDEX IF EXISTS siwe_sessions_expires_at;
+ALTER TABLE siwe_sessions DROP COLUMN expires_at;
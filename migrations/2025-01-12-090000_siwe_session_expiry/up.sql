@@ -0,0 +1,13 @@
+-- Your SQL goes here
+-- When a session ends in UTC, sortable as text like siwe_nonces.expires_at, so expired
+-- sessions are purged with one DELETE. Sessions whose times do not parse stay NULL and are
+-- purged as expired.
+ALTER TABLE siwe_sessions ADD COLUMN expires_at TEXT;
+
+-- sessions without an expiration time last the default SIWE_SESSION_MAX_LIFETIME_SECONDS
+UPDATE siwe_sessions SET expires_at = CASE
+    WHEN expiration_time IS NOT NULL THEN strftime('%Y-%m-%d %H:%M:%S', expiration_time)
+    ELSE strftime('%Y-%m-%d %H:%M:%S', issued_at, '+86400 seconds')
+END;
+
+CREATE INDEX IF NOT EXISTS siwe_sessions_expires_at ON siwe_sessions (expires_at);
@@ -0,0 +1,11 @@
+-- This file should undo anything in `up.sql`
+DROP TABLE IF EXISTS revisions;
+DROP TABLE IF EXISTS chain_links;
+DROP TABLE IF EXISTS signature_modes;
+DROP TABLE IF EXISTS signature_requests;
+DROP TABLE IF EXISTS siwe_nonces;
+DROP TABLE IF EXISTS file_access;
+DROP TABLE IF EXISTS share_data;
+DROP TABLE IF EXISTS user_profiles;
+DROP TABLE IF EXISTS siwe_sessions;
+DROP TABLE IF EXISTS pages;
\ No newline at end of file
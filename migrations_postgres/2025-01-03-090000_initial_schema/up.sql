@@ -0,0 +1,108 @@
+-- Your SQL goes here
+-- the schema of the SQLite migrations up to 2024-12-30-143005_page_version
+CREATE TABLE IF NOT EXISTS pages (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    extension TEXT NOT NULL,
+    page_data TEXT NOT NULL,
+    owner TEXT NOT NULL,
+    mode TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    is_shared BOOLEAN DEFAULT false NOT NULL,
+    version INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS siwe_sessions (
+    id SERIAL PRIMARY KEY,
+    address TEXT NOT NULL,
+    nonce TEXT NOT NULL,
+    issued_at TEXT NOT NULL,
+    expiration_time TEXT
+);
+
+CREATE TABLE IF NOT EXISTS user_profiles (
+    id SERIAL PRIMARY KEY,
+    address TEXT NOT NULL,
+    chain TEXT NOT NULL,
+    theme TEXT NOT NULL,
+    contract_address TEXT NOT NULL,
+    file_mode TEXT NOT NULL,
+    domain_name TEXT NOT NULL,
+    protocol_version TEXT NOT NULL DEFAULT '1.2'
+);
+
+CREATE TABLE IF NOT EXISTS share_data (
+    id SERIAL PRIMARY KEY,
+    file_id INTEGER NOT NULL,
+    identifier TEXT NOT NULL,
+    created_time TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS file_access (
+    id SERIAL PRIMARY KEY,
+    file_id INTEGER NOT NULL,
+    address TEXT NOT NULL,
+    access TEXT NOT NULL,
+    created_time TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS siwe_nonces (
+    id SERIAL PRIMARY KEY,
+    nonce TEXT NOT NULL UNIQUE,
+    created_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS signature_requests (
+    id SERIAL PRIMARY KEY,
+    file_id INTEGER NOT NULL,
+    requester TEXT NOT NULL,
+    signer TEXT NOT NULL,
+    status TEXT NOT NULL,
+    created_time TEXT NOT NULL,
+    updated_time TEXT NOT NULL,
+    expires_at TEXT,
+    verification_hash TEXT
+);
+CREATE INDEX IF NOT EXISTS signature_requests_signer ON signature_requests (signer);
+CREATE INDEX IF NOT EXISTS signature_requests_file_id ON signature_requests (file_id);
+
+CREATE TABLE IF NOT EXISTS signature_modes (
+    id SERIAL PRIMARY KEY,
+    verification_hash TEXT NOT NULL UNIQUE,
+    mode TEXT NOT NULL,
+    typed_data TEXT NOT NULL,
+    created_time TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS chain_links (
+    id SERIAL PRIMARY KEY,
+    file_id INTEGER NOT NULL,
+    revision_hash TEXT NOT NULL UNIQUE,
+    linked_file_id INTEGER NOT NULL,
+    linked_verification_hash TEXT NOT NULL,
+    created_time TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS chain_links_file_id ON chain_links (file_id);
+
+CREATE TABLE IF NOT EXISTS revisions (
+    id SERIAL PRIMARY KEY,
+    file_id INTEGER NOT NULL,
+    position INTEGER NOT NULL,
+    verification_hash TEXT NOT NULL,
+    previous_verification_hash TEXT,
+    kind TEXT NOT NULL,
+    domain_id TEXT NOT NULL,
+    time_stamp TEXT NOT NULL,
+    metadata_hash TEXT NOT NULL,
+    content_hash TEXT NOT NULL,
+    file_hash TEXT,
+    signer TEXT,
+    witness_network TEXT,
+    witness_tx_hash TEXT,
+    revision_data TEXT NOT NULL,
+    UNIQUE (file_id, position)
+);
+CREATE INDEX IF NOT EXISTS revisions_verification_hash ON revisions (verification_hash);
+CREATE INDEX IF NOT EXISTS revisions_signer ON revisions (signer);
+CREATE INDEX IF NOT EXISTS revisions_witness_network ON revisions (witness_network);
\ No newline at end of file
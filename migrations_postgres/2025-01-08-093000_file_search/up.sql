@@ -0,0 +1,50 @@
+-- Your SQL goes here
+-- text is split into runs of letters and digits, the way the SQLite FTS5 index tokenizes it
+CREATE TABLE IF NOT EXISTS file_search (
+    file_id INTEGER PRIMARY KEY,
+    owner TEXT NOT NULL,
+    name TEXT NOT NULL,
+    extension TEXT NOT NULL,
+    hashes TEXT NOT NULL,
+    document TSVECTOR GENERATED ALWAYS AS (
+        to_tsvector(
+            'simple',
+            regexp_replace(name || ' ' || extension || ' ' || hashes, '[^[:alnum:]]+', ' ', 'g')
+        )
+    ) STORED
+);
+CREATE INDEX IF NOT EXISTS file_search_document ON file_search USING GIN (document);
+CREATE INDEX IF NOT EXISTS file_search_owner ON file_search (owner);
+
+-- v1.2 chains are found by their revision rows, v1.3 chains by their revisions in page_data
+INSERT INTO file_search (file_id, owner, name, extension, hashes)
+SELECT
+    pages.id,
+    pages.owner,
+    pages.name,
+    pages.extension,
+    trim(
+        COALESCE((
+            SELECT string_agg(concat_ws(' ',
+                revisions.verification_hash, revisions.file_hash, revisions.signer,
+                revisions.witness_tx_hash
+            ), ' ')
+            FROM revisions WHERE revisions.file_id = pages.id
+        ), '') || ' ' ||
+        COALESCE((
+            SELECT string_agg(concat_ws(' ',
+                entry.key,
+                entry.value ->> 'legacy_verification_hash',
+                entry.value ->> 'file_hash',
+                entry.value ->> 'signature_wallet_address',
+                entry.value ->> 'witness_transaction_hash'
+            ), ' ')
+            FROM jsonb_each(
+                CASE WHEN jsonb_typeof(pages.page_data::jsonb -> 'revisions') = 'object'
+                    THEN pages.page_data::jsonb -> 'revisions'
+                    ELSE '{}'::jsonb
+                END
+            ) AS entry
+        ), '')
+    )
+FROM pages;
\ No newline at end of file
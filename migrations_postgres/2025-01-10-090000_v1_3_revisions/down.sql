@@ -0,0 +1,31 @@
+-- This file should undo anything in `up.sql`
+-- The revisions of a v1.3 document, NULL for v1.2 chains and for page_data that is not JSON,
+-- which the cast would otherwise abort the migration on. Dropped again below.
+CREATE FUNCTION v1_3_revisions_of(page_data TEXT) RETURNS JSONB AS $$
+DECLARE
+    doc JSONB;
+BEGIN
+    doc := page_data::jsonb;
+    IF jsonb_typeof(doc) = 'object' AND NOT (doc ? 'pages')
+        AND jsonb_typeof(doc -> 'revisions') = 'object' THEN
+        RETURN doc -> 'revisions';
+    END IF;
+    RETURN NULL;
+EXCEPTION WHEN others THEN
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+UPDATE pages
+SET page_data = jsonb_set(page_data::jsonb, '{revisions}', (
+    SELECT jsonb_object_agg(revisions.verification_hash, revisions.revision_data::jsonb)
+    FROM revisions
+    WHERE revisions.file_id = pages.id
+))::text
+WHERE v1_3_revisions_of(page_data) IS NOT NULL
+    AND id IN (SELECT DISTINCT file_id FROM revisions);
+
+DELETE FROM revisions
+WHERE file_id IN (SELECT id FROM pages WHERE v1_3_revisions_of(page_data) IS NOT NULL);
+
+DROP FUNCTION v1_3_revisions_of(TEXT);
@@ -0,0 +1,73 @@
+-- Your SQL goes here
+-- v1.3 chains move their revisions here too, in chain order from the genesis revision.
+-- Chains that do not form a single line stay whole in page_data
+-- The revisions of a v1.3 document, NULL for v1.2 chains and for page_data that is not JSON,
+-- which the cast would otherwise abort the migration on. Dropped again below.
+CREATE FUNCTION v1_3_revisions_of(page_data TEXT) RETURNS JSONB AS $$
+DECLARE
+    doc JSONB;
+BEGIN
+    doc := page_data::jsonb;
+    IF jsonb_typeof(doc) = 'object' AND NOT (doc ? 'pages')
+        AND jsonb_typeof(doc -> 'revisions') = 'object' THEN
+        RETURN doc -> 'revisions';
+    END IF;
+    RETURN NULL;
+EXCEPTION WHEN others THEN
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql IMMUTABLE;
+
+WITH RECURSIVE entries AS (
+    SELECT pages.id AS file_id, entry.key AS hash, entry.value AS revision
+    FROM pages, jsonb_each(v1_3_revisions_of(pages.page_data)) AS entry
+),
+chain (file_id, position, hash, revision) AS (
+    SELECT file_id, 0, hash, revision FROM entries
+    WHERE COALESCE(revision ->> 'previous_verification_hash', '') = ''
+    UNION ALL
+    SELECT entries.file_id, chain.position + 1, entries.hash, entries.revision
+    FROM chain JOIN entries
+        ON entries.file_id = chain.file_id
+        AND entries.revision ->> 'previous_verification_hash' = chain.hash
+),
+linear AS (
+    SELECT chain.file_id FROM chain
+    GROUP BY chain.file_id
+    HAVING COUNT(*) = COUNT(DISTINCT chain.position)
+        AND COUNT(*) = (SELECT COUNT(*) FROM entries WHERE entries.file_id = chain.file_id)
+)
+INSERT INTO revisions (
+    file_id, position, verification_hash, previous_verification_hash, kind, domain_id,
+    time_stamp, metadata_hash, content_hash, file_hash, signer, witness_network,
+    witness_tx_hash, revision_data
+)
+SELECT
+    chain.file_id,
+    chain.position,
+    chain.hash,
+    NULLIF(chain.revision ->> 'previous_verification_hash', ''),
+    CASE chain.revision ->> 'revision_type'
+        WHEN 'signature' THEN 'signature'
+        WHEN 'witness' THEN 'witness'
+        WHEN 'link' THEN 'link'
+        ELSE 'content'
+    END,
+    '',
+    COALESCE(chain.revision ->> 'local_timestamp', ''),
+    '',
+    '',
+    NULLIF(chain.revision ->> 'file_hash', ''),
+    NULLIF(lower(chain.revision ->> 'signature_wallet_address'), ''),
+    NULLIF(chain.revision ->> 'witness_network', ''),
+    NULLIF(chain.revision ->> 'witness_transaction_hash', ''),
+    chain.revision::text
+FROM chain
+WHERE chain.file_id IN (SELECT file_id FROM linear);
+
+UPDATE pages
+SET page_data = jsonb_set(page_data::jsonb, '{revisions}', '{}'::jsonb)::text
+WHERE v1_3_revisions_of(page_data) IS NOT NULL
+    AND id IN (SELECT DISTINCT file_id FROM revisions);
+
+DROP FUNCTION v1_3_revisions_of(TEXT);
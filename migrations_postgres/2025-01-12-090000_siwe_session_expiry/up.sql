@@ -0,0 +1,23 @@
+-- Your SQL goes here
+-- When a session ends in UTC, sortable as text like siwe_nonces.expires_at, so expired
+-- sessions are purged with one DELETE. Sessions whose times do not parse stay NULL and are
+-- purged as expired.
+ALTER TABLE siwe_sessions ADD COLUMN expires_at TEXT;
+
+CREATE FUNCTION siwe_utc_time(value TEXT, lifetime INTERVAL) RETURNS TEXT AS $$
+BEGIN
+    RETURN to_char((value::timestamptz + lifetime) AT TIME ZONE 'UTC', 'YYYY-MM-DD HH24:MI:SS');
+EXCEPTION WHEN others THEN
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+-- sessions without an expiration time last the default SIWE_SESSION_MAX_LIFETIME_SECONDS
+UPDATE siwe_sessions SET expires_at = CASE
+    WHEN expiration_time IS NOT NULL THEN siwe_utc_time(expiration_time, INTERVAL '0 seconds')
+    ELSE siwe_utc_time(issued_at, INTERVAL '86400 seconds')
+END;
+
+DROP FUNCTION siwe_utc_time(TEXT, INTERVAL);
+
+CREATE INDEX IF NOT EXISTS siwe_sessions_expires_at ON siwe_sessions (expires_at);
@@ -1,3 +1,7 @@
+pub mod nonce;
+pub mod permissions;
+pub mod session;
+
 use crate::db::siwe::fetch_siwe_data;
 use crate::models::UserProfilesTable;
 use crate::Db;
@@ -35,6 +39,12 @@ pub struct SiweNonceRequest {
     pub nonce: String,
 }
 
+#[derive(Serialize, Debug)]
+pub struct SiweNonceResponse {
+    pub nonce: String,
+    pub expires_at: String,
+}
+
 #[derive(Serialize, Debug)]
 pub struct SiweResponse {
     pub logs: Vec<String>,
@@ -50,6 +60,9 @@ pub enum SiweError {
     AddressMismatch,
     InvalidMessage,
     MessageVerificationFailed,
+    InvalidNonce,
+    MessageExpired,
+    WalletContractUnavailable,
 }
 
 impl fmt::Display for SiweError {
@@ -60,6 +73,11 @@ impl fmt::Display for SiweError {
             SiweError::AddressMismatch => write!(f, "Ethereum address mismatch"),
             SiweError::InvalidMessage => write!(f, "Invalid SIWE Message"),
             SiweError::MessageVerificationFailed => write!(f, "Message verification failed"),
+            SiweError::InvalidNonce => write!(f, "Nonce was not issued, already used or expired"),
+            SiweError::MessageExpired => write!(f, "SIWE message is expired or not yet valid"),
+            SiweError::WalletContractUnavailable => {
+                write!(f, "Smart contract wallet could not be checked")
+            }
         }
     }
 }
@@ -0,0 +1,125 @@
+use crate::db::siwe::{delete_expired_siwe_nonces, delete_expired_siwe_sessions};
+use crate::models::{SiweSessionsTable, DB_POOL};
+use chrono::{DateTime, Duration, Utc};
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+use std::env;
+
+const DEFAULT_NONCE_TTL_SECONDS: i64 = 300;
+const DEFAULT_SESSION_MAX_LIFETIME_SECONDS: i64 = 86_400;
+const CLEANUP_INTERVAL_SECONDS: u64 = 60;
+
+/// Time format of `siwe_nonces.created_at`/`expires_at`, sortable as text.
+pub const NONCE_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+fn seconds_from_env(name: &str, default: i64) -> Duration {
+    let seconds = env::var(name)
+        .ok()
+        .and_then(|value| value.parse::<i64>().ok())
+        .filter(|value| *value > 0)
+        .unwrap_or(default);
+    Duration::seconds(seconds)
+}
+
+/// How long an issued nonce can be used, configurable with `SIWE_NONCE_TTL_SECONDS`.
+pub fn nonce_ttl() -> Duration {
+    seconds_from_env("SIWE_NONCE_TTL_SECONDS", DEFAULT_NONCE_TTL_SECONDS)
+}
+
+/// How long a session whose SIWE message has no expiration time lasts from `issued_at`,
+/// configurable with `SIWE_SESSION_MAX_LIFETIME_SECONDS`.
+pub fn session_max_lifetime() -> Duration {
+    seconds_from_env(
+        "SIWE_SESSION_MAX_LIFETIME_SECONDS",
+        DEFAULT_SESSION_MAX_LIFETIME_SECONDS,
+    )
+}
+
+/// SIWE nonces must be at least 8 alphanumeric characters.
+pub fn generate_nonce() -> String {
+    thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(17)
+        .map(char::from)
+        .collect()
+}
+
+pub fn format_nonce_time(time: DateTime<Utc>) -> String {
+    time.naive_utc().format(NONCE_TIME_FORMAT).to_string()
+}
+
+pub fn session_is_expired(expiration_time: &Option<String>, now: DateTime<Utc>) -> bool {
+    match expiration_time {
+        Some(time) => match DateTime::parse_from_rfc3339(time) {
+            Ok(time) => time.with_timezone(&Utc) <= now,
+            Err(e) => {
+                tracing::error!("Unable to parse session expiration time {}", e);
+                true
+            }
+        },
+        None => false,
+    }
+}
+
+/// Sessions end at their `expiration_time`, or `session_max_lifetime` after `issued_at` when
+/// the message set none.
+pub fn siwe_session_is_expired(session: &SiweSessionsTable, now: DateTime<Utc>) -> bool {
+    if session.expiration_time.is_some() {
+        return session_is_expired(&session.expiration_time, now);
+    }
+    match DateTime::parse_from_rfc3339(&session.issued_at) {
+        Ok(issued_at) => issued_at.with_timezone(&Utc) + session_max_lifetime() <= now,
+        Err(e) => {
+            tracing::error!("Unable to parse session issue time {}", e);
+            true
+        }
+    }
+}
+
+/// The end of a session as `siwe_session_is_expired` works it out, in `NONCE_TIME_FORMAT` for
+/// `siwe_sessions.expires_at`. `None` when the times do not parse, such sessions are expired.
+pub fn siwe_session_expires_at(issued_at: &str, expiration_time: &Option<String>) -> Option<String> {
+    let end = match expiration_time {
+        Some(time) => DateTime::parse_from_rfc3339(time).ok()?.with_timezone(&Utc),
+        None => {
+            DateTime::parse_from_rfc3339(issued_at).ok()?.with_timezone(&Utc)
+                + session_max_lifetime()
+        }
+    };
+    Some(format_nonce_time(end))
+}
+
+fn purge_expired_siwe_data(pool: &DB_POOL) -> Result<(), String> {
+    let mut conn = pool.get().map_err(|e| e.to_string())?;
+    let now = format_nonce_time(Utc::now());
+
+    let nonces = delete_expired_siwe_nonces(&now, &mut conn)?;
+    let sessions = delete_expired_siwe_sessions(&now, &mut conn)?;
+
+    if nonces > 0 || sessions > 0 {
+        tracing::debug!(
+            "Purged {} expired nonce(s) and {} expired session(s)",
+            nonces,
+            sessions
+        );
+    }
+    Ok(())
+}
+
+/// Periodically removes expired nonces and sessions.
+pub fn spawn_siwe_cleanup(pool: DB_POOL) {
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(CLEANUP_INTERVAL_SECONDS));
+        loop {
+            interval.tick().await;
+            let pool = pool.clone();
+            let result = tokio::task::spawn_blocking(move || purge_expired_siwe_data(&pool)).await;
+            match result {
+                Ok(Err(e)) => tracing::error!("Failed to purge expired SIWE data: {}", e),
+                Err(e) => tracing::error!("SIWE cleanup task failed: {}", e),
+                Ok(Ok(())) => {}
+            }
+        }
+    });
+}
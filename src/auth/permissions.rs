@@ -0,0 +1,99 @@
+use crate::auth::session::AuthenticatedUser;
+use crate::db::file_access::fetch_file_access_for_address;
+use crate::db::pages_db::fetch_page_data;
+use crate::db::signature_requests::fetch_pending_signature_requests;
+use crate::models::PagesTable;
+use axum::http::StatusCode;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use crate::db::DbConnection;
+use std::fmt;
+
+/// Stored in `file_access.access` for callers allowed to append revisions.
+pub const WRITE_ACCESS: &str = "write";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileAccess {
+    /// Only the owner of the file, e.g. deleting or sharing it.
+    Owner,
+    /// The owner or an address granted write access, e.g. signing or witnessing.
+    Write,
+    /// Anyone with write access, anyone asked to sign it, or anyone signed in once the file is shared.
+    Read,
+}
+
+#[derive(Debug)]
+pub enum AuthorizationError {
+    FileNotFound(i32),
+    Forbidden { file_id: i32, address: String },
+    DatabaseError(String),
+}
+
+impl AuthorizationError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            AuthorizationError::FileNotFound(_) => StatusCode::NOT_FOUND,
+            AuthorizationError::Forbidden { .. } => StatusCode::FORBIDDEN,
+            AuthorizationError::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl fmt::Display for AuthorizationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthorizationError::FileNotFound(file_id) => {
+                write!(f, "File {} not found in database", file_id)
+            }
+            AuthorizationError::Forbidden { file_id, address } => {
+                write!(f, "{} is not allowed to access file {}", address, file_id)
+            }
+            AuthorizationError::DatabaseError(e) => write!(f, "Database error {}", e),
+        }
+    }
+}
+
+impl std::error::Error for AuthorizationError {}
+
+/// Fetches the page for `file_id` if `user` holds the requested access to it.
+pub fn authorize_file_access(
+    user: &AuthenticatedUser,
+    file_id: i32,
+    access: FileAccess,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<PagesTable, AuthorizationError> {
+    let page = fetch_page_data(file_id, db_connection).map_err(|e| {
+        tracing::error!("authorize_file_access: {}", e);
+        AuthorizationError::FileNotFound(file_id)
+    })?;
+
+    if user.is(&page.owner) {
+        return Ok(page);
+    }
+
+    if access == FileAccess::Read && page.is_shared {
+        return Ok(page);
+    }
+
+    // invitees need to see the file they are asked to sign
+    if access == FileAccess::Read {
+        let requests = fetch_pending_signature_requests(file_id, &user.address, db_connection)
+            .map_err(AuthorizationError::DatabaseError)?;
+        if !requests.is_empty() {
+            return Ok(page);
+        }
+    }
+
+    if access != FileAccess::Owner {
+        let grants = fetch_file_access_for_address(file_id, &user.address, db_connection)
+            .map_err(AuthorizationError::DatabaseError)?;
+
+        if grants.iter().any(|grant| grant.access == WRITE_ACCESS) {
+            return Ok(page);
+        }
+    }
+
+    Err(AuthorizationError::Forbidden {
+        file_id,
+        address: user.address.clone(),
+    })
+}
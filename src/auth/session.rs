@@ -0,0 +1,134 @@
+use crate::auth::nonce::siwe_session_is_expired;
+use crate::db::siwe::{delete_siwe_session_by_nonce, fetch_siwe_session_by_nonce};
+use crate::models::page_data::ApiResponse;
+use crate::Db;
+use axum::{
+    extract::FromRequestParts,
+    http::{request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+
+/// Header carrying the session nonce returned by `siwe_sign_in`.
+pub const SESSION_HEADER: &str = "nonce";
+
+/// Query parameter `ContentUser` accepts instead of the header, for urls used directly by the
+/// browser (e.g. `<video src>`), which cannot set custom headers.
+pub const SESSION_QUERY_PARAMETER: &str = "nonce";
+
+/// A caller whose session nonce resolved to a live row in `siwe_sessions`.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser {
+    pub address: String,
+    pub nonce: String,
+}
+
+impl AuthenticatedUser {
+    /// Wallet addresses are stored checksummed in some rows and lower case in others.
+    pub fn is(&self, address: &str) -> bool {
+        self.address.eq_ignore_ascii_case(address)
+    }
+}
+
+/// A caller of `GET /files/{id}/content`, the only route that also takes the session nonce
+/// from the query string. Anywhere else a nonce in the url would end up in logs and history.
+#[derive(Debug, Clone)]
+pub struct ContentUser(pub AuthenticatedUser);
+
+#[derive(Debug)]
+pub enum AuthError {
+    MissingSession,
+    InvalidSession,
+    ExpiredSession,
+    DatabaseError(String),
+}
+
+impl IntoResponse for AuthError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            AuthError::MissingSession => (
+                StatusCode::UNAUTHORIZED,
+                "Error : session nonce missing, sign in first".to_string(),
+            ),
+            AuthError::InvalidSession => (
+                StatusCode::UNAUTHORIZED,
+                "Error : session not found".to_string(),
+            ),
+            AuthError::ExpiredSession => (
+                StatusCode::UNAUTHORIZED,
+                "Error : session expired, sign in again".to_string(),
+            ),
+            AuthError::DatabaseError(e) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Failed to get database connection: {}", e),
+            ),
+        };
+
+        let res = ApiResponse {
+            logs: vec![message],
+            file: None,
+            files: Vec::new(),
+            head: None,
+        };
+        (status, Json(res)).into_response()
+    }
+}
+
+fn session_from_query(query: Option<&str>) -> Option<String> {
+    query?.split('&').find_map(|pair| {
+        let (key, value) = pair.split_once('=')?;
+        (key == SESSION_QUERY_PARAMETER).then(|| value.trim().to_string())
+    })
+}
+
+fn session_from_header(parts: &Parts) -> Option<String> {
+    parts
+        .headers
+        .get(SESSION_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim().to_string())
+}
+
+fn authenticate(nonce: Option<String>, state: &Db) -> Result<AuthenticatedUser, AuthError> {
+    let nonce = nonce
+        .filter(|value| !value.is_empty())
+        .ok_or(AuthError::MissingSession)?;
+
+    let mut conn = state
+        .pool
+        .get()
+        .map_err(|e| AuthError::DatabaseError(e.to_string()))?;
+
+    let session = fetch_siwe_session_by_nonce(&nonce, &mut conn).map_err(|e| {
+        tracing::debug!("session lookup failed: {}", e);
+        AuthError::InvalidSession
+    })?;
+
+    if siwe_session_is_expired(&session, Utc::now()) {
+        let _ = delete_siwe_session_by_nonce(&nonce, &mut conn);
+        return Err(AuthError::ExpiredSession);
+    }
+
+    Ok(AuthenticatedUser {
+        address: session.address,
+        nonce,
+    })
+}
+
+impl FromRequestParts<Db> for AuthenticatedUser {
+    type Rejection = AuthError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Db) -> Result<Self, Self::Rejection> {
+        authenticate(session_from_header(parts), state)
+    }
+}
+
+impl FromRequestParts<Db> for ContentUser {
+    type Rejection = AuthError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Db) -> Result<Self, Self::Rejection> {
+        let nonce = session_from_header(parts).or_else(|| session_from_query(parts.uri.query()));
+        authenticate(nonce, state).map(ContentUser)
+    }
+}
@@ -0,0 +1,252 @@
+use crate::db::revisions::file_hash_referenced;
+use crate::db::DbConnection;
+use crate::models::page_data::PageDataContainer;
+use crate::protocol::v1_3::sha256_hex;
+use crate::protocol::AquaDocument;
+use crate::UPLOADS_DIRECTORY;
+use aqua_verifier_rs_types::models::base64::Base64;
+use aqua_verifier_rs_types::models::content::FileContent;
+use aqua_verifier_rs_types::models::hash::Hash;
+use aqua_verifier_rs_types::models::page_data::HashChain;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use sha3::{Digest, Sha3_512};
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use tempfile::{Builder, NamedTempFile, TempPath};
+extern crate serde_json_path_to_error as serde_json;
+
+/// File bytes are stored once under `UPLOADS_DIRECTORY`, named after their SHA3-512 `file_hash`.
+/// Chains in `pages.page_data` keep the genesis `FileContent` without data and reference the
+/// blob through `content.content.file_hash`.
+pub fn blob_path(file_hash: &Hash) -> Result<PathBuf, String> {
+    blob_path_by_key(&file_hash.to_string())
+}
+
+fn is_lower_hex(key: &str) -> bool {
+    key.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+/// Whether `key` is the lowercase hex of a SHA-256, the file hash of v1.3 chains.
+pub fn is_sha256_key(key: &str) -> bool {
+    key.len() == 64 && is_lower_hex(key)
+}
+
+/// Whether `key` can name a blob: the lowercase hex of a SHA-256 or SHA3-512 hash. Keys come
+/// from chains uploaded by users and must never be read as a path.
+pub fn is_blob_key(key: &str) -> bool {
+    is_sha256_key(key) || (key.len() == 128 && is_lower_hex(key))
+}
+
+/// Path of a blob by the hex hash naming it, SHA3-512 for v1.2 chains and SHA-256 for v1.3.
+pub fn blob_path_by_key(key: &str) -> Result<PathBuf, String> {
+    if !is_blob_key(key) {
+        return Err(format!("Invalid blob key {:?}", key));
+    }
+    Ok(PathBuf::from(UPLOADS_DIRECTORY).join(key))
+}
+
+pub fn hash_file_bytes(bytes: &[u8]) -> Hash {
+    let mut file_hasher = Sha3_512::default();
+    file_hasher.update(bytes);
+    Hash::from(file_hasher.finalize())
+}
+
+pub fn blob_exists(file_hash: &Hash) -> bool {
+    blob_path(file_hash).map_or(false, |path| path.is_file())
+}
+
+/// Writes `bytes` to the store, a blob that already exists is left untouched.
+pub fn store_blob(file_hash: &Hash, bytes: &[u8]) -> Result<(), String> {
+    if hash_file_bytes(bytes) != *file_hash {
+        return Err(format!("file data does not match file hash {}", file_hash));
+    }
+
+    let path = blob_path(file_hash)?;
+    if path.is_file() {
+        return Ok(());
+    }
+
+    write_blob(&path, &file_hash.to_string(), bytes)
+}
+
+/// Writes a blob to a temporary file of its own and renames it into place once complete, so
+/// neither a crash nor a concurrent upload of the same file leaves a truncated blob behind.
+fn write_blob(path: &Path, key: &str, bytes: &[u8]) -> Result<(), String> {
+    fs::create_dir_all(UPLOADS_DIRECTORY)
+        .map_err(|e| format!("Error creating uploads directory: {}", e))?;
+
+    let mut file = NamedTempFile::new_in(UPLOADS_DIRECTORY)
+        .map_err(|e| format!("Error creating blob {}: {}", key, e))?;
+    file.write_all(bytes)
+        .and_then(|_| file.as_file().sync_all())
+        .map_err(|e| format!("Error writing blob {}: {}", key, e))?;
+    // the temporary file is removed when persisting fails
+    file.persist(path)
+        .map(|_| ())
+        .map_err(|e| format!("Error saving blob {}: {}", key, e.error))
+}
+
+/// A unique file inside the uploads directory for streaming an upload before its hash is known.
+/// It is deleted when the returned path is dropped without being persisted.
+pub fn temp_upload() -> Result<TempPath, String> {
+    fs::create_dir_all(UPLOADS_DIRECTORY)
+        .map_err(|e| format!("Error creating uploads directory: {}", e))?;
+    Builder::new()
+        .prefix("upload-")
+        .suffix(".tmp")
+        .tempfile_in(UPLOADS_DIRECTORY)
+        .map(NamedTempFile::into_temp_path)
+        .map_err(|e| format!("Error creating upload: {}", e))
+}
+
+/// Moves a fully written temporary upload into the store under `file_hash`.
+pub fn persist_temp_upload(tmp_path: TempPath, file_hash: &Hash) -> Result<(), String> {
+    persist_temp_upload_by_key(tmp_path, &file_hash.to_string())
+}
+
+pub fn persist_temp_upload_by_key(tmp_path: TempPath, key: &str) -> Result<(), String> {
+    let path = blob_path_by_key(key)?;
+    if path.is_file() {
+        // identical content is already stored
+        return tmp_path
+            .close()
+            .map_err(|e| format!("Error removing temporary upload: {}", e));
+    }
+    tmp_path
+        .persist(&path)
+        .map_err(|e| format!("Error saving blob {}: {}", key, e.error))
+}
+
+/// Stores `bytes` under their SHA-256, as referenced by v1.3 chains, and returns the key.
+pub fn store_blob_sha256(bytes: &[u8]) -> Result<String, String> {
+    let key = sha256_hex(bytes);
+    let path = blob_path_by_key(&key)?;
+    if path.is_file() {
+        return Ok(key);
+    }
+
+    write_blob(&path, &key, bytes)?;
+    Ok(key)
+}
+
+pub fn read_blob(file_hash: &Hash) -> Result<Vec<u8>, String> {
+    read_blob_by_key(&file_hash.to_string())
+}
+
+pub fn read_blob_by_key(key: &str) -> Result<Vec<u8>, String> {
+    fs::read(blob_path_by_key(key)?).map_err(|e| format!("Error reading blob {}: {}", key, e))
+}
+
+/// Removes the blobs of `keys` that no stored chain references anymore, called with the file
+/// hashes of chains that were just deleted or upgraded. Failures are logged, the chains are
+/// gone either way.
+pub fn remove_unused_blobs(
+    keys: &[String],
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) {
+    for key in keys {
+        let removed = file_hash_referenced(key, db_connection).and_then(|referenced| {
+            if referenced {
+                return Ok(());
+            }
+            match fs::remove_file(blob_path_by_key(key)?) {
+                Err(e) if e.kind() != ErrorKind::NotFound => {
+                    Err(format!("Error removing blob {}: {}", key, e))
+                }
+                _ => Ok(()),
+            }
+        });
+        if let Err(e) = removed {
+            tracing::error!("Failed to remove blob: {}", e);
+        }
+    }
+}
+
+/// The file hash and `FileContent` of the genesis revision of the first chain.
+pub fn genesis_file(doc: &PageDataContainer<HashChain>) -> Option<(Hash, FileContent)> {
+    let chain = doc.pages.first()?;
+    chain.revisions.iter().find_map(|(hash, revision)| {
+        if hash.to_string() != chain.genesis_hash {
+            return None;
+        }
+        let file = revision.content.file.clone()?;
+        Some((revision.content.content.file_hash, file))
+    })
+}
+
+/// Moves inline file data of every revision into the blob store.
+/// Only the genesis revision keeps its `FileContent` (without data) for the file name and size.
+pub fn strip_file_data(doc: &mut PageDataContainer<HashChain>) -> Result<(), String> {
+    for chain in doc.pages.iter_mut() {
+        let genesis_hash = chain.genesis_hash.clone();
+        for (hash, revision) in chain.revisions.iter_mut() {
+            let file_hash = revision.content.content.file_hash;
+
+            if let Some(file) = revision.content.file.as_mut() {
+                let bytes: &[u8] = file.data.as_ref();
+                if !bytes.is_empty() {
+                    store_blob(&file_hash, bytes)?;
+                    file.data = Base64::from(Vec::new());
+                }
+            }
+
+            if hash.to_string() != genesis_hash {
+                revision.content.file = None;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Puts the file bytes back into the genesis revision, as expected by Aqua JSON exports.
+pub fn inline_file_data(doc: &mut PageDataContainer<HashChain>) -> Result<(), String> {
+    for chain in doc.pages.iter_mut() {
+        let genesis_hash = chain.genesis_hash.clone();
+        for (hash, revision) in chain.revisions.iter_mut() {
+            if hash.to_string() != genesis_hash {
+                continue;
+            }
+
+            let file_hash = revision.content.content.file_hash;
+            if let Some(file) = revision.content.file.as_mut() {
+                let is_empty = {
+                    let bytes: &[u8] = file.data.as_ref();
+                    bytes.is_empty()
+                };
+                if is_empty {
+                    file.data = Base64::from(read_blob(&file_hash)?);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Serializes a chain for the `pages.page_data` column, without inline file data.
+pub fn serialize_for_storage(doc: &mut PageDataContainer<HashChain>) -> Result<String, String> {
+    strip_file_data(doc)?;
+    serde_json::to_string(doc).map_err(|e| format!("Failed to serialize page data: {}", e))
+}
+
+/// Rebuilds the Aqua JSON with inline file data from a stored `page_data` value.
+/// v1.3 chains never embed the file and are returned as stored.
+pub fn hydrate_page_data(page_data: &str) -> Result<String, String> {
+    let mut doc = match AquaDocument::parse(page_data.as_bytes())? {
+        AquaDocument::V1_2(doc) => doc,
+        AquaDocument::V1_3(_) => return Ok(page_data.to_string()),
+    };
+    inline_file_data(&mut doc)?;
+    serde_json::to_string(&doc).map_err(|e| format!("Failed to serialize page data: {}", e))
+}
+
+/// `page_data` as returned to clients, falls back to the stored form if a blob is missing.
+pub fn page_data_for_response(page_data: String) -> String {
+    match hydrate_page_data(&page_data) {
+        Ok(data) => data,
+        Err(e) => {
+            tracing::error!("Failed to inline file data: {}", e);
+            page_data
+        }
+    }
+}
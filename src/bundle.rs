@@ -0,0 +1,303 @@
+use crate::blob_store::{
+    genesis_file, hash_file_bytes, read_blob, read_blob_by_key, strip_file_data,
+};
+use crate::controllers::api_controller::max_file_size;
+use crate::models::page_data::PageDataContainer;
+use crate::protocol::v1_3::{sha256_hex, AquaChainV13};
+use crate::protocol::AquaDocument;
+use crate::signing::ExportedSignatureModes;
+use aqua_verifier_rs_types::models::base64::Base64;
+use aqua_verifier_rs_types::models::page_data::HashChain;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{Cursor, Read, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::{ZipArchive, ZipWriter};
+extern crate serde_json_path_to_error as serde_json;
+
+pub const BUNDLE_MANIFEST_FILE: &str = "manifest.json";
+pub const BUNDLE_CHAIN_FILE: &str = "aqua.json";
+pub const BUNDLE_VERSION: &str = "1";
+
+/// Zip archives start with a local file header.
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+/// Lists what an Aqua bundle contains. The chain JSON is stored without inline file data,
+/// the original bytes live next to it under `file_name`. `file_hash` is the hash the chain
+/// names, SHA3-512 for v1.2 and SHA-256 for v1.3.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BundleManifest {
+    pub version: String,
+    pub genesis_hash: String,
+    pub file_name: String,
+    pub file_hash: String,
+    pub file_size: u64,
+    /// Verification hashes of every revision, in chain order.
+    pub revisions: Vec<String>,
+    /// How the EIP-712 signatures of the chain were signed.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub signature_modes: ExportedSignatureModes,
+}
+
+/// What `read_bundle` takes out of a bundle.
+#[derive(Debug)]
+pub struct Bundle {
+    pub document: AquaDocument,
+    pub file_bytes: Vec<u8>,
+    pub signature_modes: ExportedSignatureModes,
+}
+
+pub fn is_bundle(bytes: &[u8]) -> bool {
+    bytes.starts_with(ZIP_MAGIC)
+}
+
+/// Archive entry name for the original file, never a path outside the archive root.
+fn bundle_file_name(file_name: &str) -> String {
+    Path::new(file_name)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .filter(|name| *name != BUNDLE_MANIFEST_FILE && *name != BUNDLE_CHAIN_FILE)
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| "file".to_string())
+}
+
+/// Builds a zip with the manifest, the chain JSON and the original file of a document of
+/// either protocol version.
+pub fn build_bundle(
+    document: &AquaDocument,
+    signature_modes: &ExportedSignatureModes,
+) -> Result<Vec<u8>, String> {
+    let (mut manifest, chain_json, file_bytes) = match document {
+        AquaDocument::V1_2(doc) => bundle_contents_v1_2(doc)?,
+        AquaDocument::V1_3(chain) => bundle_contents_v1_3(chain)?,
+    };
+    manifest.signature_modes = signature_modes.clone();
+    let manifest_json = serde_json::to_string_pretty(&manifest)
+        .map_err(|e| format!("Failed to serialize manifest: {}", e))?;
+
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+    for (name, bytes) in [
+        (BUNDLE_MANIFEST_FILE, manifest_json.as_bytes()),
+        (BUNDLE_CHAIN_FILE, chain_json.as_bytes()),
+        (manifest.file_name.as_str(), file_bytes.as_slice()),
+    ] {
+        zip.start_file(name, options)
+            .map_err(|e| format!("Error writing {} to bundle: {}", name, e))?;
+        zip.write_all(bytes)
+            .map_err(|e| format!("Error writing {} to bundle: {}", name, e))?;
+    }
+
+    let cursor = zip
+        .finish()
+        .map_err(|e| format!("Error finishing bundle: {}", e))?;
+    Ok(cursor.into_inner())
+}
+
+/// Manifest, chain JSON and file bytes of the first chain of a v1.2 document.
+fn bundle_contents_v1_2(
+    doc: &PageDataContainer<HashChain>,
+) -> Result<(BundleManifest, String, Vec<u8>), String> {
+    let (file_hash, file_content) =
+        genesis_file(doc).ok_or_else(|| "genesis revision does not contain file info".to_string())?;
+    let chain = doc
+        .pages
+        .first()
+        .ok_or_else(|| "first chain not found".to_string())?;
+
+    let file_bytes = {
+        let inline: &[u8] = file_content.data.as_ref();
+        if inline.is_empty() {
+            read_blob(&file_hash)?
+        } else {
+            inline.to_vec()
+        }
+    };
+
+    let mut stored = doc.clone();
+    strip_file_data(&mut stored)?;
+    let chain_json = serde_json::to_string_pretty(&stored)
+        .map_err(|e| format!("Failed to serialize page data: {}", e))?;
+
+    let manifest = BundleManifest {
+        version: BUNDLE_VERSION.to_string(),
+        genesis_hash: chain.genesis_hash.clone(),
+        file_name: bundle_file_name(&file_content.filename),
+        file_hash: file_hash.to_string(),
+        file_size: file_bytes.len() as u64,
+        revisions: chain.revisions.iter().map(|(hash, _)| hash.to_string()).collect(),
+        signature_modes: ExportedSignatureModes::new(),
+    };
+    Ok((manifest, chain_json, file_bytes))
+}
+
+/// Manifest, chain JSON and file bytes of a v1.3 chain, whose file is the blob keyed by its
+/// SHA-256.
+fn bundle_contents_v1_3(chain: &AquaChainV13) -> Result<(BundleManifest, String, Vec<u8>), String> {
+    let revisions: Vec<String> = chain
+        .ordered()?
+        .into_iter()
+        .map(|(hash, _)| hash.clone())
+        .collect();
+    let file_hash = chain
+        .file_hash()
+        .ok_or_else(|| "genesis revision names no file hash".to_string())?;
+    let file_bytes = read_blob_by_key(&file_hash)?;
+
+    let chain_json = serde_json::to_string_pretty(chain)
+        .map_err(|e| format!("Failed to serialize page data: {}", e))?;
+
+    let manifest = BundleManifest {
+        version: BUNDLE_VERSION.to_string(),
+        genesis_hash: revisions[0].clone(),
+        file_name: bundle_file_name(&chain.file_name().unwrap_or_default()),
+        file_hash,
+        file_size: file_bytes.len() as u64,
+        revisions,
+        signature_modes: ExportedSignatureModes::new(),
+    };
+    Ok((manifest, chain_json, file_bytes))
+}
+
+/// Reads an archive entry, refusing anything larger than `limit` so a crafted zip cannot exhaust memory.
+fn read_entry(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    name: &str,
+    limit: u64,
+) -> Result<Vec<u8>, String> {
+    let entry = archive
+        .by_name(name)
+        .map_err(|e| format!("Bundle entry {} missing: {}", name, e))?;
+
+    let mut bytes = Vec::new();
+    entry
+        .take(limit + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Error reading bundle entry {}: {}", name, e))?;
+    if bytes.len() as u64 > limit {
+        return Err(format!("Bundle entry {} exceeds {} bytes", name, limit));
+    }
+    Ok(bytes)
+}
+
+/// Fails unless the manifest lists exactly the genesis and revisions of the chain it ships.
+fn check_manifest_revisions(
+    manifest: &BundleManifest,
+    genesis_hash: &str,
+    revisions: &[String],
+) -> Result<(), String> {
+    if manifest.genesis_hash != genesis_hash {
+        return Err(format!(
+            "manifest genesis hash {} does not match chain genesis hash {}",
+            manifest.genesis_hash, genesis_hash
+        ));
+    }
+    if manifest.revisions != revisions {
+        return Err("manifest revisions do not match the revisions of the chain".to_string());
+    }
+    Ok(())
+}
+
+/// Fails if the file bytes are not the size the manifest declares.
+fn check_manifest_file_size(manifest: &BundleManifest, file_bytes: &[u8]) -> Result<(), String> {
+    if manifest.file_size != file_bytes.len() as u64 {
+        return Err(format!(
+            "manifest file size {} does not match file size {}",
+            manifest.file_size,
+            file_bytes.len()
+        ));
+    }
+    Ok(())
+}
+
+/// Opens a bundle and returns its chain with the original file. A v1.2 chain also gets the
+/// file data inlined into its genesis revision. Fails if the manifest does not describe the
+/// chain and file it ships, or if the file bytes do not hash to the genesis `file_hash`.
+pub fn read_bundle(bytes: &[u8]) -> Result<Bundle, String> {
+    let mut archive =
+        ZipArchive::new(Cursor::new(bytes)).map_err(|e| format!("Invalid bundle: {}", e))?;
+    let limit = max_file_size();
+
+    let manifest: BundleManifest =
+        serde_json::from_slice(&read_entry(&mut archive, BUNDLE_MANIFEST_FILE, limit)?)
+            .map_err(|e| format!("Failed to parse bundle manifest: {}", e))?;
+    let document = AquaDocument::parse(&read_entry(&mut archive, BUNDLE_CHAIN_FILE, limit)?)
+        .map_err(|e| format!("Failed to parse bundle chain: {}", e))?;
+    let mut doc = match document {
+        AquaDocument::V1_2(doc) => doc,
+        AquaDocument::V1_3(chain) => {
+            let revisions: Vec<String> = chain
+                .ordered()?
+                .into_iter()
+                .map(|(hash, _)| hash.clone())
+                .collect();
+            check_manifest_revisions(&manifest, &revisions[0], &revisions)?;
+            let file_hash = chain
+                .file_hash()
+                .ok_or_else(|| "genesis revision names no file hash".to_string())?;
+            if manifest.file_hash != file_hash {
+                return Err(format!(
+                    "manifest file hash {} does not match chain file hash {}",
+                    manifest.file_hash, file_hash
+                ));
+            }
+            let file_bytes =
+                read_entry(&mut archive, &bundle_file_name(&manifest.file_name), limit)?;
+            check_manifest_file_size(&manifest, &file_bytes)?;
+            if sha256_hex(&file_bytes) != file_hash {
+                return Err(format!("file data does not match file hash {}", file_hash));
+            }
+            return Ok(Bundle {
+                document: AquaDocument::V1_3(chain),
+                file_bytes,
+                signature_modes: manifest.signature_modes,
+            });
+        }
+    };
+
+    {
+        let chain = doc
+            .pages
+            .first()
+            .ok_or_else(|| "first chain not found".to_string())?;
+        let revisions: Vec<String> =
+            chain.revisions.iter().map(|(hash, _)| hash.to_string()).collect();
+        check_manifest_revisions(&manifest, &chain.genesis_hash, &revisions)?;
+    }
+
+    let (file_hash, _) =
+        genesis_file(&doc).ok_or_else(|| "genesis revision does not contain file info".to_string())?;
+    if manifest.file_hash != file_hash.to_string() {
+        return Err(format!(
+            "manifest file hash {} does not match chain file hash {}",
+            manifest.file_hash, file_hash
+        ));
+    }
+
+    let file_bytes = read_entry(&mut archive, &bundle_file_name(&manifest.file_name), limit)?;
+    check_manifest_file_size(&manifest, &file_bytes)?;
+    if hash_file_bytes(&file_bytes) != file_hash {
+        return Err(format!("file data does not match file hash {}", file_hash));
+    }
+
+    let chain = doc
+        .pages
+        .first_mut()
+        .ok_or_else(|| "first chain not found".to_string())?;
+    let genesis_hash = chain.genesis_hash.clone();
+    for (hash, revision) in chain.revisions.iter_mut() {
+        if hash.to_string() != genesis_hash {
+            continue;
+        }
+        if let Some(file) = revision.content.file.as_mut() {
+            file.data = Base64::from(file_bytes.clone());
+        }
+    }
+
+    Ok(Bundle {
+        document: AquaDocument::V1_2(doc),
+        file_bytes,
+        signature_modes: manifest.signature_modes,
+    })
+}
@@ -1,17 +1,50 @@
+use crate::auth::permissions::{authorize_file_access, FileAccess};
+use crate::auth::session::AuthenticatedUser;
+use crate::db::revisions::{
+    fetch_file_hashes, fetch_file_ids_signed_by, fetch_file_ids_witnessed_on,
+    fetch_owner_file_hashes, owner_has_file,
+};
 use crate::models::input::{
-    DeleteInput, MergeInput, RevisionInput, UpdateConfigurationInput, WitnessInput,
+    DeleteInput, FileFilterInput, MergeInput, RevisionInput, UpdateConfigurationInput,
+    WitnessInput,
+};
+use crate::models::page_data::{
+    ApiResponse, MergeApiResponse, PageDataContainer, VerificationApiResponse,
 };
-use crate::models::page_data::{ApiResponse, PageDataContainer};
 use crate::models::NewPagesTable;
 use crate::models::{file::FileInfo, page_data};
 use crate::util::{
     check_if_page_data_revision_are_okay, check_or_generate_domain, compute_content_hash,
     get_content_type, get_file_info, make_empty_hash, update_env_file,
 };
-use crate::Db;
-use aqua_verifier::util::{
-    content_hash, metadata_hash, signature_hash, verification_hash, witness_hash,
+use crate::blob_store::{
+    blob_path_by_key, hash_file_bytes, page_data_for_response, persist_temp_upload,
+    persist_temp_upload_by_key, remove_unused_blobs, serialize_for_storage, store_blob_sha256,
+    temp_upload,
+};
+use crate::verification::{
+    check_wallet_signature, confirm_contract_signatures, sha3_hash,
+    verify_hash_chain_with_context, SignatureContext,
 };
+use crate::signing::{
+    append_document_signature, import_signature_modes, load_document_signature_schemes,
+    load_signature_schemes, load_signature_schemes_for, record_signature_scheme,
+    signature_scheme, with_exported_modes, ExportedSignatureModes,
+};
+use crate::witness::{
+    append_witness_revision, latest_verification_hash, witness_event_verification_hash,
+};
+use crate::protocol::{v1_3, AquaDocument, ProtocolVersion};
+use crate::links::check_links;
+use crate::merge::{
+    plan_merge, plan_merge_v13, validate_merge, validate_merge_v13, MergeError,
+};
+use crate::bundle::{is_bundle, read_bundle};
+use crate::witness_confirmation::{confirm_witness, witness_contract_address};
+use crate::networks::network_registry;
+use crate::db::user_profiles::fetch_user_profile;
+use crate::Db;
+use aqua_verifier::util::{content_hash, metadata_hash, signature_hash, verification_hash};
 use aqua_verifier_rs_types::models::base64::Base64;
 use aqua_verifier_rs_types::models::content::RevisionContentContent;
 use aqua_verifier_rs_types::models::content::{FileContent, RevisionContent};
@@ -23,11 +56,11 @@ use aqua_verifier_rs_types::models::revision::Revision;
 use aqua_verifier_rs_types::models::signature::{RevisionSignature, Signature};
 use aqua_verifier_rs_types::models::timestamp::Timestamp;
 use aqua_verifier_rs_types::models::tx_hash::TxHash;
-use aqua_verifier_rs_types::models::witness::{MerkleNode, RevisionWitness};
+use aqua_verifier_rs_types::models::witness::MerkleNode;
 use axum::response::{IntoResponse, Response};
 use axum::{
     body::Bytes,
-    extract::{DefaultBodyLimit, Multipart, Path, Request, State},
+    extract::{multipart::Field, DefaultBodyLimit, Multipart, Path, Query, Request, State},
     handler::HandlerWithoutStateExt,
     http::{HeaderMap, StatusCode},
     response::{Html, Redirect},
@@ -35,6 +68,9 @@ use axum::{
     BoxError, Form, Json, Router,
 };
 use chrono::{DateTime, NaiveDateTime, Utc};
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use diesel::Connection;
+use crate::db::DbConnection;
 use dotenv::from_path;
 use ethaddr::address;
 use ethers::core::k256::sha2::Sha256;
@@ -43,62 +79,153 @@ use serde::{Deserialize, Serialize};
 extern crate serde_json_path_to_error as serde_json;
 use crate::db::pages_db::{
     delete_all_data, delete_all_user_files, delete_page_data, fetch_all_pages_data_per_user,
-    fetch_page_data, insert_page_data, update_page_data,
+    fetch_page_data, insert_page_data, update_page_data, PageUpdateError,
 };
 use dotenv::{dotenv, vars};
 use sha3::{Digest, Sha3_512};
 use std::collections::HashMap;
 use std::env;
-use std::error::Error;
+use std::fmt;
 use std::fs;
 use std::io;
 use std::net::SocketAddr;
 use std::time::SystemTime;
 use std::{collections::BTreeMap, time::UNIX_EPOCH};
+use tokio::io::AsyncWriteExt;
 use tokio::{fs::File, io::BufWriter};
+use tempfile::TempPath;
 use tokio_util::io::StreamReader;
 use tower::ServiceExt;
 use tracing_subscriber::{fmt::format, layer::SubscriberExt, util::SubscriberInitExt};
 
-const MAX_FILE_SIZE: u32 = 20 * 1024 * 1024; // 20 MB in bytes
+const DEFAULT_MAX_FILE_SIZE: u64 = 20 * 1024 * 1024; // 20 MB in bytes
+
+/// Upload limit in bytes, configurable with `MAX_FILE_SIZE`.
+pub fn max_file_size() -> u64 {
+    env::var("MAX_FILE_SIZE")
+        .ok()
+        .and_then(|value| value.parse::<u64>().ok())
+        .filter(|value| *value > 0)
+        .unwrap_or(DEFAULT_MAX_FILE_SIZE)
+}
+
+/// Request body limit, leaves room for base64 encoded Aqua JSON imports of a maximum size file.
+pub fn max_request_body_size() -> usize {
+    (max_file_size() * 4 / 3 + 1024 * 1024) as usize
+}
 
 #[derive(Debug)]
 pub enum UploadError {
-    FileTooLarge(u32),
+    FileTooLarge(u64),
     MissingAccount,
     MissingFile,
     MultipartError(String),
+    StorageError(String),
+}
+
+impl UploadError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            UploadError::FileTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            UploadError::MissingAccount | UploadError::MissingFile => {
+                StatusCode::PRECONDITION_FAILED
+            }
+            UploadError::MultipartError(_) => StatusCode::BAD_REQUEST,
+            UploadError::StorageError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl fmt::Display for UploadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UploadError::FileTooLarge(limit) => {
+                write!(f, "File size exceeds maximum of {} bytes", limit)
+            }
+            UploadError::MissingAccount => write!(f, "Account field is required"),
+            UploadError::MissingFile => write!(f, "File is required"),
+            UploadError::MultipartError(msg) => write!(f, "{}", msg),
+            UploadError::StorageError(msg) => write!(f, "{}", msg),
+        }
+    }
 }
 
 impl IntoResponse for UploadError {
     fn into_response(self) -> Response {
-        match self {
-            UploadError::FileTooLarge(size) => (
-                StatusCode::PRECONDITION_FAILED,
-                format!(
-                    "File size {} bytes exceeds maximum of {} bytes",
-                    size, MAX_FILE_SIZE
-                ),
-            )
-                .into_response(),
-            UploadError::MissingAccount => (
-                StatusCode::PRECONDITION_FAILED,
-                "Account field is required".to_string(),
-            )
-                .into_response(),
-            UploadError::MissingFile => (
-                StatusCode::PRECONDITION_FAILED,
-                "File is required".to_string(),
-            )
-                .into_response(),
-            UploadError::MultipartError(msg) => (StatusCode::BAD_REQUEST, msg).into_response(),
+        (self.status_code(), self.to_string()).into_response()
+    }
+}
+
+/// A multipart file written to a temporary upload while it was hashed. The temporary file is
+/// deleted when the upload is dropped without being persisted, on every early return.
+struct StreamedUpload {
+    path: TempPath,
+    file_hash: Hash,
+    /// Hex SHA-256 of the file, how v1.3 chains reference it.
+    sha256: String,
+    size: u64,
+}
+
+/// Streams `field` to disk chunk by chunk, feeding the SHA3-512 and SHA-256 file hashers as it goes.
+/// Stops with `UploadError::FileTooLarge` as soon as more than `limit` bytes arrived.
+async fn stream_upload(mut field: Field<'_>, limit: u64) -> Result<StreamedUpload, UploadError> {
+    let path = temp_upload().map_err(UploadError::StorageError)?;
+    let (file_hash, sha256, size) = write_upload(&mut field, &path, limit).await?;
+
+    Ok(StreamedUpload {
+        path,
+        file_hash,
+        sha256,
+        size,
+    })
+}
+
+async fn write_upload(
+    field: &mut Field<'_>,
+    path: &std::path::Path,
+    limit: u64,
+) -> Result<(Hash, String, u64), UploadError> {
+    let file = File::create(path)
+        .await
+        .map_err(|e| UploadError::StorageError(format!("Error creating upload: {}", e)))?;
+    let mut writer = BufWriter::new(file);
+    let mut file_hasher = Sha3_512::default();
+    let mut sha256_hasher = Sha256::default();
+    let mut size: u64 = 0;
+
+    while let Some(chunk) = field
+        .chunk()
+        .await
+        .map_err(|e| UploadError::MultipartError(format!("Failed to read file bytes: {}", e)))?
+    {
+        size += chunk.len() as u64;
+        if size > limit {
+            return Err(UploadError::FileTooLarge(limit));
         }
+        file_hasher.update(&chunk);
+        sha256_hasher.update(&chunk);
+        writer
+            .write_all(&chunk)
+            .await
+            .map_err(|e| UploadError::StorageError(format!("Error writing upload: {}", e)))?;
     }
+
+    writer
+        .flush()
+        .await
+        .map_err(|e| UploadError::StorageError(format!("Error writing upload: {}", e)))?;
+
+    Ok((
+        Hash::from(file_hasher.finalize()),
+        hex::encode(sha256_hasher.finalize()),
+        size,
+    ))
 }
 
 pub async fn fetch_explorer_files(
     State(server_database): State<Db>,
-    headers: HeaderMap,
+    user: AuthenticatedUser,
+    Query(filter): Query<FileFilterInput>,
 ) -> (StatusCode, Json<ApiResponse>) {
     tracing::debug!("fetch_explorer_files");
     let mut log_data: Vec<String> = Vec::new();
@@ -106,19 +233,12 @@ pub async fn fetch_explorer_files(
         logs: log_data.clone(),
         file: None,
         files: Vec::new(),
+        head: None,
     };
 
     // Initialize an empty Vec to hold the result
     let mut pages: Vec<FileInfo> = Vec::new();
 
-    let user_address: Result<String, String> = match headers.get("metamask_address") {
-        Some(value) => match value.to_str() {
-            Ok(address) => Ok(address.to_string()), // Successfully extracted address
-            Err(_) => Err("Failed to parse the metamask_address header.".to_string()),
-        },
-        None => Err("metamask_address header not found.".to_string()), // Header not found
-    };
-
     let mut conn = match server_database.pool.get() {
         Ok(connection) => connection,
         Err(e) => {
@@ -129,71 +249,103 @@ pub async fn fetch_explorer_files(
                 logs: log_data.clone(),
                 file: None,
                 files: Vec::new(),
+                head: None,
             };
             return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
         }
     };
 
-    if user_address.is_ok() {
-        let page_data_result = fetch_all_pages_data_per_user(user_address.unwrap(), &mut conn);
-        if page_data_result.is_err() {
-            log_data.push("Failed data not found in database".to_string());
+    let page_data_result = fetch_all_pages_data_per_user(user.address, &mut conn);
+    if page_data_result.is_err() {
+        log_data.push("Failed data not found in database".to_string());
 
-            let res: ApiResponse = ApiResponse {
-                logs: log_data.clone(),
-                file: None,
-                files: Vec::new(),
-            };
-            return (StatusCode::NOT_FOUND, Json(res));
-        }
+        let res: ApiResponse = ApiResponse {
+            logs: log_data.clone(),
+            file: None,
+            files: Vec::new(),
+            head: None,
+        };
+        return (StatusCode::NOT_FOUND, Json(res));
+    }
 
-        let page_data = page_data_result.unwrap();
+    let mut page_data = page_data_result.unwrap();
 
-        for row in page_data {
-            pages.push(FileInfo {
-                id: row.id.try_into().unwrap(),
-                name: row.name,
-                extension: row.extension,
-                page_data: row.page_data,
-                mode: row.mode,
-                owner: row.owner,
-            });
+    // filters are answered from the revisions table, not by parsing every chain
+    let filtered_ids = match file_filter_ids(&filter, &mut conn) {
+        Ok(ids) => ids,
+        Err(e) => {
+            tracing::error!("Failed to filter files: {}", e);
+            res.logs.push(format!("Failed to filter files: {}", e));
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
         }
+    };
+    if let Some(ids) = filtered_ids {
+        page_data.retain(|row| ids.contains(&row.id));
+    }
 
-        if pages.is_empty() {
-            res.logs.push("No pages found".to_string());
-            return (StatusCode::OK, Json::from(res));
-        }
+    // listings return chains without file bytes, `/files/{id}/content` serves them
+    for row in page_data {
+        pages.push(FileInfo {
+            id: row.id.try_into().unwrap(),
+            name: row.name,
+            extension: row.extension,
+            page_data: row.page_data,
+            mode: row.mode,
+            owner: row.owner,
+        });
+    }
 
-        res.files = pages;
-        // Return the populated list of pages
+    if pages.is_empty() {
+        res.logs.push("No pages found".to_string());
         return (StatusCode::OK, Json::from(res));
-    } else {
-        log_data
-            .push("Unable to parse metamask address/ Metamask address not provided".to_string());
-        let res: ApiResponse = ApiResponse {
-            logs: log_data.clone(),
-            file: None,
-            files: Vec::new(),
-        };
-        return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
     }
 
-    (StatusCode::INTERNAL_SERVER_ERROR, Json::from(res))
+    res.files = pages;
+    // Return the populated list of pages
+    (StatusCode::OK, Json::from(res))
+}
+
+/// Ids of the files matching every given filter, `None` without filters.
+fn file_filter_ids(
+    filter: &FileFilterInput,
+    conn: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<Option<Vec<i32>>, String> {
+    let mut ids: Option<Vec<i32>> = None;
+    let mut narrow = |matching: Vec<i32>| {
+        ids = Some(match ids.take() {
+            Some(current) => current.into_iter().filter(|id| matching.contains(id)).collect(),
+            None => matching,
+        });
+    };
+
+    if let Some(signer) = filter.signer.as_deref().filter(|signer| !signer.is_empty()) {
+        narrow(fetch_file_ids_signed_by(signer, conn)?);
+    }
+    if let Some(network) = filter
+        .witness_network
+        .as_deref()
+        .filter(|network| !network.is_empty())
+    {
+        narrow(fetch_file_ids_witnessed_on(network, conn)?);
+    }
+    Ok(ids)
 }
 
 pub async fn explorer_file_verify_hash_upload(
     State(server_database): State<Db>,
+    _user: AuthenticatedUser,
     mut multipart: Multipart,
-) -> (StatusCode, Json<ApiResponse>) {
+) -> (StatusCode, Json<VerificationApiResponse>) {
     tracing::debug!("explorer_file_verify_hash_upload fn");
     let mut log_data: Vec<String> = Vec::new();
-    let mut res: ApiResponse = ApiResponse {
+    let mut res: VerificationApiResponse = VerificationApiResponse {
         logs: log_data,
-        file: None,
-        files: Vec::new(),
+        report: None,
     };
 
+    let mut document: Option<AquaDocument> = None;
+    let mut signature_modes = ExportedSignatureModes::new();
+
     while let Ok(Some(field)) = multipart.next_field().await {
         let name = match field.name() {
             Some(name) => name.to_string(),
@@ -232,96 +384,133 @@ pub async fn explorer_file_verify_hash_upload(
                 };
 
                 // Try to parse the file content into your struct
-                match serde_json::from_slice::<PageDataContainer<HashChain>>(&data) {
-                    Ok(parsed_data) => {
-                        tracing::debug!("file is okay fn");
-
-                        // verify t
-                        let mut matches = true;
-                        let mut failure_reason = "".to_string();
-                        let parsed_data_chain = parsed_data.pages.get(0).unwrap();
-                        // if the aqua json file has more than one revision compare the has
-                        // current has with the previous  metadata > verification_hash
-                        tracing::error!("Loop starts");
-                        if (parsed_data_chain.revisions.len() > 1) {
-                            tracing::error!("revisions more than 1 result");
-                            (matches, failure_reason) = check_if_page_data_revision_are_okay(
-                                parsed_data_chain.revisions.clone(),
-                            );
-                            tracing::error!("revisions are valied ? {}", matches);
-                        } else {
-                            // let rev  = parsed_data_chain.revisions.get(0).unwrap();
-                            // let hash =  compute_content_hash(rev);
-
-                            let (verification_hash, revision) = parsed_data_chain
-                                .revisions
-                                .first()
-                                .expect("No revisions found");
-
-                            // Step 3: Recompute the content hash for the revision
-                            let recomputed_content_hash = compute_content_hash(&revision.content);
-
-                            match recomputed_content_hash {
-                                Ok(data) => {
-                                    // Step 4: Compare the recomputed content hash with the stored content hash
-
-                                    let contnent_hash_str =
-                                        format!("{:#?}", revision.content.content_hash);
-                                    let data_str = format!("{:#?}", revision.content.content_hash);
-                                    // data_str,
-                                    // contnent_hash_str
-
-                                    tracing::error!(
-                                        " returd conetnet is   {} \n  my son content hash is {} \n",
-                                        data_str,
-                                        contnent_hash_str
-                                    );
-                                    if data == revision.content.content_hash {
-                                        matches = true;
-                                    } else {
-                                        failure_reason = format!(
-                                            "a hash is not valid : {:#?}",
-                                            revision.content.content_hash
-                                        )
-                                    }
-                                    //revision.content.content_hash;
-                                }
-                                Err(err) => {
-                                    tracing::error!("Error compute_content_hash {} ", err);
-                                    res.logs.push("AQUA Chain valid".to_string());
-                                    return (StatusCode::OK, Json(res));
-                                }
-                            }
-                        }
-                        tracing::error!("Loop ends");
-                        return if matches {
-                            tracing::error!("Returning true");
-                            res.logs.push("AQUA Chain valid".to_string());
-                            (StatusCode::OK, Json(res))
-                        } else {
-                            tracing::error!("Returning false");
-                            res.logs.push(failure_reason);
-                            (StatusCode::BAD_REQUEST, Json(res))
-                        };
+                document = match AquaDocument::parse(&data) {
+                    Ok(document) => Some(document),
+                    Err(e) => {
+                        tracing::error!("Failed to parse JSON: {}", e);
+                        res.logs.push(e);
+                        return (StatusCode::BAD_REQUEST, Json(res));
                     }
+                };
+                tracing::debug!("file is okay fn");
+            }
+            // exported next to the chain by /files/{id}/signature_modes
+            "signature_modes" => {
+                signature_modes = match signature_modes_field(field).await {
+                    Ok(modes) => modes,
                     Err(e) => {
-                        tracing::error!("Failed to parse JSON: {:?}", e);
-                        res.logs.push(format!("Failed to parse JSON: {:?}", e));
+                        tracing::error!("{}", e);
+                        res.logs.push(e);
                         return (StatusCode::BAD_REQUEST, Json(res));
                     }
-                }
+                };
             }
             _ => continue,
         }
     }
 
     // Return an error if no file was found
-    (StatusCode::BAD_REQUEST, Json(res))
+    let document = match document {
+        Some(document) => document,
+        None => return (StatusCode::BAD_REQUEST, Json(res)),
+    };
+
+    let mut conn = match server_database.pool.get() {
+        Ok(connection) => connection,
+        Err(e) => {
+            tracing::error!("Failed to get database connection: {}", e);
+            res.logs.push("Failed to get database connection".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    // EIP-712 signatures are checked against the typed data recorded when they were made,
+    // signatures recorded elsewhere against the uploaded signature modes
+    let schemes = match load_document_signature_schemes(&document, &mut conn) {
+        Ok(schemes) => with_exported_modes(schemes, &signature_modes),
+        Err(e) => {
+            tracing::error!("Failed to load signature modes: {}", e);
+            res.logs.push(format!("Failed to load signature modes: {}", e));
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    let report = match document {
+        AquaDocument::V1_2(parsed_data) => {
+            let parsed_data_chain = match parsed_data.pages.get(0) {
+                Some(chain) => chain,
+                None => {
+                    res.logs.push("Aqua data erorr first chain not found".to_string());
+                    return (StatusCode::BAD_REQUEST, Json(res));
+                }
+            };
+
+            // signatures of smart-contract wallets are confirmed with the wallet contract
+            let contract_signatures =
+                match confirm_contract_signatures(parsed_data_chain, &schemes).await {
+                    Ok(confirmed) => confirmed,
+                    Err(e) => {
+                        tracing::error!("Failed to check wallet contracts: {}", e);
+                        res.logs.push(format!("Failed to check wallet contracts: {}", e));
+                        return (StatusCode::BAD_GATEWAY, Json(res));
+                    }
+                };
+
+            let context = SignatureContext {
+                schemes,
+                contract_signatures,
+            };
+            verify_hash_chain_with_context(parsed_data_chain, &context)
+        }
+        AquaDocument::V1_3(chain) => {
+            // upgraded signatures keep the scheme recorded under their v1.2 hash
+            let mut report = v1_3::verify_chain(&chain, &schemes);
+            // link revisions point at chains stored here
+            if let Err(e) = check_links(&chain, &mut report, &mut conn).await {
+                tracing::error!("Failed to check linked chains: {}", e);
+                res.logs.push(format!("Failed to check linked chains: {}", e));
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+            }
+            report
+        }
+    };
+
+    let status = if report.valid {
+        res.logs.push("AQUA Chain valid".to_string());
+        StatusCode::OK
+    } else {
+        res.logs.extend(report.failures());
+        StatusCode::BAD_REQUEST
+    };
+    res.report = Some(report);
+    (status, Json(res))
+}
+
+/// Records the signature modes uploaded with an imported chain, see `import_signature_modes`.
+async fn record_imported_signature_modes(
+    server_database: &Db,
+    document: &AquaDocument,
+    signature_modes: &ExportedSignatureModes,
+) -> Result<usize, String> {
+    let mut conn = server_database
+        .pool
+        .get()
+        .map_err(|e| format!("Failed to get database connection: {}", e))?;
+    import_signature_modes(document, signature_modes, &mut conn).await
+}
+
+/// Reads the `signature_modes` exported next to a chain by `explorer_file_signature_modes`.
+async fn signature_modes_field(field: Field<'_>) -> Result<ExportedSignatureModes, String> {
+    let bytes = field
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to read signature modes: {}", e))?;
+    serde_json::from_slice(&bytes).map_err(|e| format!("Failed to parse signature modes: {}", e))
 }
 
 pub async fn explorer_import_aqua_chain(
     State(server_database): State<Db>,
-    headers: HeaderMap,
+    user: AuthenticatedUser,
     mut multipart: Multipart,
 ) -> (StatusCode, Json<ApiResponse>) {
     tracing::debug!("explorer_import_aqua_chain fn");
@@ -330,35 +519,19 @@ pub async fn explorer_import_aqua_chain(
         logs: log_data,
         file: None,
         files: Vec::new(),
+        head: None,
     };
 
-    // Extract the 'metamask_address' header
-    let metamask_address = match headers.get("metamask_address") {
-        Some(value) => match value.to_str() {
-            Ok(key) => key,
-            Err(err) => {
-                tracing::error!("headers get error {} ", err);
-                // return (StatusCode::BAD_REQUEST,  Json(json!({"error": "Invalid metamask_address header"})))
-
-                res.logs
-                    .push(format!("Error: Meta mask public key  error: {:?}", err));
-                return (StatusCode::BAD_REQUEST, Json(res));
-            }
-        },
-        None => {
-            tracing::debug!("metamask_address header missing ");
-            // return (StatusCode::BAD_REQUEST, Json(json!({"error": "metamask_address header missing"})))
-            res.logs
-                .push("Error: Meta mask public key  missing".to_string());
-            return (StatusCode::BAD_REQUEST, Json(res));
-        }
-    };
+    let metamask_address = user.address.as_str();
 
     let mut account: Option<String> = None;
     let mut aqua_json: Option<PageDataContainer<HashChain>> = None;
+    let mut aqua_chain_v1_3: Option<v1_3::AquaChainV13> = None;
+    let mut signature_modes = ExportedSignatureModes::new();
+    let mut file_bytes: Option<Vec<u8>> = None;
 
-    // Process only two fields: account and file
-    for _ in 0..2 {
+    // Process only four fields: account, file, content and signature_modes
+    for _ in 0..4 {
         let field = match multipart.next_field().await {
             Ok(Some(field)) => field,
             Ok(None) => break,
@@ -402,16 +575,38 @@ pub async fn explorer_import_aqua_chain(
                     }
                 };
 
-                // Parse JSON content into AquaData struct
-                aqua_json =
-                    match serde_json::from_slice::<PageDataContainer<HashChain>>(&file_content) {
-                        Ok(data) => Some(data),
-                        Err(e) => {
-                            tracing::error!("Failed to parse JSON: {}", e);
-                            res.logs.push(format!("Failed to parse JSON: {}", e));
-                            return (StatusCode::BAD_REQUEST, Json(res));
-                        }
-                    };
+                // Parse JSON content into AquaData struct, either protocol version
+                match AquaDocument::parse(&file_content) {
+                    Ok(AquaDocument::V1_2(data)) => aqua_json = Some(data),
+                    Ok(AquaDocument::V1_3(chain)) => aqua_chain_v1_3 = Some(chain),
+                    Err(e) => {
+                        tracing::error!("Failed to parse JSON: {}", e);
+                        res.logs.push(e);
+                        return (StatusCode::BAD_REQUEST, Json(res));
+                    }
+                }
+            }
+            // the original file of a v1.3 chain, which keeps only its hash
+            "content" => {
+                file_bytes = match field.bytes().await {
+                    Ok(content) => Some(content.to_vec()),
+                    Err(e) => {
+                        tracing::error!("Failed to read file content: {}", e);
+                        res.logs.push(format!("Failed to read file content: {}", e));
+                        return (StatusCode::BAD_REQUEST, Json(res));
+                    }
+                };
+            }
+            // exported next to the chain by /files/{id}/signature_modes
+            "signature_modes" => {
+                signature_modes = match signature_modes_field(field).await {
+                    Ok(modes) => modes,
+                    Err(e) => {
+                        tracing::error!("{}", e);
+                        res.logs.push(e);
+                        return (StatusCode::BAD_REQUEST, Json(res));
+                    }
+                };
             }
             _ => {
                 tracing::warn!("Unexpected field: {}", name);
@@ -429,22 +624,145 @@ pub async fn explorer_import_aqua_chain(
         }
     };
 
-    // Verify we have both account and file
-    let aqua_json = match aqua_json {
-        Some(acc) => acc,
-        None => {
+    let document = match (aqua_chain_v1_3, aqua_json) {
+        (Some(chain), _) => AquaDocument::V1_3(chain),
+        (None, Some(data)) => AquaDocument::V1_2(data),
+        (None, None) => {
             tracing::error!("Aqua JSON data erorr");
             res.logs.push("Aqua JSON data erorr".to_string());
             return (StatusCode::BAD_REQUEST, Json(res));
         }
     };
 
-    // tracing::debug!(
-    //     "Processing aqua  upload Account {} - File data {:#?} ",
-    //     account,
-    //     aqua_json,
-    // );
+    let imported = ImportedChain {
+        document,
+        file_bytes,
+        signature_modes,
+    };
+    import_chain(&server_database, metamask_address, imported, res).await
+}
+
+/// A chain taken from an import request with what came along with it.
+struct ImportedChain {
+    document: AquaDocument,
+    /// The original file, when the request carried it next to the chain.
+    file_bytes: Option<Vec<u8>>,
+    signature_modes: ExportedSignatureModes,
+}
+
+/// Stores an imported chain of either protocol version as a new page of `owner`, shared by
+/// the JSON import and the bundle upload.
+async fn import_chain(
+    server_database: &Db,
+    owner: &str,
+    imported: ImportedChain,
+    mut res: ApiResponse,
+) -> (StatusCode, Json<ApiResponse>) {
+    // EIP-712 signatures only verify with the typed data they were made over
+    if !imported.signature_modes.is_empty() {
+        if let Err(e) = record_imported_signature_modes(
+            server_database,
+            &imported.document,
+            &imported.signature_modes,
+        )
+        .await
+        {
+            tracing::error!("Failed to record signature modes: {}", e);
+            res.logs.push(format!("Failed to record signature modes: {}", e));
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    }
+
+    match imported.document {
+        AquaDocument::V1_2(aqua_json) => insert_v1_2_chain(server_database, owner, aqua_json, res),
+        AquaDocument::V1_3(chain) => {
+            let file_hash = match chain.checked_file_hash() {
+                Ok(file_hash) => file_hash,
+                Err(e) => {
+                    res.logs.push(format!("Aqua data erorr {}", e));
+                    return (StatusCode::BAD_REQUEST, Json(res));
+                }
+            };
+
+            let mut conn = match server_database.pool.get() {
+                Ok(connection) => connection,
+                Err(e) => {
+                    tracing::error!("Failed to get database connection: {}", e);
+                    res.logs.push("Failed to get database connection".to_string());
+                    return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+                }
+            };
+
+            // only chains whose every revision verifies are taken in
+            let schemes =
+                match load_signature_schemes_for(&chain.legacy_signature_hashes(), &mut conn) {
+                    Ok(schemes) => with_exported_modes(schemes, &imported.signature_modes),
+                    Err(e) => {
+                        tracing::error!("Failed to load signature modes: {}", e);
+                        res.logs.push(format!("Failed to load signature modes: {}", e));
+                        return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+                    }
+                };
+            let report = v1_3::verify_chain(&chain, &schemes);
+            if !report.valid {
+                res.logs.push("AQUA Chain invalid".to_string());
+                res.logs.extend(report.failures());
+                return (StatusCode::BAD_REQUEST, Json(res));
+            }
+
+            // the file of a v1.3 chain is only stored as a blob, which has to come with the
+            // chain unless the caller already has it
+            match &imported.file_bytes {
+                Some(bytes) => {
+                    if v1_3::sha256_hex(bytes) != file_hash {
+                        res.logs
+                            .push(format!("file data does not match file hash {}", file_hash));
+                        return (StatusCode::BAD_REQUEST, Json(res));
+                    }
+                    if let Err(e) = store_blob_sha256(bytes) {
+                        tracing::error!("Failed to store file: {}", e);
+                        res.logs.push(format!("Failed to store file: {}", e));
+                        return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+                    }
+                }
+                None => match owns_blob(owner, &file_hash, &mut conn) {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        res.logs.push(format!(
+                            "Aqua data erorr file {} is missing, send it as content or in a bundle",
+                            file_hash
+                        ));
+                        return (StatusCode::BAD_REQUEST, Json(res));
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to look up file: {}", e);
+                        res.logs.push(format!("Failed to look up file: {}", e));
+                        return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+                    }
+                },
+            }
+            insert_v1_3_chain(server_database, owner, chain, None, res)
+        }
+    }
+}
+
+/// Whether `owner` has a chain of the file `file_hash` with its blob stored, so an import may
+/// reference it instead of carrying the bytes. Blobs of other users are never handed out.
+fn owns_blob(
+    owner: &str,
+    file_hash: &str,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<bool, String> {
+    Ok(owner_has_file(owner, file_hash, db_connection)? && blob_path_by_key(file_hash)?.is_file())
+}
 
+/// Stores a v1.2 chain as a new page, its file name and type come from the genesis revision.
+fn insert_v1_2_chain(
+    server_database: &Db,
+    owner: &str,
+    aqua_json: PageDataContainer<HashChain>,
+    mut res: ApiResponse,
+) -> (StatusCode, Json<ApiResponse>) {
     let mut mode = "private".to_string();
     let file_mode = env::var("FILE_MODE").unwrap_or_default();
 
@@ -503,6 +821,47 @@ pub async fn explorer_import_aqua_chain(
         .unwrap()
         .filename;
 
+    // the file comes inline, or is one of the owner's files already stored here
+    let genesis_file_hash = genesis_revision.unwrap().content.content.file_hash;
+    let genesis_data: Vec<u8> = genesis_revision
+        .unwrap()
+        .content
+        .file
+        .as_ref()
+        .map(|file| {
+            let bytes: &[u8] = file.data.as_ref();
+            bytes.to_vec()
+        })
+        .unwrap_or_default();
+    if genesis_data.is_empty() {
+        let owned = server_database
+            .pool
+            .get()
+            .map_err(|e| format!("Failed to get database connection: {}", e))
+            .and_then(|mut conn| owns_blob(owner, &genesis_file_hash.to_string(), &mut conn));
+        match owned {
+            Ok(true) => {}
+            Ok(false) => {
+                res.logs.push(format!(
+                    "Aqua data data erorr genesis revision does not contain the data of file {}",
+                    genesis_file_hash
+                ));
+                return (StatusCode::BAD_REQUEST, Json(res));
+            }
+            Err(e) => {
+                tracing::error!("Failed to look up file: {}", e);
+                res.logs.push(format!("Failed to look up file: {}", e));
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+            }
+        }
+    } else if hash_file_bytes(&genesis_data) != genesis_file_hash {
+        res.logs.push(format!(
+            "Aqua data data erorr file data does not match file hash {}",
+            genesis_file_hash
+        ));
+        return (StatusCode::BAD_REQUEST, Json(res));
+    }
+
     let path = std::path::Path::new(&file_name);
     let mut content_type: String = String::from("");
 
@@ -552,8 +911,9 @@ pub async fn explorer_import_aqua_chain(
         };
     }
 
-    // Convert struct to JSON string
-    let json_string = match serde_json::to_string(&aqua_json) {
+    // Convert struct to JSON string, the file bytes go to the blob store
+    let mut aqua_json = aqua_json;
+    let json_string = match serialize_for_storage(&mut aqua_json) {
         Ok(json) => json,
         Err(e) => {
             tracing::error!("Failed to serialize page data: {}", e);
@@ -571,7 +931,7 @@ pub async fn explorer_import_aqua_chain(
         extension: content_type,
         page_data: json_string,
         mode,
-        owner: metamask_address.to_string(),
+        owner: owner.to_string(),
         is_shared: false,
         created_at: datetime_string,
     };
@@ -599,68 +959,132 @@ pub async fn explorer_import_aqua_chain(
         id: insert_result.unwrap(), //record.id,
         name: db_data_model.name,
         extension: db_data_model.extension,
-        page_data: db_data_model.page_data,
+        page_data: page_data_for_response(db_data_model.page_data),
         mode: db_data_model.mode,
         owner: db_data_model.owner.to_string(),
     };
     res.file = Some(file_info);
     return (StatusCode::CREATED, Json(res));
 }
-
-pub async fn explorer_aqua_file_upload(
-    State(server_database): State<Db>,
-    headers: HeaderMap,
-    mut multipart: Multipart,
+/// Stores a v1.3 chain as a new page. `content_type` falls back to the file name's extension.
+fn insert_v1_3_chain(
+    server_database: &Db,
+    owner: &str,
+    chain: v1_3::AquaChainV13,
+    content_type: Option<String>,
+    mut res: ApiResponse,
 ) -> (StatusCode, Json<ApiResponse>) {
-    tracing::debug!("explorer_aqua_file_upload fn");
-    let mut log_data: Vec<String> = Vec::new();
-    let mut res: ApiResponse = ApiResponse {
-        logs: log_data,
-        file: None,
-        files: Vec::new(),
-    };
-
-    // Extract the 'metamask_address' header
-    let metamask_address = match headers.get("metamask_address") {
-        Some(value) => match value.to_str() {
-            Ok(key) => key,
-            Err(err) => {
-                tracing::error!("headers get error {} ", err);
-                // return (StatusCode::BAD_REQUEST,  Json(json!({"error": "Invalid metamask_address header"})))
+    if let Err(e) = chain.ordered().and_then(|_| chain.checked_file_hash()) {
+        tracing::error!("Aqua data erorr {}", e);
+        res.logs.push(format!("Aqua data erorr {}", e));
+        return (StatusCode::BAD_REQUEST, Json(res));
+    }
 
-                res.logs
-                    .push(format!("Error: Meta mask public key  error: {:?}", err));
-                return (StatusCode::BAD_REQUEST, Json(res));
-            }
-        },
+    let file_name = match chain.file_name() {
+        Some(name) => name,
         None => {
-            tracing::debug!("metamask_address header missing ");
-            // return (StatusCode::BAD_REQUEST, Json(json!({"error": "metamask_address header missing"})))
             res.logs
-                .push("Error: Meta mask public key  missing".to_string());
+                .push("Aqua data erorr file_index has no entry for the genesis revision".to_string());
             return (StatusCode::BAD_REQUEST, Json(res));
         }
     };
+    let content_type = content_type
+        .or_else(|| get_content_type(&file_name))
+        .unwrap_or_else(|| "unknown".to_string());
 
-    let mut account: Option<String> = None;
-    let mut aqua_json: Option<PageDataContainer<HashChain>> = None;
+    let mut document = AquaDocument::V1_3(chain);
+    let json_string = match document.to_storage() {
+        Ok(json) => json,
+        Err(e) => {
+            tracing::error!("Failed to serialize page data: {}", e);
+            res.logs.push(e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
 
-    // Process only two fields: account and file
-    for _ in 0..2 {
-        let field = match multipart.next_field().await {
-            Ok(Some(field)) => field,
-            Ok(None) => break,
-            Err(e) => {
-                tracing::error!("Multipart error: {}", e);
-                res.logs.push(format!("Multipart error: {}", e));
-                return (StatusCode::BAD_REQUEST, Json(res));
-            }
-        };
+    let mut mode = "private".to_string();
+    let file_mode = env::var("FILE_MODE").unwrap_or_default();
+    if !file_mode.is_empty() {
+        mode = file_mode;
+    }
 
-        let name = match field.name() {
-            Some(name) => name.to_string(),
-            None => {
-                tracing::error!("Field name missing");
+    let db_data_model = NewPagesTable {
+        name: file_name,
+        extension: content_type,
+        page_data: json_string,
+        mode,
+        owner: owner.to_string(),
+        is_shared: false,
+        created_at: Utc::now().naive_utc().format("%Y-%m-%d %H:%M:%S").to_string(),
+    };
+
+    let mut conn = match server_database.pool.get() {
+        Ok(connection) => connection,
+        Err(e) => {
+            tracing::error!("Failed to get database connection: {}", e);
+            res.logs.push("Failed to get database connection".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    let id = match insert_page_data(db_data_model.clone(), &mut conn) {
+        Ok(id) => id,
+        Err(e) => {
+            tracing::error!("Failed to insert page: {}", e);
+            res.logs.push(format!("Failed to insert page: {}", e));
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    res.file = Some(FileInfo {
+        id,
+        name: db_data_model.name,
+        extension: db_data_model.extension,
+        page_data: page_data_for_response(db_data_model.page_data),
+        mode: db_data_model.mode,
+        owner: db_data_model.owner,
+    });
+    (StatusCode::CREATED, Json(res))
+}
+
+pub async fn explorer_aqua_file_upload(
+    State(server_database): State<Db>,
+    user: AuthenticatedUser,
+    mut multipart: Multipart,
+) -> (StatusCode, Json<ApiResponse>) {
+    tracing::debug!("explorer_aqua_file_upload fn");
+    let mut log_data: Vec<String> = Vec::new();
+    let mut res: ApiResponse = ApiResponse {
+        logs: log_data,
+        file: None,
+        files: Vec::new(),
+        head: None,
+    };
+
+    let metamask_address = user.address.as_str();
+
+    let mut account: Option<String> = None;
+    let mut aqua_json: Option<PageDataContainer<HashChain>> = None;
+    let mut aqua_chain_v1_3: Option<v1_3::AquaChainV13> = None;
+    let mut signature_modes = ExportedSignatureModes::new();
+    let mut file_bytes: Option<Vec<u8>> = None;
+
+    // Process only four fields: account, file, content and signature_modes
+    for _ in 0..4 {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break,
+            Err(e) => {
+                tracing::error!("Multipart error: {}", e);
+                res.logs.push(format!("Multipart error: {}", e));
+                return (StatusCode::BAD_REQUEST, Json(res));
+            }
+        };
+
+        let name = match field.name() {
+            Some(name) => name.to_string(),
+            None => {
+                tracing::error!("Field name missing");
                 res.logs.push("Field name missing".to_string());
                 return (StatusCode::BAD_REQUEST, Json(res));
             }
@@ -690,16 +1114,57 @@ pub async fn explorer_aqua_file_upload(
                     }
                 };
 
-                // Parse JSON content into AquaData struct
-                aqua_json =
-                    match serde_json::from_slice::<PageDataContainer<HashChain>>(&file_content) {
-                        Ok(data) => Some(data),
+                // A zip bundle carries the file next to the chain, its bytes are checked against file_hash
+                if is_bundle(&file_content) {
+                    let bundle = match read_bundle(&file_content) {
+                        Ok(bundle) => bundle,
                         Err(e) => {
-                            tracing::error!("Failed to parse JSON: {}", e);
-                            res.logs.push(format!("Failed to parse JSON: {}", e));
+                            tracing::error!("Failed to read bundle: {}", e);
+                            res.logs.push(format!("Failed to read bundle: {}", e));
                             return (StatusCode::BAD_REQUEST, Json(res));
                         }
                     };
+                    signature_modes.extend(bundle.signature_modes);
+                    file_bytes = Some(bundle.file_bytes);
+                    match bundle.document {
+                        AquaDocument::V1_2(data) => aqua_json = Some(data),
+                        AquaDocument::V1_3(chain) => aqua_chain_v1_3 = Some(chain),
+                    }
+                    continue;
+                }
+
+                // Parse JSON content into AquaData struct, either protocol version
+                match AquaDocument::parse(&file_content) {
+                    Ok(AquaDocument::V1_2(data)) => aqua_json = Some(data),
+                    Ok(AquaDocument::V1_3(chain)) => aqua_chain_v1_3 = Some(chain),
+                    Err(e) => {
+                        tracing::error!("Failed to parse JSON: {}", e);
+                        res.logs.push(e);
+                        return (StatusCode::BAD_REQUEST, Json(res));
+                    }
+                }
+            }
+            // the original file of a v1.3 chain, which keeps only its hash
+            "content" => {
+                file_bytes = match field.bytes().await {
+                    Ok(content) => Some(content.to_vec()),
+                    Err(e) => {
+                        tracing::error!("Failed to read file content: {}", e);
+                        res.logs.push(format!("Failed to read file content: {}", e));
+                        return (StatusCode::BAD_REQUEST, Json(res));
+                    }
+                };
+            }
+            // exported next to the chain by /files/{id}/signature_modes
+            "signature_modes" => {
+                signature_modes = match signature_modes_field(field).await {
+                    Ok(modes) => modes,
+                    Err(e) => {
+                        tracing::error!("{}", e);
+                        res.logs.push(e);
+                        return (StatusCode::BAD_REQUEST, Json(res));
+                    }
+                };
             }
             _ => {
                 tracing::warn!("Unexpected field: {}", name);
@@ -717,187 +1182,27 @@ pub async fn explorer_aqua_file_upload(
         }
     };
 
-    // Verify we have both account and file
-    let aqua_json = match aqua_json {
-        Some(acc) => acc,
-        None => {
+    let document = match (aqua_chain_v1_3, aqua_json) {
+        (Some(chain), _) => AquaDocument::V1_3(chain),
+        (None, Some(data)) => AquaDocument::V1_2(data),
+        (None, None) => {
             tracing::error!("Aqua JSON data erorr");
             res.logs.push("Aqua JSON data erorr".to_string());
             return (StatusCode::BAD_REQUEST, Json(res));
         }
     };
 
-    // tracing::debug!(
-    //     "Processing aqua  upload Account {} - File data {:#?} ",
-    //     account,
-    //     aqua_json,
-    // );
-
-    let mut mode = "private".to_string();
-    let file_mode = env::var("FILE_MODE").unwrap_or_default();
-
-    if !file_mode.is_empty() {
-        mode = file_mode;
-    }
-
-    let start = SystemTime::now();
-    let timestamp = start
-        .duration_since(UNIX_EPOCH)
-        .expect("Time went backwards")
-        .as_secs();
-    println!("Current Unix timestamp: {}", timestamp);
-
-    let mut file_name = format!("{}", timestamp);
-
-    let chain: Option<&HashChain> = aqua_json.pages.get(0);
-
-    if (chain.is_none()) {
-        // tracing::error!("Aqua JSON data erorr first chain not found");
-        res.logs
-            .push("Aqua data data erorr first chain not found".to_string());
-        return (StatusCode::BAD_REQUEST, Json(res));
-    }
-
-    let genesis_revision = chain
-        .unwrap()
-        .revisions
-        .iter()
-        .find_map(|(hash, revision)| {
-            if hash.to_string() == chain.unwrap().genesis_hash {
-                tracing::info!("Found geneisis revision");
-                Some(revision)
-            } else {
-                tracing::error!("genesis revision not found");
-                None
-            }
-        });
-    if (genesis_revision.is_none()) {
-        // tracing::error!("Aqua JSON data erorr genesis revision not found");
-        res.logs
-            .push("Aqua data data erorr genesis revision not found".to_string());
-        return (StatusCode::BAD_REQUEST, Json(res));
-    }
-    if (genesis_revision.unwrap().content.file.is_none()) {
-        tracing::error!("Aqua JSON data erorr genesis revision does not contain file info");
-        res.logs
-            .push("Aqua data data erorr genesis revision does not contain file info".to_string());
-        return (StatusCode::BAD_REQUEST, Json(res));
-    }
-    let file_name = genesis_revision
-        .unwrap()
-        .content
-        .file
-        .clone()
-        .unwrap()
-        .filename;
-
-    let path = std::path::Path::new(&file_name);
-    let mut content_type: String = String::from("");
-
-    // Check if the file has an extension
-    if (path.extension().is_some()) {
-        tracing::error!("Aqua JSON generating file type from extension");
-        res.logs
-            .push("Aqua JSON generating file type from extension".to_string());
-
-        match get_content_type(&file_name) {
-            Some(data) => {
-                content_type = data;
-            }
-            None => {
-                content_type = "unknown".to_string();
-            }
-        }
-    } else {
-        tracing::error!("Aqua JSON generating file type from content bytes");
-        res.logs
-            .push("Aqua data data erorr genesis type from content bytes".to_string());
-
-        let file_data_info = get_file_info(
-            genesis_revision
-                .unwrap()
-                .content
-                .file
-                .clone()
-                .unwrap()
-                .data
-                .to_string(),
-        );
-
-        content_type = match file_data_info {
-            Ok(data) => {
-                tracing::error!(
-                    "file type found   {} the gerenal result is  {:#?}",
-                    data.file_type,
-                    data
-                );
-                data.file_type
-            }
-            Err(err) => {
-                tracing::error!("Failed infer file type  {}", err);
-                "unknown".to_string()
-            }
-        };
-    }
-
-    // Convert struct to JSON string
-    let json_string = match serde_json::to_string(&aqua_json) {
-        Ok(json) => json,
-        Err(e) => {
-            tracing::error!("Failed to serialize page data: {}", e);
-            res.logs
-                .push(format!("Failed to serialize page data: {}", e));
-            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
-        }
-    };
-
-    let naive_datetime: NaiveDateTime = Utc::now().naive_utc();
-    let datetime_string = naive_datetime.format("%Y-%m-%d %H:%M:%S").to_string();
-
-    let db_data_model = NewPagesTable {
-        name: file_name,
-        extension: content_type,
-        page_data: json_string,
-        mode,
-        owner: metamask_address.to_string(),
-        is_shared: false,
-        created_at: datetime_string,
-    };
-
-    let mut conn = match server_database.pool.get() {
-        Ok(connection) => connection,
-        Err(e) => {
-            res.logs
-                .push("Failed to get database connection".to_string());
-
-            println!("Error Fetching connection {:#?}", res);
-            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
-        }
-    };
-
-    let insert_result = insert_page_data(db_data_model.clone(), &mut conn);
-    if insert_result.is_err() {
-        let err = insert_result.err().unwrap();
-        tracing::error!("Failed to insert page: {}", err);
-        res.logs.push(format!("Failed to insert page: {}", err));
-        return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
-    }
-
-    let file_info = FileInfo {
-        id: insert_result.unwrap(), //record.id,
-        name: db_data_model.name,
-        extension: db_data_model.extension,
-        page_data: db_data_model.page_data,
-        mode: db_data_model.mode,
-        owner: db_data_model.owner.to_string(),
+    let imported = ImportedChain {
+        document,
+        file_bytes,
+        signature_modes,
     };
-    res.file = Some(file_info);
-    return (StatusCode::CREATED, Json(res));
+    import_chain(&server_database, metamask_address, imported, res).await
 }
 
 pub async fn explorer_file_upload(
     State(server_database): State<Db>,
-    headers: HeaderMap,
+    user: AuthenticatedUser,
     mut multipart: Multipart,
 ) -> (StatusCode, Json<ApiResponse>) {
     tracing::debug!("explorer_file_upload fn");
@@ -907,29 +1212,10 @@ pub async fn explorer_file_upload(
         logs: log_data,
         file: None,
         files: Vec::new(),
+        head: None,
     };
 
-    // Extract the 'metamask_address' header
-    let metamask_address = match headers.get("metamask_address") {
-        Some(value) => match value.to_str() {
-            Ok(key) => key,
-            Err(err) => {
-                tracing::error!("headers get error {} ", err);
-                // return (StatusCode::BAD_REQUEST,  Json(json!({"error": "Invalid metamask_address header"})))
-
-                res.logs
-                    .push(format!("Error: Meta mask public key  error: {:?}", err));
-                return (StatusCode::BAD_REQUEST, Json(res));
-            }
-        },
-        None => {
-            tracing::debug!("metamask_address header missing ");
-            // return (StatusCode::BAD_REQUEST, Json(json!({"error": "metamask_address header missing"})))
-            res.logs
-                .push("Error: Meta mask public key  missing".to_string());
-            return (StatusCode::BAD_REQUEST, Json(res));
-        }
-    };
+    let metamask_address = user.address.as_str();
 
     let mut account = None;
     let mut file_info = None;
@@ -986,16 +1272,16 @@ pub async fn explorer_file_upload(
                     }
                 };
 
-                let body_bytes = match field.bytes().await {
-                    Ok(bytes) => bytes.to_vec(),
+                let upload = match stream_upload(field, max_file_size()).await {
+                    Ok(upload) => upload,
                     Err(e) => {
-                        tracing::error!("Failed to read file bytes: {}", e);
-                        res.logs.push(format!("Failed to read file bytes: {}", e));
-                        return (StatusCode::BAD_REQUEST, Json(res));
+                        tracing::error!("Failed to receive file: {}", e);
+                        res.logs.push(format!("Error : {}", e));
+                        return (e.status_code(), Json(res));
                     }
                 };
 
-                let file_size: u32 = match body_bytes.len().try_into() {
+                let file_size: u32 = match upload.size.try_into() {
                     Ok(size) => size,
                     Err(_) => {
                         tracing::error!("File size exceeds u32::MAX");
@@ -1004,16 +1290,7 @@ pub async fn explorer_file_upload(
                     }
                 };
 
-                if file_size > MAX_FILE_SIZE {
-                    tracing::error!("File size {} exceeds maximum allowed size", file_size);
-                    res.logs.push(format!(
-                        "File size {} exceeds maximum allowed size",
-                        file_size
-                    ));
-                    return (StatusCode::BAD_REQUEST, Json(res));
-                }
-
-                file_info = Some((file_name, content_type, body_bytes, file_size));
+                file_info = Some((file_name, content_type, upload, file_size));
             }
             _ => {
                 tracing::warn!("Unexpected field: {}", name);
@@ -1030,7 +1307,7 @@ pub async fn explorer_file_upload(
             return (StatusCode::BAD_REQUEST, Json(res));
         }
     };
-    let (file_name, content_type, body_bytes, file_size) = match file_info {
+    let (file_name, content_type, upload, file_size) = match file_info {
         Some(info) => info,
         None => {
             tracing::error!("File information missing");
@@ -1046,10 +1323,38 @@ pub async fn explorer_file_upload(
         file_size
     );
 
-    let b64 = Base64::from(body_bytes);
-    let mut file_hasher = sha3::Sha3_512::default();
-    file_hasher.update(b64.clone());
-    let file_hash_current = Hash::from(file_hasher.finalize());
+    // new chains follow the owner's protocol version
+    let profile = match server_database.pool.get() {
+        Ok(mut conn) => fetch_user_profile(metamask_address.to_string(), &mut conn).ok(),
+        Err(e) => {
+            tracing::error!("Failed to get database connection: {}", e);
+            res.logs.push("Failed to get database connection".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+    if ProtocolVersion::for_profile(profile.as_ref()) == ProtocolVersion::V1_3 {
+        if let Err(e) = persist_temp_upload_by_key(upload.path, &upload.sha256) {
+            tracing::error!("Failed to store file: {}", e);
+            res.logs.push(format!("Failed to store file: {}", e));
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+        let chain = v1_3::genesis_chain(&file_name, &upload.sha256);
+        return insert_v1_3_chain(
+            &server_database,
+            metamask_address,
+            chain,
+            Some(content_type),
+            res,
+        );
+    }
+
+    // the file is stored once, revisions only reference it by file hash
+    let file_hash_current = upload.file_hash;
+    if let Err(e) = persist_temp_upload(upload.path, &file_hash_current) {
+        tracing::error!("Failed to store file: {}", e);
+        res.logs.push(format!("Failed to store file: {}", e));
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+    }
 
     let mut content_current = BTreeMap::new();
     content_current.insert("file_hash".to_owned(), file_hash_current.to_string());
@@ -1078,7 +1383,7 @@ pub async fn explorer_file_upload(
         file_hash: file_hash_current,
     };
 
-    let pagedata_current = PageDataContainer {
+    let mut pagedata_current = PageDataContainer {
         pages: vec![HashChain {
             genesis_hash: verification_hash_current.clone().to_string(),
             domain_id: domain_id_current,
@@ -1090,7 +1395,7 @@ pub async fn explorer_file_upload(
                 Revision {
                     content: RevisionContent {
                         file: Some(FileContent {
-                            data: b64,
+                            data: Base64::from(Vec::new()),
                             filename: file_name.clone(),
                             size: file_size,
                             comment: String::new(),
@@ -1113,7 +1418,7 @@ pub async fn explorer_file_upload(
     };
 
     // Convert struct to JSON string
-    let json_string = match serde_json::to_string(&pagedata_current) {
+    let json_string = match serialize_for_storage(&mut pagedata_current) {
         Ok(json) => json,
         Err(e) => {
             tracing::error!("Failed to serialize page data: {}", e);
@@ -1167,7 +1472,7 @@ pub async fn explorer_file_upload(
         id: insert_result.unwrap(), //record.id,
         name: db_data_model.name,
         extension: db_data_model.extension,
-        page_data: db_data_model.page_data,
+        page_data: page_data_for_response(db_data_model.page_data),
         mode: db_data_model.mode,
         owner: db_data_model.owner.to_string(),
     };
@@ -1178,6 +1483,7 @@ pub async fn explorer_file_upload(
 
 pub async fn explorer_sign_revision(
     State(server_database): State<Db>,
+    user: AuthenticatedUser,
     Form(input): Form<RevisionInput>,
 ) -> (StatusCode, Json<ApiResponse>) {
     let mut log_data: Vec<String> = Vec::new();
@@ -1191,6 +1497,7 @@ pub async fn explorer_sign_revision(
             logs: log_data,
             file: None,
             files: Vec::new(),
+            head: None,
         };
         return (StatusCode::BAD_REQUEST, Json(res));
     };
@@ -1205,6 +1512,7 @@ pub async fn explorer_sign_revision(
                 logs: log_data,
                 file: None,
                 files: Vec::new(),
+                head: None,
             };
 
             println!("Error Fetching connection {:#?}", res);
@@ -1212,54 +1520,54 @@ pub async fn explorer_sign_revision(
         }
     };
 
-    // let insert_result = insert_page_data(db_data_model.clone(), & mut conn);
-    let page_data_result = fetch_page_data(input.file_id, &mut conn);
-
-    if page_data_result.is_err() {
-        tracing::error!("Failed not found ",);
-
-        log_data.push("Failed data not found in database".to_string());
-
-        let res: ApiResponse = ApiResponse {
-            logs: log_data,
-            file: None,
-            files: Vec::new(),
-        };
-        return (StatusCode::NOT_FOUND, Json(res));
-    }
-    let page_data = page_data_result.unwrap();
-
-    let deserialized: PageDataContainer<HashChain> =
-        match serde_json::from_str(&page_data.page_data) {
-            Ok(data) => {
-                log_data.push("Success  : parse page data".to_string());
-                data
-            }
+    let page_data =
+        match authorize_file_access(&user, input.file_id, FileAccess::Write, &mut conn) {
+            Ok(page) => page,
             Err(e) => {
-                tracing::error!("Failed to parse page data record: {:?}", e);
-                log_data.push(format!("error : Failed to parse page data record: {:?}", e));
-                if let Some(source) = e.source() {
-                    tracing::info!("Source: {}", source);
-                } else {
-                    tracing::info!("Source NOT FOUND ");
-                }
+                tracing::error!("Authorization failed: {}", e);
+                log_data.push(format!("Error : {}", e));
+
                 let res: ApiResponse = ApiResponse {
                     logs: log_data,
                     file: None,
                     files: Vec::new(),
+                    head: None,
                 };
-                return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+                return (e.status_code(), Json(res));
             }
         };
 
-    let mut doc = deserialized;
-    let len = doc.pages[0].revisions.len();
-
-    let (ver1, rev1) = &doc.pages[0].revisions[len - 1].clone();
+    let mut document = match AquaDocument::parse(page_data.page_data.as_bytes()) {
+        Ok(document) => {
+            log_data.push("Success  : parse page data".to_string());
+            document
+        }
+        Err(e) => {
+            tracing::error!("Failed to parse page data record: {}", e);
+            log_data.push(format!("error : Failed to parse page data record: {}", e));
+            let res: ApiResponse = ApiResponse {
+                logs: log_data,
+                file: None,
+                files: Vec::new(),
+                head: None,
+            };
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
 
-    let mut rev2 = rev1.clone();
-    rev2.witness = None;
-    rev2.metadata.previous_verification_hash = Some(*ver1);
+    let ver1 = match document.latest_verification_hash() {
+        Some(hash) => hash,
+        None => {
+            log_data.push("Error : chain has no revisions or is not linear".to_string());
+            let res: ApiResponse = ApiResponse {
+                logs: log_data,
+                file: None,
+                files: Vec::new(),
+                head: None,
+            };
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
 
     // Parse input data with proper error handling
     let sig = match input.signature.parse::<Signature>() {
@@ -1275,6 +1583,7 @@ pub async fn explorer_sign_revision(
                 logs: log_data,
                 file: None,
                 files: Vec::new(),
+                head: None,
             };
 
             return (StatusCode::BAD_REQUEST, Json(res));
@@ -1294,6 +1603,7 @@ pub async fn explorer_sign_revision(
                 logs: log_data,
                 file: None,
                 files: Vec::new(),
+                head: None,
             };
 
             return (StatusCode::BAD_REQUEST, Json(res));
@@ -1313,45 +1623,68 @@ pub async fn explorer_sign_revision(
                 logs: log_data,
                 file: None,
                 files: Vec::new(),
+                head: None,
             };
 
             return (StatusCode::BAD_REQUEST, Json(res));
         }
     };
 
-    let sig_hash = signature_hash(&sig, &pubk);
-
-    rev2.signature = Some(RevisionSignature {
-        signature: sig,
-        public_key: pubk,
-        signature_hash: sig_hash.clone(),
-        wallet_address: addr,
-    });
-
-    let timestamp_current = Timestamp::from(chrono::Utc::now().naive_utc());
-    rev2.metadata.time_stamp = timestamp_current.clone();
+    let scheme = match signature_scheme(
+        input.signing_mode.as_deref(),
+        input.timestamp,
+        &document,
+    ) {
+        Ok(scheme) => scheme,
+        Err(e) => {
+            tracing::error!("Invalid signing mode: {}", e);
+            log_data.push(format!("Error : {}", e));
 
-    let metadata_hash_current =
-        metadata_hash(&doc.pages[0].domain_id, &timestamp_current, Some(ver1));
+            let res: ApiResponse = ApiResponse {
+                logs: log_data,
+                file: None,
+                files: Vec::new(),
+                head: None,
+            };
+            return (StatusCode::BAD_REQUEST, Json(res));
+        }
+    };
 
-    let verification_hash_current = verification_hash(
-        &rev2.content.content_hash,
-        &metadata_hash_current,
-        Some(&sig_hash),
-        None,
-    );
+    // the signature must be over the latest revision and belong to wallet_address,
+    // directly or through its wallet contract
+    if let Err(e) = check_wallet_signature(&sig, &pubk, &addr, &ver1, &scheme).await {
+        tracing::error!("Signature rejected: {}", e);
+        log_data.push(format!("Error : signature rejected: {}", e));
 
-    rev2.metadata.metadata_hash = metadata_hash_current;
-    rev2.metadata.verification_hash = verification_hash_current;
+        let res: ApiResponse = ApiResponse {
+            logs: log_data,
+            file: None,
+            files: Vec::new(),
+            head: None,
+        };
+        return (e.status_code(), Json(res));
+    }
+    log_data.push("Success : signature verified".to_string());
 
-    doc.pages[0]
-        .revisions
-        .push((verification_hash_current, rev2));
+    let signature_revision_hash =
+        match append_document_signature(&mut document, sig, pubk, addr, &scheme) {
+            Ok(hash) => hash,
+            Err(e) => {
+                tracing::error!("Failed to append signature revision: {}", e);
+                log_data.push(format!("Error : failed to append signature revision: {}", e));
 
-    println!("Revisisons len is: {}", doc.pages[0].revisions.len());
+                let res: ApiResponse = ApiResponse {
+                    logs: log_data,
+                    file: None,
+                    files: Vec::new(),
+                    head: None,
+                };
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+            }
+        };
 
     // Serialize the updated document
-    let page_data_new = match serde_json::to_string(&doc) {
+    let page_data_new = match document.to_storage() {
         Ok(data) => {
             log_data.push("revision  serialized  successfully".to_string());
 
@@ -1366,6 +1699,7 @@ pub async fn explorer_sign_revision(
                 logs: log_data,
                 file: None,
                 files: Vec::new(),
+                head: None,
             };
 
             return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
@@ -1395,25 +1729,33 @@ pub async fn explorer_sign_revision(
     // let insert_result = insert_page_data(db_data_model.clone(), & mut conn);
     // let page_data_result = fetch_page_data(input.filename, & mut conn);
 
-    let update_result = update_page_data(new_data.clone(), &mut conn);
-    if update_result.is_err() {
-        let e = update_result.err().unwrap();
-        tracing::error!("Failed to update page data: {:?}", e);
-        log_data.push(format!("Failed to update page data : {:?}", e));
+    // the signing mode of a v1.2 signature is stored next to the chain so verification can
+    // rebuild the typed data, v1.3 signatures carry it
+    let update_result = conn.transaction::<_, PageUpdateError, _>(|conn| {
+        update_page_data(new_data.clone(), conn)?;
+        if document.version() == ProtocolVersion::V1_2 {
+            record_signature_scheme(&signature_revision_hash, &scheme, conn)?;
+        }
+        Ok(())
+    });
+    if let Err(e) = update_result {
+        tracing::error!("Failed to save signature revision: {}", e);
+        log_data.push(format!("Failed to update page data : {}", e));
 
         let res: ApiResponse = ApiResponse {
             logs: log_data,
             file: None,
             files: Vec::new(),
+            head: e.head(),
         };
-        return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        return (e.status_code(), Json(res));
     }
 
     let file_info = FileInfo {
         id: new_data.id as i64,
         name: new_data.name,
         extension: new_data.extension,
-        page_data: page_data_new.clone(),
+        page_data: page_data_for_response(page_data_new.clone()),
         owner: new_data.owner,
         mode: new_data.mode,
     };
@@ -1422,189 +1764,213 @@ pub async fn explorer_sign_revision(
         logs: log_data,
         file: Some(file_info),
         files: Vec::new(),
+        head: None,
     };
     return (StatusCode::OK, Json(res));
 }
 
-pub async fn explorer_merge_chain(
-    State(server_database): State<Db>,
-    Json(input): Json<MergeInput>,
-) -> (StatusCode, Json<ApiResponse>) {
-    let mut log_data: Vec<String> = Vec::new();
+/// Why `explorer_merge_chain` rejected a merge.
+enum MergeFailure {
+    Merge(MergeError),
+    Other(String),
+}
 
-    tracing::debug!("explorer_merge_chain");
+impl fmt::Display for MergeFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeFailure::Merge(e) => write!(f, "{}", e),
+            MergeFailure::Other(e) => write!(f, "{}", e),
+        }
+    }
+}
 
-    let file_id = input.file_id;
-    let last_identical_revision_hash = input.last_identical_revision_hash;
-    let revisions_to_import = input.revisions_to_import;
+fn merge_rejected(e: MergeError) -> (StatusCode, MergeFailure) {
+    (e.status_code(), MergeFailure::Merge(e))
+}
 
-    let mut conn = match server_database.pool.get() {
-        Ok(connection) => connection,
-        Err(e) => {
-            log_data.push("Failed data not found in database".to_string());
+fn incoming_revisions<T: for<'de> Deserialize<'de>>(
+    revisions: Vec<serde_json::Value>,
+    version: ProtocolVersion,
+) -> Result<Vec<T>, (StatusCode, MergeFailure)> {
+    revisions
+        .into_iter()
+        .map(serde_json::from_value)
+        .collect::<Result<Vec<T>, _>>()
+        .map_err(|e| {
+            let message = format!("revisions_to_import are not v{} revisions: {}", version, e);
+            (StatusCode::BAD_REQUEST, MergeFailure::Other(message))
+        })
+}
 
-            log_data.push("Failed to get database connection".to_string());
-            let res: ApiResponse = ApiResponse {
-                logs: log_data,
-                file: None,
-                files: Vec::new(),
-            };
+/// Merges into a v1.2 chain, returns how many revisions were appended.
+async fn merge_v1_2(
+    doc: &mut PageDataContainer<HashChain>,
+    last_identical_revision_hash: &str,
+    revisions: Vec<serde_json::Value>,
+    conn: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<usize, (StatusCode, MergeFailure)> {
+    let incoming: Vec<Revision> = incoming_revisions(revisions, ProtocolVersion::V1_2)?;
+    let chain = doc.pages.first().ok_or_else(|| {
+        (StatusCode::BAD_REQUEST, MergeFailure::Other("file has no chain".to_string()))
+    })?;
+
+    let plan = plan_merge(chain, last_identical_revision_hash, incoming).map_err(merge_rejected)?;
+    if plan.appended == 0 {
+        return Ok(0);
+    }
 
-            println!("Error Fetching connection {:#?}", res);
-            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
-        }
+    // incoming signatures are checked like any other, including contract wallets
+    let schemes = load_signature_schemes(&plan.chain, conn).map_err(|e| {
+        let message = format!("Failed to load signature modes: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, MergeFailure::Other(message))
+    })?;
+    let contract_signatures = confirm_contract_signatures(&plan.chain, &schemes)
+        .await
+        .map_err(|e| {
+            let message = format!("Failed to check wallet contracts: {}", e);
+            (StatusCode::BAD_GATEWAY, MergeFailure::Other(message))
+        })?;
+    let context = SignatureContext {
+        schemes,
+        contract_signatures,
     };
+    validate_merge(&plan, &context).map_err(merge_rejected)?;
 
-    // let insert_result = insert_page_data(db_data_model.clone(), & mut conn);
-    let page_data_result = fetch_page_data(file_id, &mut conn);
-
-    if page_data_result.is_err() {
-        tracing::error!("Failed not found ",);
-
-        log_data.push("Failed data not found in database".to_string());
+    let appended = plan.appended;
+    doc.pages[0] = plan.chain;
+    Ok(appended)
+}
 
-        let res: ApiResponse = ApiResponse {
-            logs: log_data,
-            file: None,
-            files: Vec::new(),
-        };
-        return (StatusCode::NOT_FOUND, Json(res));
+/// Merges into a v1.3 chain, returns how many revisions were appended.
+fn merge_v1_3(
+    chain: &mut v1_3::AquaChainV13,
+    last_identical_revision_hash: &str,
+    revisions: Vec<serde_json::Value>,
+    conn: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<usize, (StatusCode, MergeFailure)> {
+    let incoming: Vec<v1_3::RevisionV13> = incoming_revisions(revisions, ProtocolVersion::V1_3)?;
+    let plan = plan_merge_v13(chain, last_identical_revision_hash, incoming)
+        .map_err(merge_rejected)?;
+    if plan.appended.is_empty() {
+        return Ok(0);
     }
-    let page_data = page_data_result.unwrap();
 
-    let deserialized: PageDataContainer<HashChain> =
-        match serde_json::from_str(&page_data.page_data) {
-            Ok(data) => {
-                log_data.push("Success  : parse page data".to_string());
-                data
-            }
-            Err(e) => {
-                tracing::error!("Failed to parse page data record: {:?}", e);
-                log_data.push(format!("error : Failed to parse page data record: {:?}", e));
-                if let Some(source) = e.source() {
-                    tracing::info!("Source: {}", source);
-                } else {
-                    tracing::info!("Source NOT FOUND ");
-                }
-                let res: ApiResponse = ApiResponse {
-                    logs: log_data,
-                    file: None,
-                    files: Vec::new(),
-                };
-                return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
-            }
-        };
+    // new signatures carry their typed data, only upgraded ones have recorded schemes
+    let schemes = load_signature_schemes_for(&plan.chain.legacy_signature_hashes(), conn)
+        .map_err(|e| {
+            let message = format!("Failed to load signature modes: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, MergeFailure::Other(message))
+        })?;
+    validate_merge_v13(&plan, &schemes).map_err(merge_rejected)?;
+
+    let appended = plan.appended.len();
+    *chain = plan.chain;
+    Ok(appended)
+}
 
-    let mut doc = deserialized;
-    let len = doc.pages[0].revisions.len();
+/// Appends revisions made elsewhere to a chain. Every incoming revision must link to the one
+/// before it and recompute correctly, a chain that moved on locally is reported as a conflict.
+pub async fn explorer_merge_chain(
+    State(server_database): State<Db>,
+    user: AuthenticatedUser,
+    Json(input): Json<MergeInput>,
+) -> (StatusCode, Json<MergeApiResponse>) {
+    tracing::debug!("explorer_merge_chain");
+    let mut res = MergeApiResponse {
+        logs: Vec::new(),
+        file: None,
+        conflict: None,
+        head: None,
+    };
 
-    let page_revisions = &doc.pages[0].revisions;
+    let mut conn = match server_database.pool.get() {
+        Ok(connection) => connection,
+        Err(e) => {
+            tracing::error!("Failed to get database connection: {}", e);
+            res.logs.push("Failed to get database connection".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
 
-    // Find the index of the last identical revision hash
-    let last_index = page_revisions
-        .iter()
-        .position(|(hash, _)| format!("{:?}", hash) == last_identical_revision_hash)
-        .ok_or_else(|| {
-            format!(
-                "Hash {} not found in existing revisions",
-                last_identical_revision_hash
-            )
-        }).unwrap();
-
-    // Create a new vector with revisions up to the last identical hash
-    let mut new_revisions = page_revisions[..=last_index].to_vec();
-
-    // Append the new revisions, mapping them to (verification_hash, revision) tuples
-    new_revisions.extend(revisions_to_import.into_iter().map(|revision| {
-        let hash = revision.metadata.verification_hash.clone();
-        (hash, revision)
-    }));
-
-    // Replace the original revisions with the new combined vector
-    doc.pages[0].revisions = new_revisions;
+    let page_data = match authorize_file_access(&user, input.file_id, FileAccess::Write, &mut conn) {
+        Ok(page) => page,
+        Err(e) => {
+            tracing::error!("Authorization failed: {}", e);
+            res.logs.push(format!("Error : {}", e));
+            return (e.status_code(), Json(res));
+        }
+    };
 
-    // Serialize the updated document
-    let page_data_new = match serde_json::to_string(&doc) {
-        Ok(data) => {
-            log_data.push("revision  serialized  successfully".to_string());
+    let mut document = match AquaDocument::parse(page_data.page_data.as_bytes()) {
+        Ok(document) => document,
+        Err(e) => {
+            tracing::error!("Failed to parse page data record: {}", e);
+            res.logs
+                .push(format!("error : Failed to parse page data record: {}", e));
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
 
-            data
+    let fork_point = input.last_identical_revision_hash;
+    let incoming = input.revisions_to_import;
+    let merged = match &mut document {
+        AquaDocument::V1_2(doc) => merge_v1_2(doc, &fork_point, incoming, &mut conn).await,
+        AquaDocument::V1_3(chain) => merge_v1_3(chain, &fork_point, incoming, &mut conn),
+    };
+    let appended = match merged {
+        Ok(appended) => appended,
+        Err((status, e)) => {
+            tracing::error!("Merge rejected: {}", e);
+            res.logs.push(format!("Error : {}", e));
+            if let MergeFailure::Merge(MergeError::Conflict(conflict)) = e {
+                res.conflict = Some(conflict);
+            }
+            return (status, Json(res));
         }
+    };
+    if appended == 0 {
+        res.logs.push("Success : chain is already up to date".to_string());
+        return (StatusCode::OK, Json(res));
+    }
+
+    let page_data_new = match document.to_storage() {
+        Ok(data) => data,
         Err(e) => {
             tracing::error!("Failed to serialize updated page data: {:?}", e);
-
-            log_data.push(format!("Failed to serialize updated page data : {:?}", e));
-
-            let res: ApiResponse = ApiResponse {
-                logs: log_data,
-                file: None,
-                files: Vec::new(),
-            };
-
+            res.logs
+                .push(format!("Failed to serialize updated page data : {:?}", e));
             return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
         }
     };
 
     let mut new_data = page_data.clone();
-    new_data.page_data = page_data_new.clone();
-
-
-    let update_result = update_page_data(new_data.clone(), &mut conn);
-    if update_result.is_err() {
-        let e = update_result.err().unwrap();
-        tracing::error!("Failed to update page data: {:?}", e);
-        log_data.push(format!("Failed to update page data : {:?}", e));
-
-        let res: ApiResponse = ApiResponse {
-            logs: log_data,
-            file: None,
-            files: Vec::new(),
-        };
-        return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+    new_data.page_data = page_data_new;
+    if let Err(e) = update_page_data(new_data.clone(), &mut conn) {
+        tracing::error!("Failed to update page data: {}", e);
+        res.logs.push(format!("Failed to update page data : {}", e));
+        res.head = e.head();
+        return (e.status_code(), Json(res));
     }
 
-    let file_info = FileInfo {
+    res.logs
+        .push(format!("Success : merged {} revisions", appended));
+    res.file = Some(FileInfo {
         id: new_data.id as i64,
         name: new_data.name,
         extension: new_data.extension,
-        page_data: page_data_new.clone(),
+        page_data: page_data_for_response(new_data.page_data),
         owner: new_data.owner,
         mode: new_data.mode,
-    };
-
-    let res: ApiResponse = ApiResponse {
-        logs: log_data,
-        file: Some(file_info),
-        files: Vec::new(),
-    };
-    return (StatusCode::OK, Json(res));
+    });
+    (StatusCode::OK, Json(res))
 }
 
 pub async fn explorer_delete_all_files(
     State(server_database): State<Db>,
-    headers: HeaderMap,
+    user: AuthenticatedUser,
 ) -> (StatusCode, Json<ApiResponse>) {
     let mut log_data: Vec<String> = Vec::new();
 
-    let user_address: Result<String, String> = match headers.get("metamask_address") {
-        Some(value) => match value.to_str() {
-            Ok(address) => Ok(address.to_string()), // Successfully extracted address
-            Err(_) => Err("Failed to parse the metamask_address header.".to_string()),
-        },
-        None => Err("metamask_address header not found.".to_string()), // Header not found
-    };
-
-    if user_address.is_err() {
-        log_data
-            .push("Unable to parse metamask address/ Metamask address not provided".to_string());
-        let res: ApiResponse = ApiResponse {
-            logs: log_data.clone(),
-            file: None,
-            files: Vec::new(),
-        };
-        return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
-    }
-
     let mut conn = match server_database.pool.get() {
         Ok(connection) => connection,
         Err(e) => {
@@ -1615,6 +1981,7 @@ pub async fn explorer_delete_all_files(
                 logs: log_data,
                 file: None,
                 files: Vec::new(),
+                head: None,
             };
 
             println!("Error Fetching connection {:#?}", res);
@@ -1622,11 +1989,16 @@ pub async fn explorer_delete_all_files(
         }
     };
 
-    let addr = user_address.unwrap();
-    let result = delete_all_user_files(addr, &mut conn);
+    // blobs of the chains are removed once no other chain references them
+    let file_hashes = fetch_owner_file_hashes(&user.address, &mut conn).unwrap_or_else(|e| {
+        tracing::error!("Failed to fetch file hashes: {}", e);
+        Vec::new()
+    });
+    let result = delete_all_user_files(user.address, &mut conn);
 
     match result {
         Ok(result_data) => {
+            remove_unused_blobs(&file_hashes, &mut conn);
             // Check the number of affected rows
             // if result_data.rows_affected() > 0 {
             tracing::error!("Successfully deleted all the row with name");
@@ -1635,6 +2007,7 @@ pub async fn explorer_delete_all_files(
                 logs: log_data,
                 file: None,
                 files: Vec::new(),
+                head: None,
             };
             return (StatusCode::OK, Json(res));
             // } else {
@@ -1656,6 +2029,7 @@ pub async fn explorer_delete_all_files(
                 logs: log_data,
                 file: None,
                 files: Vec::new(),
+                head: None,
             };
             (StatusCode::INTERNAL_SERVER_ERROR, Json(res))
         }
@@ -1664,6 +2038,7 @@ pub async fn explorer_delete_all_files(
 
 pub async fn explorer_delete_file(
     State(server_database): State<Db>,
+    user: AuthenticatedUser,
     Form(input): Form<DeleteInput>,
 ) -> (StatusCode, Json<ApiResponse>) {
     tracing::debug!("explorer_delete_file");
@@ -1676,6 +2051,7 @@ pub async fn explorer_delete_file(
             logs: log_data,
             file: None,
             files: Vec::new(),
+            head: None,
         };
         return (StatusCode::BAD_REQUEST, Json(res));
     };
@@ -1694,6 +2070,7 @@ pub async fn explorer_delete_file(
                 logs: log_data,
                 file: None,
                 files: Vec::new(),
+                head: None,
             };
 
             println!("Error Fetching connection {:#?}", res);
@@ -1701,18 +2078,38 @@ pub async fn explorer_delete_file(
         }
     };
 
+    if let Err(e) = authorize_file_access(&user, input.file_id, FileAccess::Owner, &mut conn) {
+        tracing::error!("Authorization failed: {}", e);
+        log_data.push(format!("Error : {}", e));
+
+        let res: ApiResponse = ApiResponse {
+            logs: log_data,
+            file: None,
+            files: Vec::new(),
+            head: None,
+        };
+        return (e.status_code(), Json(res));
+    }
+
+    // blobs of the chain are removed once no other chain references them
+    let file_hashes = fetch_file_hashes(&[input.file_id], &mut conn).unwrap_or_else(|e| {
+        tracing::error!("Failed to fetch file hashes: {}", e);
+        Vec::new()
+    });
     let result = delete_page_data(input.file_id, &mut conn);
 
     match result {
         Ok(result_data) => {
             // Check the number of affected rows
             if result_data > 0 {
+                remove_unused_blobs(&file_hashes, &mut conn);
                 tracing::error!("Successfully deleted the row with name: {}", input.file_id);
                 log_data.push("Error : file data is deleted ".to_string());
                 let res: ApiResponse = ApiResponse {
                     logs: log_data,
                     file: None,
                     files: Vec::new(),
+                    head: None,
                 };
                 return (StatusCode::OK, Json(res));
             } else {
@@ -1723,6 +2120,7 @@ pub async fn explorer_delete_file(
                     logs: log_data,
                     file: None,
                     files: Vec::new(),
+                    head: None,
                 };
                 return (StatusCode::NOT_FOUND, Json(res));
             }
@@ -1734,6 +2132,7 @@ pub async fn explorer_delete_file(
                 logs: log_data,
                 file: None,
                 files: Vec::new(),
+                head: None,
             };
             (StatusCode::INTERNAL_SERVER_ERROR, Json(res))
         }
@@ -1742,6 +2141,7 @@ pub async fn explorer_delete_file(
 
 pub async fn explorer_witness_file(
     State(server_database): State<Db>,
+    user: AuthenticatedUser,
     Form(input): Form<WitnessInput>,
 ) -> (StatusCode, Json<ApiResponse>) {
     tracing::debug!("explorer_witness_file");
@@ -1754,16 +2154,7 @@ pub async fn explorer_witness_file(
             logs: log_data,
             file: None,
             files: Vec::new(),
-        };
-        return (StatusCode::BAD_REQUEST, Json(res));
-    };
-
-    if input.network.is_empty() {
-        log_data.push("Error : Network is empty".to_string());
-        let res: ApiResponse = ApiResponse {
-            logs: log_data,
-            file: None,
-            files: Vec::new(),
+            head: None,
         };
         return (StatusCode::BAD_REQUEST, Json(res));
     };
@@ -1780,6 +2171,7 @@ pub async fn explorer_witness_file(
                 logs: log_data,
                 file: None,
                 files: Vec::new(),
+                head: None,
             };
 
             println!("Error Fetching connection {:#?}", res);
@@ -1787,58 +2179,68 @@ pub async fn explorer_witness_file(
         }
     };
 
-    let page_data_result = fetch_page_data(input.file_id, &mut conn);
-
-    if page_data_result.is_err() {
-        tracing::error!("Failed not found  {}", page_data_result.err().unwrap());
-
-        log_data.push("Failed data not found in database".to_string());
+    let page_data =
+        match authorize_file_access(&user, input.file_id, FileAccess::Write, &mut conn) {
+            Ok(page) => page,
+            Err(e) => {
+                tracing::error!("Authorization failed: {}", e);
+                log_data.push(format!("Error : {}", e));
 
-        let res: ApiResponse = ApiResponse {
-            logs: log_data,
-            file: None,
-            files: Vec::new(),
+                let res: ApiResponse = ApiResponse {
+                    logs: log_data,
+                    file: None,
+                    files: Vec::new(),
+                    head: None,
+                };
+                return (e.status_code(), Json(res));
+            }
         };
-        return (StatusCode::NOT_FOUND, Json(res));
-    }
-
-    let page_data = page_data_result.unwrap();
 
     log_data.push(format!(
         "Success :  Page data for {} not found in database",
         input.file_id
     ));
 
-    // Deserialize page data
-    let deserialized: PageDataContainer<HashChain> =
-        match serde_json::from_str(&page_data.page_data) {
-            Ok(data) => {
-                log_data.push("Success :  Page Data Object parse".to_string());
-
-                data
-            }
-            Err(e) => {
-                tracing::error!("Failed to parse page data record: {:?}", e);
-
-                log_data.push("Error : Failure to parse Page Data Object".to_string());
+    // an empty network falls back to the chain of the caller's profile
+    let profile = fetch_user_profile(user.address.clone(), &mut conn).ok();
+    let network = match network_registry()
+        .resolve(&input.network, profile.as_ref().map(|p| p.chain.as_str()))
+    {
+        Ok(network) => network,
+        Err(e) => {
+            tracing::error!("Invalid witness network: {}", e);
+            log_data.push(format!("Error : {}", e));
 
-                let res: ApiResponse = ApiResponse {
-                    logs: log_data,
-                    file: None,
-                    files: Vec::new(),
-                };
+            let res: ApiResponse = ApiResponse {
+                logs: log_data,
+                file: None,
+                files: Vec::new(),
+                head: None,
+            };
+            return (StatusCode::BAD_REQUEST, Json(res));
+        }
+    };
 
-                return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
-            }
-        };
+    let mut document = match AquaDocument::parse(page_data.page_data.as_bytes()) {
+        Ok(document) => {
+            log_data.push("Success :  Page Data Object parse".to_string());
+            document
+        }
+        Err(e) => {
+            tracing::error!("Failed to parse page data record: {}", e);
 
-    let mut doc = deserialized.clone();
-    let len = doc.pages[0].revisions.len();
+            log_data.push("Error : Failure to parse Page Data Object".to_string());
 
-    let (ver1, rev1) = &doc.pages[0].revisions[len - 1].clone();
+            let res: ApiResponse = ApiResponse {
+                logs: log_data,
+                file: None,
+                files: Vec::new(),
+                head: None,
+            };
 
-    let mut rev2 = rev1.clone();
-    rev2.metadata.previous_verification_hash = Some(*ver1);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
 
     let txHash = match input.tx_hash.parse::<TxHash>() {
         Ok(s) => s,
@@ -1850,6 +2252,7 @@ pub async fn explorer_witness_file(
                 logs: log_data,
                 file: None,
                 files: Vec::new(),
+                head: None,
             };
 
             return (StatusCode::BAD_REQUEST, Json(res));
@@ -1869,6 +2272,7 @@ pub async fn explorer_witness_file(
                 logs: log_data,
                 file: None,
                 files: Vec::new(),
+                head: None,
             };
             return (StatusCode::BAD_REQUEST, Json(res));
         }
@@ -1878,95 +2282,98 @@ pub async fn explorer_witness_file(
         wallet_address
     ));
 
-    let domain_snapshot_genesis_string = &deserialized.pages.get(0).unwrap().genesis_hash;
-
-    let mut hasher = sha3::Sha3_512::default();
-    hasher.update("");
-
-    let domain_snapshot_genesis_hash = Hash::from(hasher.finalize());
-    tracing::debug!(
-        "Rev1 Metadata verification hash: {}",
-        rev1.metadata.verification_hash
-    );
-
-    let witness_hash = witness_hash(
-        &domain_snapshot_genesis_hash,
-        &rev1.metadata.verification_hash,
-        "sepolia",
-        &txHash,
-    );
-    tracing::debug!("Tx hash after user: {}", txHash);
-
-    let mut merkle_tree_successor_hasher = sha3::Sha3_512::default();
-    merkle_tree_successor_hasher.update(format!(
-        "{}{}",
-        &rev1.metadata.verification_hash.to_string(),
-        make_empty_hash().to_string()
-    ));
-
-    let merkle_tree_successor = Hash::from(merkle_tree_successor_hasher.finalize());
-
-    let mut merkle_tree = Vec::new();
-    merkle_tree.push(MerkleNode {
-        left_leaf: rev1.metadata.verification_hash,
-        right_leaf: make_empty_hash(),
-        successor: merkle_tree_successor,
-    });
-
-    let mut hasher_verification = sha3::Sha3_512::default();
-    hasher_verification.update(format!(
-        "{}{}",
-        domain_snapshot_genesis_hash.to_string(),
-        &rev1.metadata.verification_hash.to_string()
-    ));
-
-    let witness_event_verification_hash = Hash::from(hasher_verification.finalize());
-    tracing::debug!(
-        "Witness event verification hash: {}",
-        witness_event_verification_hash
-    );
-
-    rev2.witness = Some(RevisionWitness {
-        domain_snapshot_genesis_hash: domain_snapshot_genesis_hash,
-        merkle_root: rev1.metadata.verification_hash,
-        witness_network: input.network,
-        witness_event_transaction_hash: txHash,
-        witness_event_verification_hash: witness_event_verification_hash,
-        witness_hash: witness_hash,
-        structured_merkle_proof: merkle_tree,
-    });
-
-    rev2.signature = None;
-
-    let timestamp_current = Timestamp::from(chrono::Utc::now().naive_utc());
-    rev2.metadata.time_stamp = timestamp_current.clone();
-
-    tracing::debug!(
-        "Current timestamp: {}, domain id: {}, previous Ver: {}",
-        timestamp_current,
-        doc.pages[0].domain_id,
-        ver1
-    );
-
-    let metadata_hash_current =
-        metadata_hash(&doc.pages[0].domain_id, &timestamp_current, Some(ver1));
+    // a single chain is its own merkle root, v1.2 publishes the witness event verification
+    // hash over it and v1.3 the root itself
+    let (ver1, published_hash) = match &document {
+        AquaDocument::V1_2(doc) => {
+            let ver1 = match doc.pages.first().and_then(latest_verification_hash) {
+                Some(hash) => hash,
+                None => {
+                    log_data.push("Error : chain has no revisions".to_string());
+                    let res: ApiResponse = ApiResponse {
+                        logs: log_data,
+                        file: None,
+                        files: Vec::new(),
+                        head: None,
+                    };
+                    return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+                }
+            };
+            let published = witness_event_verification_hash(&make_empty_hash(), &ver1);
+            (ver1.to_string(), published.to_string())
+        }
+        AquaDocument::V1_3(chain) => match chain.latest_verification_hash() {
+            Some(hash) => (hash.clone(), hash),
+            None => {
+                log_data.push("Error : chain has no revisions or is not linear".to_string());
+                let res: ApiResponse = ApiResponse {
+                    logs: log_data,
+                    file: None,
+                    files: Vec::new(),
+                    head: None,
+                };
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+            }
+        },
+    };
+    tracing::debug!("Witnessing {}, published hash {}", ver1, published_hash);
 
-    let verification_hash_current = verification_hash(
-        &rev2.content.content_hash,
-        &metadata_hash_current,
-        None,
-        Some(&witness_hash),
-    );
+    let contract_address = witness_contract_address(network, profile.as_ref());
+    if let Err(e) = confirm_witness(network, &txHash, &contract_address, &published_hash).await {
+        tracing::error!("Witness transaction not confirmed: {}", e);
+        log_data.push(format!("Error : witness transaction not confirmed: {}", e));
 
-    rev2.metadata.metadata_hash = metadata_hash_current;
-    rev2.metadata.verification_hash = verification_hash_current;
+        let res: ApiResponse = ApiResponse {
+            logs: log_data,
+            file: None,
+            files: Vec::new(),
+            head: None,
+        };
+        return (e.status_code(), Json(res));
+    }
+    log_data.push("Success : witness transaction confirmed".to_string());
+
+    let appended = match &mut document {
+        // v1.2 witnesses have no field for the sender, it is only logged
+        AquaDocument::V1_2(doc) => {
+            tracing::info!("Witness transaction {} sent by {}", txHash, wallet_address);
+            let chain = &mut doc.pages[0];
+            let ver1 = chain.revisions[chain.revisions.len() - 1].0;
+            let proof = vec![MerkleNode {
+                left_leaf: ver1,
+                right_leaf: make_empty_hash(),
+                successor: sha3_hash(format!("{}{}", ver1, make_empty_hash())),
+            }];
+            append_witness_revision(chain, ver1, proof, &network.name, txHash)
+                .map(|hash| hash.to_string())
+        }
+        AquaDocument::V1_3(chain) => v1_3::append_witness_revision(
+            chain,
+            v1_3::WitnessV13 {
+                merkle_root: ver1.clone(),
+                merkle_proof: Vec::new(),
+                network: network.name.clone(),
+                transaction_hash: txHash.to_string(),
+                smart_contract_address: contract_address.clone(),
+                sender_account_address: wallet_address.to_string(),
+            },
+        ),
+    };
+    if let Err(e) = appended {
+        tracing::error!("Failed to append witness revision: {}", e);
+        log_data.push(format!("Error : failed to append witness revision: {}", e));
 
-    doc.pages[0]
-        .revisions
-        .push((verification_hash_current, rev2));
+        let res: ApiResponse = ApiResponse {
+            logs: log_data,
+            file: None,
+            files: Vec::new(),
+            head: None,
+        };
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+    }
 
     // Serialize the updated document
-    let page_data_new = match serde_json::to_string(&doc) {
+    let page_data_new = match document.to_storage() {
         Ok(data) => data,
         Err(e) => {
             tracing::error!("Failed to serialize updated page data: {:?}", e);
@@ -1979,6 +2386,7 @@ pub async fn explorer_witness_file(
                 logs: log_data,
                 file: None,
                 files: Vec::new(),
+                head: None,
             };
             return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
         }
@@ -1997,6 +2405,7 @@ pub async fn explorer_witness_file(
                 logs: log_data,
                 file: None,
                 files: Vec::new(),
+                head: None,
             };
 
             println!("Error Fetching connection {:#?}", res);
@@ -2005,24 +2414,24 @@ pub async fn explorer_witness_file(
     };
 
     let update_result = update_page_data(new_data.clone(), &mut conn);
-    if update_result.is_err() {
-        let e = update_result.err().unwrap();
-        tracing::error!("Failed to update page data: {:?}", e);
-        log_data.push(format!("Failed to update page data : {:?}", e));
+    if let Err(e) = update_result {
+        tracing::error!("Failed to update page data: {}", e);
+        log_data.push(format!("Failed to update page data : {}", e));
 
         let res: ApiResponse = ApiResponse {
             logs: log_data,
             file: None,
             files: Vec::new(),
+            head: e.head(),
         };
-        return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        return (e.status_code(), Json(res));
     }
 
     let file_info = FileInfo {
         id: new_data.id as i64,
         name: new_data.name,
         extension: new_data.extension,
-        page_data: page_data_new,
+        page_data: page_data_for_response(page_data_new),
         owner: new_data.owner,
         mode: new_data.mode,
     };
@@ -2031,6 +2440,7 @@ pub async fn explorer_witness_file(
         logs: log_data,
         file: Some(file_info),
         files: Vec::new(),
+        head: None,
     };
     return (StatusCode::OK, Json(res));
 
@@ -4,6 +4,7 @@ use crate::db::siwe::fetch_siwe_data;
 use crate::db::siwe::fetch_siwe_session_by_nonce;
 use crate::db::siwe::insert_siwe_data;
 use crate::db::user_profiles::insert_user_profile_data;
+use crate::eip1271::{contract_signature_valid, is_well_formed_signature};
 use crate::Db;
 use axum::{extract::State, http::StatusCode, Form, Json};
 use ethers::types::Signature;
@@ -22,7 +23,11 @@ use tokio::sync::Mutex;
 use tracing::{error, info};
 use rand::{thread_rng, Rng};
 
-use crate::auth::{SiweError, SiweNonceRequest, SiweResponse, SiweSession};
+use crate::auth::nonce::{format_nonce_time, generate_nonce, nonce_ttl, siwe_session_is_expired};
+use crate::auth::{SiweError, SiweNonceRequest, SiweNonceResponse, SiweResponse, SiweSession};
+use crate::db::siwe::{consume_siwe_nonce, insert_siwe_nonce};
+use crate::models::SiweNonceTable;
+use chrono::Utc;
 
 pub async fn siwe_sign_in(
     State(server_database): State<Db>,
@@ -52,6 +57,34 @@ pub async fn siwe_sign_in(
                 }
             };
 
+            // the nonce must have been issued by /siwe_nonce and not used before
+            let now = format_nonce_time(Utc::now());
+            match consume_siwe_nonce(&siwe_session.nonce, &now, &mut conn) {
+                Ok(true) => {}
+                Ok(false) => {
+                    error!("SIWE sign-in with unknown or used nonce");
+                    log_data.push(format!("SIWE sign-in failed: {}", SiweError::InvalidNonce));
+                    let res = SiweResponse {
+                        logs: log_data,
+                        success: false,
+                        session: None,
+                        user_profile: None,
+                    };
+                    return (StatusCode::UNAUTHORIZED, Json(res));
+                }
+                Err(e) => {
+                    error!("Error occured consuming nonce: {:#?}", e);
+                    log_data.push("Failed to create sign in session".to_string());
+                    let res = SiweResponse {
+                        logs: log_data,
+                        success: false,
+                        session: None,
+                        user_profile: None,
+                    };
+                    return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+                }
+            }
+
             let res = insert_siwe_data(siwe_session.clone(), &mut conn);
             if res.is_err() {
                 let e = res.err().unwrap();
@@ -126,34 +159,41 @@ pub async fn verify_siwe_message(
     // // let eth_signed_message = message;
     let message_hash = keccak256(eth_signed_message.as_bytes());
 
-    // // Parse the signature
-    let sig = Signature::from_str(&signature).map_err(|_| SiweError::InvalidSignature)?;
-
-    // // Recover the Ethereum address from the signature and message hash
-    let recovered_address = sig
-        .recover(message_hash)
-        .map_err(|_| SiweError::VerificationFailed)?;
+    // // Parse the signature, contract wallets may send signatures of any length
+    let sig_bytes = Vec::<u8>::from_hex(signature.trim_start_matches("0x"))
+        .map_err(|_| SiweError::InvalidSignature)?;
 
     // SIWE Message
-    let _message = Message::from_str(&message).unwrap();
-    let expiration_time = _message.clone().expiration_time;
-
-    // // Expected address (this could be dynamically provided or extracted from the SIWE message)
-    // let expected_address = Address::from_str(&user_address).unwrap();
-    // // Compare the recovered address with the expected address
-    if recovered_address != ethers::types::H160(_message.address) {
-        return Err(SiweError::AddressMismatch);
+    let _message = Message::from_str(&message).map_err(|_| SiweError::InvalidMessage)?;
+
+    // honour expiration_time and not_before
+    if !_message.valid_now() {
+        return Err(SiweError::MessageExpired);
     }
-    // let cased_address = ethers::types::H160::from_str(&format!("{:?}", recovered_address)).expect("Invalid Ethereum address");
-    // println!("Cased address: {:?}", cased_address);
 
-    let sig = <[u8; 65]>::from_hex(format!(r#"{}"#, signature)).unwrap();
+    let address = ethers::types::H160(_message.address);
 
+    // // Recover the Ethereum address from the signature and message hash
+    let recovered_address = Signature::try_from(sig_bytes.as_slice())
+        .ok()
+        .and_then(|sig| sig.recover(message_hash).ok());
+
+    // a signature that is not from the address itself may come from its wallet contract
+    if recovered_address != Some(address) {
+        if !is_well_formed_signature(&sig_bytes) {
+            return Err(SiweError::InvalidSignature);
+        }
+        verify_siwe_contract_signature(&_message, &domain, message_hash, &sig_bytes).await?;
+        info!("Message is Okay and wallet contract accepted the signature");
+        return Ok(siwe_session(&_message, address));
+    }
+
+    let sig = <[u8; 65]>::try_from(sig_bytes.as_slice()).map_err(|_| SiweError::InvalidSignature)?;
+
+    // the nonce itself is checked against siwe_nonces by the caller
     let verification_opts = VerificationOpts {
-        domain: Some(domain.parse().unwrap()),
-        // We can adjust the fields once we create database sessions. There is a table already
-        // nonce: Some("kEWepMt9knR6lWJ6A".into()),
-        // timestamp: Some(OffsetDateTime::parse("2021-12-08T00:00:00Z", &Rfc3339).unwrap()),
+        domain: Some(domain.parse().map_err(|_| SiweError::InvalidMessage)?),
+        nonce: Some(_message.nonce.clone()),
         ..Default::default()
     };
 
@@ -162,21 +202,77 @@ pub async fn verify_siwe_message(
     // Confirm whether the message verification is successful
     if msg_verification.is_ok() {
         info!("Message is Okay and recovered address is correct");
-        let siwe_session = SiweSession {
-            address: format!("{:?}", recovered_address),
-            nonce: _message.nonce.to_string(),
-            issued_at: _message.issued_at.to_string(),
-            expiration_time: _message.expiration_time.map(|time| time.to_string()),
-        };
-
-        // Ok(format!("{:?}", recovered_address))
-        Ok(siwe_session)
+        Ok(siwe_session(&_message, address))
     } else {
         error!("Quack Message");
         Err(SiweError::MessageVerificationFailed)
     }
 }
 
+fn siwe_session(message: &Message, address: Address) -> SiweSession {
+    SiweSession {
+        address: format!("{:?}", address),
+        nonce: message.nonce.to_string(),
+        issued_at: message.issued_at.to_string(),
+        expiration_time: message.expiration_time.as_ref().map(|time| time.to_string()),
+    }
+}
+
+/// EIP-1271 sign-in for smart-contract wallets such as a Safe, the wallet contract is
+/// asked whether it accepts the signature over the EIP-191 hash of the message.
+async fn verify_siwe_contract_signature(
+    message: &Message,
+    domain: &str,
+    message_hash: [u8; 32],
+    signature: &[u8],
+) -> Result<(), SiweError> {
+    if !message.domain.to_string().eq_ignore_ascii_case(domain.trim()) {
+        return Err(SiweError::MessageVerificationFailed);
+    }
+
+    let address = ethers::types::H160(message.address);
+    match contract_signature_valid(address, message_hash, signature).await {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(SiweError::AddressMismatch),
+        Err(e) => {
+            error!("Wallet contract check failed for {:?}: {}", address, e);
+            Err(SiweError::WalletContractUnavailable)
+        }
+    }
+}
+
+pub async fn siwe_issue_nonce(
+    State(server_database): State<Db>,
+) -> (StatusCode, Json<Option<SiweNonceResponse>>) {
+    let mut conn = match server_database.pool.get() {
+        Ok(connection) => connection,
+        Err(e) => {
+            error!("Failed to get database connection: {}", e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(None));
+        }
+    };
+
+    let created_at = Utc::now();
+    let expires_at = created_at + nonce_ttl();
+    let record = SiweNonceTable {
+        id: None,
+        nonce: generate_nonce(),
+        created_at: format_nonce_time(created_at),
+        expires_at: format_nonce_time(expires_at),
+    };
+
+    if let Err(e) = insert_siwe_nonce(record.clone(), &mut conn) {
+        error!("Error occured inserting nonce into db: {:#?}", e);
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(None));
+    }
+
+    let res = SiweNonceResponse {
+        nonce: record.nonce,
+        expires_at: expires_at.to_rfc3339(),
+    };
+    (StatusCode::OK, Json(Some(res)))
+}
+
 pub async fn fetch_nonce_session(
     State(server_database): State<Db>,
     Form(payload): Form<SiweNonceRequest>,
@@ -199,21 +295,26 @@ pub async fn fetch_nonce_session(
         }
     };
 
-    let session = fetch_siwe_session_by_nonce(&payload.nonce, &mut conn);
+    let session = match fetch_siwe_session_by_nonce(&payload.nonce, &mut conn) {
+        Ok(session) => session,
+        Err(_) => return (StatusCode::NOT_FOUND, Json(None)),
+    };
 
-    if session.is_err() {
-        return (StatusCode::NOT_FOUND, Json(None));
+    // the same expiry as every authenticated request, see `auth::session`
+    if siwe_session_is_expired(&session, Utc::now()) {
+        if let Err(e) = delete_siwe_session_by_nonce(&payload.nonce, &mut conn) {
+            error!("Failed to delete expired session: {}", e);
+        }
+        return (StatusCode::UNAUTHORIZED, Json(None));
     }
 
-    let _session = session.unwrap();
-
     let siwe = SiweSession {
-        address: _session.address.clone(),
-        nonce: _session.address.clone(),
-        issued_at: _session.issued_at.clone(),
-        expiration_time: _session.expiration_time.clone(),
+        address: session.address,
+        nonce: session.nonce,
+        issued_at: session.issued_at,
+        expiration_time: session.expiration_time,
     };
-    return (StatusCode::OK, Json(Some(siwe)));
+    (StatusCode::OK, Json(Some(siwe)))
 }
 
 pub async fn session_logout_by_nonce(
@@ -274,3 +375,54 @@ pub async fn session_logout_by_nonce(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eip1271::tests::{deploy, ACCEPTING_INIT_CODE, REJECTING_INIT_CODE};
+    use chrono::SecondsFormat;
+    use ethers::core::utils::{to_checksum, Anvil};
+
+    const DOMAIN: &str = "localhost:3000";
+
+    fn siwe_message(address: Address) -> String {
+        format!(
+            "{domain} wants you to sign in with your Ethereum account:\n{address}\n\n\
+             Sign in with Ethereum to the app.\n\n\
+             URI: http://{domain}\nVersion: 1\nChain ID: 31337\nNonce: {nonce}\nIssued At: {issued_at}",
+            domain = DOMAIN,
+            address = to_checksum(&address, None),
+            nonce = "aquananonce1",
+            issued_at = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+        )
+    }
+
+    // needs `anvil` on the PATH, run with `cargo test -- --ignored`
+    #[tokio::test]
+    #[ignore]
+    async fn signs_in_wallet_contracts_through_eip1271() {
+        let anvil = Anvil::new().chain_id(31337).spawn();
+        let accepting = deploy(&anvil, ACCEPTING_INIT_CODE).await;
+        let rejecting = deploy(&anvil, REJECTING_INIT_CODE).await;
+        std::env::set_var("EIP1271_RPC_URL", anvil.endpoint());
+        let signature = format!("0x{}", hex::encode([0x11; 65]));
+
+        let session = verify_siwe_message(
+            siwe_message(accepting),
+            signature.clone(),
+            DOMAIN.to_string(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(session.address, format!("{:?}", accepting));
+
+        let rejected =
+            verify_siwe_message(siwe_message(rejecting), signature, DOMAIN.to_string()).await;
+        assert!(matches!(rejected, Err(SiweError::AddressMismatch)), "{:?}", rejected);
+
+        // an empty signature is not sent to the wallet contract at all
+        let empty = verify_siwe_message(siwe_message(accepting), "0x".to_string(), DOMAIN.to_string())
+            .await;
+        assert!(matches!(empty, Err(SiweError::InvalidSignature)), "{:?}", empty);
+    }
+}
@@ -0,0 +1,378 @@
+use crate::auth::permissions::{authorize_file_access, FileAccess};
+use crate::auth::session::{AuthenticatedUser, ContentUser};
+use crate::blob_store::blob_path_by_key;
+use crate::bundle::build_bundle;
+use crate::models::page_data::{ApiResponse, PageDataContainer};
+use crate::protocol::AquaDocument;
+use crate::signing::{export_signature_modes, load_document_signature_schemes};
+use crate::util::{get_content_type, get_file_info};
+use crate::Db;
+use aqua_verifier_rs_types::models::page_data::HashChain;
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use std::io::SeekFrom;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+extern crate serde_json_path_to_error as serde_json;
+
+/// Bytes read from the start of a file to sniff its type when the name has no known extension.
+const SNIFF_LENGTH: usize = 64;
+
+#[derive(Debug, PartialEq)]
+enum ByteRange {
+    /// Serve the whole file.
+    Full,
+    /// Inclusive start and end offsets.
+    Partial(u64, u64),
+    Unsatisfiable,
+}
+
+/// Parses a single `bytes=` range, multiple ranges are answered with the full file.
+fn parse_range(value: &str, len: u64) -> ByteRange {
+    let spec = match value.trim().strip_prefix("bytes=") {
+        Some(spec) if !spec.contains(',') => spec.trim(),
+        _ => return ByteRange::Full,
+    };
+    let (start, end) = match spec.split_once('-') {
+        Some(parts) => parts,
+        None => return ByteRange::Full,
+    };
+
+    let range = if start.is_empty() {
+        // suffix range, the last `end` bytes
+        match end.parse::<u64>() {
+            Ok(0) | Err(_) => return ByteRange::Unsatisfiable,
+            Ok(suffix) => (len.saturating_sub(suffix), len.saturating_sub(1)),
+        }
+    } else {
+        let start = match start.parse::<u64>() {
+            Ok(start) => start,
+            Err(_) => return ByteRange::Full,
+        };
+        let end = if end.is_empty() {
+            len.saturating_sub(1)
+        } else {
+            match end.parse::<u64>() {
+                Ok(end) => end.min(len.saturating_sub(1)),
+                Err(_) => return ByteRange::Full,
+            }
+        };
+        (start, end)
+    };
+
+    if len == 0 || range.0 >= len || range.0 > range.1 {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial(range.0, range.1)
+}
+
+fn error_response(status: StatusCode, message: String) -> Response {
+    let res = ApiResponse {
+        logs: vec![message],
+        file: None,
+        files: Vec::new(),
+        head: None,
+    };
+    (status, Json(res)).into_response()
+}
+
+fn content_disposition(disposition: &str, file_name: &str) -> String {
+    let sanitized: String = file_name
+        .chars()
+        .map(|c| if c == '"' || c == '\\' || c.is_control() { '_' } else { c })
+        .collect();
+    format!("{}; filename=\"{}\"", disposition, sanitized)
+}
+
+/// Serves the original bytes of a file with its MIME type, ETag and HTTP range support.
+pub async fn explorer_file_content(
+    State(server_database): State<Db>,
+    ContentUser(user): ContentUser,
+    Path(file_id): Path<i32>,
+    headers: HeaderMap,
+) -> Response {
+    tracing::debug!("explorer_file_content {}", file_id);
+
+    let mut conn = match server_database.pool.get() {
+        Ok(connection) => connection,
+        Err(e) => {
+            tracing::error!("Failed to get database connection: {}", e);
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to get database connection".to_string(),
+            );
+        }
+    };
+
+    let page_data = match authorize_file_access(&user, file_id, FileAccess::Read, &mut conn) {
+        Ok(page) => page,
+        Err(e) => {
+            tracing::error!("Authorization failed: {}", e);
+            return error_response(e.status_code(), format!("Error : {}", e));
+        }
+    };
+
+    let doc = match AquaDocument::parse(page_data.page_data.as_bytes()) {
+        Ok(doc) => doc,
+        Err(e) => {
+            tracing::error!("Failed to parse page data record: {}", e);
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error : Failure to parse Page Data Object".to_string(),
+            );
+        }
+    };
+
+    let stored_file = match doc.stored_file() {
+        Some(file) => file,
+        None => {
+            return error_response(
+                StatusCode::NOT_FOUND,
+                "Error : genesis revision does not contain file info".to_string(),
+            )
+        }
+    };
+
+    let file_hash = stored_file.blob_key;
+    let etag = format!("\"{}\"", file_hash);
+    let path = match blob_path_by_key(&file_hash) {
+        Ok(path) => path,
+        Err(e) => {
+            tracing::error!("Refusing to serve file of {}: {}", file_id, e);
+            return error_response(
+                StatusCode::NOT_FOUND,
+                "Error : file content not found".to_string(),
+            );
+        }
+    };
+
+    // chains written before the blob store keep the file inline
+    let inline_data: Option<Vec<u8>> = if path.is_file() {
+        None
+    } else {
+        let bytes = stored_file.inline_data;
+        if bytes.is_empty() {
+            tracing::error!("Blob {} is missing", file_hash);
+            return error_response(
+                StatusCode::NOT_FOUND,
+                "Error : file content not found".to_string(),
+            );
+        }
+        Some(bytes)
+    };
+
+    let mut file = None;
+    let len = match &inline_data {
+        Some(bytes) => bytes.len() as u64,
+        None => match tokio::fs::File::open(&path).await {
+            Ok(opened) => {
+                let len = match opened.metadata().await {
+                    Ok(metadata) => metadata.len(),
+                    Err(e) => {
+                        return error_response(
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            format!("Error reading file: {}", e),
+                        )
+                    }
+                };
+                file = Some(opened);
+                len
+            }
+            Err(e) => {
+                tracing::error!("Failed to open blob {}: {}", file_hash, e);
+                return error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Error reading file: {}", e),
+                );
+            }
+        },
+    };
+
+    let mime_type = match get_content_type(&stored_file.file_name) {
+        Some(mime) => mime,
+        None => {
+            let mut head = vec![0u8; SNIFF_LENGTH.min(len as usize)];
+            match (&inline_data, file.as_mut()) {
+                (Some(bytes), _) => head.copy_from_slice(&bytes[..head.len()]),
+                (None, Some(opened)) => {
+                    if opened.read_exact(&mut head).await.is_err() {
+                        head.clear();
+                    }
+                }
+                _ => head.clear(),
+            }
+            get_file_info(STANDARD.encode(&head))
+                .map(|info| info.mime_type)
+                .unwrap_or_else(|_| "application/octet-stream".to_string())
+        }
+    };
+
+    let if_none_match = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok());
+    if let Some(value) = if_none_match {
+        if value.split(',').any(|tag| tag.trim() == etag || tag.trim() == "*") {
+            return Response::builder()
+                .status(StatusCode::NOT_MODIFIED)
+                .header(header::ETAG, &etag)
+                .body(Body::empty())
+                .unwrap();
+        }
+    }
+
+    // a range is only honoured if If-Range still matches the current representation
+    let if_range_matches = headers
+        .get(header::IF_RANGE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.trim() == etag)
+        .unwrap_or(true);
+    let range = match headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+    {
+        Some(value) if if_range_matches => parse_range(value, len),
+        _ => ByteRange::Full,
+    };
+
+    let (status, start, end) = match range {
+        ByteRange::Full => (StatusCode::OK, 0, len.saturating_sub(1)),
+        ByteRange::Partial(start, end) => (StatusCode::PARTIAL_CONTENT, start, end),
+        ByteRange::Unsatisfiable => {
+            return Response::builder()
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", len))
+                .header(header::ETAG, &etag)
+                .body(Body::empty())
+                .unwrap();
+        }
+    };
+    let content_length = if len == 0 { 0 } else { end - start + 1 };
+
+    let body = match (inline_data, file) {
+        (Some(bytes), _) => {
+            Body::from(bytes[start as usize..(start + content_length) as usize].to_vec())
+        }
+        (None, Some(mut opened)) => {
+            if let Err(e) = opened.seek(SeekFrom::Start(start)).await {
+                return error_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    format!("Error reading file: {}", e),
+                );
+            }
+            Body::from_stream(ReaderStream::new(opened.take(content_length)))
+        }
+        _ => Body::empty(),
+    };
+
+    let mut builder = Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, mime_type)
+        .header(header::CONTENT_LENGTH, content_length)
+        .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::ETAG, &etag)
+        .header(
+            header::CONTENT_DISPOSITION,
+            HeaderValue::from_str(&content_disposition("inline", &stored_file.file_name))
+                .unwrap_or_else(|_| HeaderValue::from_static("inline")),
+        );
+    if status == StatusCode::PARTIAL_CONTENT {
+        builder = builder.header(
+            header::CONTENT_RANGE,
+            format!("bytes {}-{}/{}", start, end, len),
+        );
+    }
+
+    builder.body(body).unwrap_or_else(|e| {
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Error building response: {}", e),
+        )
+    })
+}
+
+/// Exports a file as a zip bundle holding the manifest, the chain JSON and the original file.
+pub async fn explorer_export_bundle(
+    State(server_database): State<Db>,
+    user: AuthenticatedUser,
+    Path(file_id): Path<i32>,
+) -> Response {
+    tracing::debug!("explorer_export_bundle {}", file_id);
+
+    let mut conn = match server_database.pool.get() {
+        Ok(connection) => connection,
+        Err(e) => {
+            tracing::error!("Failed to get database connection: {}", e);
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to get database connection".to_string(),
+            );
+        }
+    };
+
+    let page_data = match authorize_file_access(&user, file_id, FileAccess::Read, &mut conn) {
+        Ok(page) => page,
+        Err(e) => {
+            tracing::error!("Authorization failed: {}", e);
+            return error_response(e.status_code(), format!("Error : {}", e));
+        }
+    };
+
+    let document = match AquaDocument::parse(page_data.page_data.as_bytes()) {
+        Ok(document) => document,
+        Err(e) => {
+            tracing::error!("Failed to parse page data record: {}", e);
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error : Failure to parse Page Data Object".to_string(),
+            );
+        }
+    };
+
+    // EIP-712 signatures only verify with the typed data recorded here
+    let signature_modes = match load_document_signature_schemes(&document, &mut conn) {
+        Ok(schemes) => export_signature_modes(&schemes),
+        Err(e) => {
+            tracing::error!("Failed to load signature modes: {}", e);
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error : failed to load signature modes {}", e),
+            );
+        }
+    };
+
+    let bundle = match build_bundle(&document, &signature_modes) {
+        Ok(bundle) => bundle,
+        Err(e) => {
+            tracing::error!("Failed to build bundle: {}", e);
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error : failed to build bundle {}", e),
+            );
+        }
+    };
+
+    let disposition = content_disposition("attachment", &format!("{}.aqua.zip", page_data.name));
+
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/zip")
+        .header(header::CONTENT_LENGTH, bundle.len())
+        .header(
+            header::CONTENT_DISPOSITION,
+            HeaderValue::from_str(&disposition)
+                .unwrap_or_else(|_| HeaderValue::from_static("attachment")),
+        )
+        .body(Body::from(bundle))
+        .unwrap_or_else(|e| {
+            error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error building response: {}", e),
+            )
+        })
+}
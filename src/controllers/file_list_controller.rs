@@ -0,0 +1,169 @@
+use crate::auth::session::AuthenticatedUser;
+use crate::db::file_search::{search_file_ids, search_words};
+use crate::db::pages_db::{fetch_file_summaries, fetch_file_summaries_by_ids};
+use crate::models::file::FileListCursor;
+use crate::models::input::{FileListInput, FileSearchInput};
+use crate::models::page_data::FileListApiResponse;
+use crate::Db;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{NaiveDate, NaiveDateTime};
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+const MAX_PAGE_SIZE: i64 = 200;
+
+/// `YYYY-MM-DD` or `YYYY-MM-DD HH:MM:SS` in the form `created_at` is stored in.
+fn created_at_bound(value: &str) -> Result<String, String> {
+    let value = value.trim();
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map(|date| date.and_hms_opt(0, 0, 0).unwrap_or_default())
+        })
+        .map(|time| time.format("%Y-%m-%d %H:%M:%S").to_string())
+        .map_err(|_| format!("invalid date {}, expected YYYY-MM-DD or YYYY-MM-DD HH:MM:SS", value))
+}
+
+/// Lists the files of the signed in user a page at a time, without their chains. Follow
+/// `next_cursor` with the same filters and sort to get the next page.
+pub async fn explorer_list_files(
+    State(server_database): State<Db>,
+    user: AuthenticatedUser,
+    Query(mut input): Query<FileListInput>,
+) -> (StatusCode, Json<FileListApiResponse>) {
+    tracing::debug!("explorer_list_files");
+    let mut res = FileListApiResponse {
+        logs: Vec::new(),
+        files: Vec::new(),
+        next_cursor: None,
+    };
+
+    let limit = input.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        res.logs
+            .push(format!("Error : limit must be between 1 and {}", MAX_PAGE_SIZE));
+        return (StatusCode::BAD_REQUEST, Json(res));
+    }
+
+    let cursor = match input
+        .cursor
+        .as_deref()
+        .filter(|cursor| !cursor.is_empty())
+        .map(FileListCursor::decode)
+        .transpose()
+    {
+        Ok(cursor) => cursor,
+        Err(e) => {
+            res.logs.push(format!("Error : {}", e));
+            return (StatusCode::BAD_REQUEST, Json(res));
+        }
+    };
+    if let Some(cursor) = &cursor {
+        if cursor.sort != input.sort || cursor.order != input.order {
+            res.logs
+                .push("Error : cursor belongs to a listing in another order".to_string());
+            return (StatusCode::BAD_REQUEST, Json(res));
+        }
+    }
+
+    for bound in [&mut input.created_from, &mut input.created_before] {
+        if let Some(value) = bound.as_deref().filter(|value| !value.is_empty()) {
+            match created_at_bound(value) {
+                Ok(normalized) => *bound = Some(normalized),
+                Err(e) => {
+                    res.logs.push(format!("Error : {}", e));
+                    return (StatusCode::BAD_REQUEST, Json(res));
+                }
+            }
+        } else {
+            *bound = None;
+        }
+    }
+
+    let mut conn = match server_database.pool.get() {
+        Ok(connection) => connection,
+        Err(e) => {
+            tracing::error!("Failed to get database connection: {}", e);
+            res.logs.push("Failed to get database connection".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    let mut files =
+        match fetch_file_summaries(&user.address, &input, cursor.as_ref(), limit, &mut conn) {
+            Ok(files) => files,
+            Err(e) => {
+                tracing::error!("Failed to list files: {}", e);
+                res.logs.push(format!("Error : {}", e));
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+            }
+        };
+
+    if files.len() as i64 > limit {
+        files.truncate(limit as usize);
+        res.next_cursor = files
+            .last()
+            .map(|file| FileListCursor::after(file, input.sort, input.order).encode());
+    }
+    if files.is_empty() {
+        res.logs.push("No pages found".to_string());
+    }
+    res.files = files;
+    (StatusCode::OK, Json(res))
+}
+
+/// Finds files of the signed in user by name, extension, verification hash (genesis or any
+/// later revision), file hash, signer address or witness transaction hash.
+pub async fn explorer_search_files(
+    State(server_database): State<Db>,
+    user: AuthenticatedUser,
+    Query(input): Query<FileSearchInput>,
+) -> (StatusCode, Json<FileListApiResponse>) {
+    tracing::debug!("explorer_search_files");
+    let mut res = FileListApiResponse {
+        logs: Vec::new(),
+        files: Vec::new(),
+        next_cursor: None,
+    };
+
+    let limit = input.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if !(1..=MAX_PAGE_SIZE).contains(&limit) {
+        res.logs
+            .push(format!("Error : limit must be between 1 and {}", MAX_PAGE_SIZE));
+        return (StatusCode::BAD_REQUEST, Json(res));
+    }
+    let words = search_words(&input.q);
+    if words.is_empty() {
+        res.logs.push("Error : search query is empty".to_string());
+        return (StatusCode::BAD_REQUEST, Json(res));
+    }
+
+    let mut conn = match server_database.pool.get() {
+        Ok(connection) => connection,
+        Err(e) => {
+            tracing::error!("Failed to get database connection: {}", e);
+            res.logs.push("Failed to get database connection".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    let found = search_file_ids(&user.address, &words, limit, &mut conn)
+        .and_then(|file_ids| fetch_file_summaries_by_ids(&user.address, &file_ids, &mut conn));
+    match found {
+        Ok(files) => {
+            if files.is_empty() {
+                res.logs.push("No pages found".to_string());
+            }
+            res.files = files;
+            (StatusCode::OK, Json(res))
+        }
+        Err(e) => {
+            tracing::error!("Failed to search files: {}", e);
+            res.logs.push(format!("Error : {}", e));
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(res))
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use crate::auth::permissions::{authorize_file_access, FileAccess};
+use crate::auth::session::AuthenticatedUser;
+use crate::blob_store::page_data_for_response;
+use crate::db::chain_links::insert_chain_link;
+use crate::db::pages_db::{update_page_data, PageUpdateError};
+use crate::links::resolve_link_graph;
+use crate::models::file::FileInfo;
+use crate::models::input::LinkFileInput;
+use crate::models::page_data::{ApiResponse, LinkGraphApiResponse};
+use crate::models::ChainLinkTable;
+use crate::protocol::{v1_3, AquaDocument};
+use crate::Db;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Form, Json,
+};
+use chrono::Utc;
+use diesel::Connection;
+
+/// Appends a link revision to the chain of `file_id` pointing at a revision of `linked_file_id`.
+pub async fn explorer_link_file(
+    State(server_database): State<Db>,
+    user: AuthenticatedUser,
+    Form(input): Form<LinkFileInput>,
+) -> (StatusCode, Json<ApiResponse>) {
+    tracing::debug!("explorer_link_file");
+    let mut res = ApiResponse {
+        logs: Vec::new(),
+        file: None,
+        files: Vec::new(),
+        head: None,
+    };
+
+    if input.file_id == input.linked_file_id {
+        res.logs.push("Error : a chain cannot link to itself".to_string());
+        return (StatusCode::BAD_REQUEST, Json(res));
+    }
+
+    let mut conn = match server_database.pool.get() {
+        Ok(connection) => connection,
+        Err(e) => {
+            tracing::error!("Failed to get database connection: {}", e);
+            res.logs.push("Failed to get database connection".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    let mut page = match authorize_file_access(&user, input.file_id, FileAccess::Write, &mut conn) {
+        Ok(page) => page,
+        Err(e) => {
+            tracing::error!("Authorization failed: {}", e);
+            res.logs.push(format!("Error : {}", e));
+            return (e.status_code(), Json(res));
+        }
+    };
+    // linking discloses nothing beyond a hash, reading the target is enough
+    let linked_page =
+        match authorize_file_access(&user, input.linked_file_id, FileAccess::Read, &mut conn) {
+            Ok(page) => page,
+            Err(e) => {
+                tracing::error!("Authorization failed: {}", e);
+                res.logs.push(format!("Error : linked file {}", e));
+                return (e.status_code(), Json(res));
+            }
+        };
+
+    let mut chain = match AquaDocument::parse(page.page_data.as_bytes()) {
+        Ok(AquaDocument::V1_3(chain)) => chain,
+        Ok(AquaDocument::V1_2(_)) => {
+            res.logs.push(
+                "Error : link revisions need a v1.3 chain, upgrade it with /explorer_upgrade_chain"
+                    .to_string(),
+            );
+            return (StatusCode::UNPROCESSABLE_ENTITY, Json(res));
+        }
+        Err(e) => {
+            tracing::error!("Failed to parse page data record: {}", e);
+            res.logs
+                .push("Error : Failure to parse Page Data Object".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+    let linked = match AquaDocument::parse(linked_page.page_data.as_bytes()) {
+        Ok(document) => document,
+        Err(e) => {
+            tracing::error!("Failed to parse page data record: {}", e);
+            res.logs
+                .push("Error : Failure to parse Page Data Object of the linked file".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    let linked_hash = match input
+        .linked_verification_hash
+        .map(|hash| hash.trim().to_string())
+        .filter(|hash| !hash.is_empty())
+    {
+        Some(hash) if linked.contains_revision(&hash) => hash,
+        Some(hash) => {
+            res.logs
+                .push(format!("Error : linked chain does not contain {}", hash));
+            return (StatusCode::BAD_REQUEST, Json(res));
+        }
+        None => match linked.latest_verification_hash() {
+            Some(hash) => hash,
+            None => {
+                res.logs.push("Error : linked chain has no revisions".to_string());
+                return (StatusCode::BAD_REQUEST, Json(res));
+            }
+        },
+    };
+    let linked_file_hash = linked.file_hash().unwrap_or_default();
+
+    let revision_hash = match v1_3::append_link_revision(&mut chain, &linked_hash, &linked_file_hash)
+    {
+        Ok(hash) => hash,
+        Err(e) => {
+            res.logs.push(format!("Error : {}", e));
+            return (StatusCode::BAD_REQUEST, Json(res));
+        }
+    };
+    page.page_data = match AquaDocument::V1_3(chain).to_storage() {
+        Ok(json) => json,
+        Err(e) => {
+            tracing::error!("Failed to serialize page data: {}", e);
+            res.logs.push(e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    // the link is indexed next to the chain so the graph can be resolved without scanning pages
+    let link = ChainLinkTable {
+        id: None,
+        file_id: page.id,
+        revision_hash,
+        linked_file_id: linked_page.id,
+        linked_verification_hash: linked_hash,
+        created_time: Utc::now().naive_utc().format("%Y-%m-%d %H:%M:%S").to_string(),
+    };
+    let update_result = conn.transaction::<_, PageUpdateError, _>(|conn| {
+        update_page_data(page.clone(), conn)?;
+        insert_chain_link(link, conn)?;
+        Ok(())
+    });
+    if let Err(e) = update_result {
+        tracing::error!("Failed to save link revision: {}", e);
+        res.logs.push(format!("Error : {}", e));
+        res.head = e.head();
+        return (e.status_code(), Json(res));
+    }
+
+    res.logs.push(format!("Success : linked to file {}", linked_page.id));
+    res.file = Some(FileInfo {
+        id: page.id as i64,
+        name: page.name,
+        extension: page.extension,
+        page_data: page_data_for_response(page.page_data),
+        mode: page.mode,
+        owner: page.owner,
+    });
+    (StatusCode::OK, Json(res))
+}
+
+/// The chains reachable from a file through link revisions.
+pub async fn explorer_file_links(
+    State(server_database): State<Db>,
+    user: AuthenticatedUser,
+    Path(file_id): Path<i32>,
+) -> (StatusCode, Json<LinkGraphApiResponse>) {
+    tracing::debug!("explorer_file_links {}", file_id);
+    link_graph_response(server_database, user, file_id, false).await
+}
+
+/// Like `explorer_file_links`, verifying every chain and that each link target exists and holds
+/// the linked revision.
+pub async fn explorer_verify_file_links(
+    State(server_database): State<Db>,
+    user: AuthenticatedUser,
+    Path(file_id): Path<i32>,
+) -> (StatusCode, Json<LinkGraphApiResponse>) {
+    tracing::debug!("explorer_verify_file_links {}", file_id);
+    link_graph_response(server_database, user, file_id, true).await
+}
+
+async fn link_graph_response(
+    server_database: Db,
+    user: AuthenticatedUser,
+    file_id: i32,
+    verify: bool,
+) -> (StatusCode, Json<LinkGraphApiResponse>) {
+    let mut res = LinkGraphApiResponse {
+        logs: Vec::new(),
+        valid: None,
+        chains: Vec::new(),
+        links: Vec::new(),
+    };
+
+    let mut conn = match server_database.pool.get() {
+        Ok(connection) => connection,
+        Err(e) => {
+            tracing::error!("Failed to get database connection: {}", e);
+            res.logs.push("Failed to get database connection".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    let graph = match resolve_link_graph(&user, file_id, verify, &mut conn).await {
+        Ok(graph) => graph,
+        Err((status, message)) => {
+            res.logs.push(message);
+            return (status, Json(res));
+        }
+    };
+
+    let mut status = StatusCode::OK;
+    res.logs.extend(
+        graph
+            .unresolved
+            .iter()
+            .map(|unresolved| format!("Unresolved link target {}", unresolved)),
+    );
+    if verify {
+        let valid = graph.valid();
+        for chain in &graph.chains {
+            if let Some(report) = &chain.report {
+                res.logs.extend(report.failures());
+            }
+        }
+        if valid {
+            res.logs.push("Linked chains valid".to_string());
+        } else {
+            status = StatusCode::BAD_REQUEST;
+        }
+        res.valid = Some(valid);
+    }
+    res.chains = graph.chains;
+    res.links = graph.links;
+    (status, Json(res))
+}
@@ -2,4 +2,10 @@ pub  mod api_controller;
 pub mod user_profile_controller;
 pub mod auth_controller;
 pub mod share_controller;
-pub mod versions_controller;
\ No newline at end of file
+pub mod file_content_controller;
+pub mod witness_controller;
+pub mod signing_controller;
+pub mod versions_controller;
+pub mod protocol_controller;
+pub mod link_controller;
+pub mod file_list_controller;
\ No newline at end of file
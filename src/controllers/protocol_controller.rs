@@ -0,0 +1,153 @@
+use crate::auth::permissions::{authorize_file_access, FileAccess};
+use crate::auth::session::AuthenticatedUser;
+use crate::blob_store::{
+    genesis_file, page_data_for_response, read_blob, remove_unused_blobs, store_blob_sha256,
+};
+use crate::db::pages_db::update_page_data;
+use crate::models::file::FileInfo;
+use crate::models::input::UpgradeChainInput;
+use crate::models::page_data::ApiResponse;
+use crate::protocol::{v1_3, AquaDocument};
+use crate::signing::load_signature_schemes;
+use crate::Db;
+use axum::{extract::State, http::StatusCode, Form, Json};
+
+/// Rewrites a v1.2 chain as v1.3. Signatures and witnesses are carried over with the
+/// v1.2 hashes they were made over, the file is re-keyed by its SHA-256.
+pub async fn explorer_upgrade_chain(
+    State(server_database): State<Db>,
+    user: AuthenticatedUser,
+    Form(input): Form<UpgradeChainInput>,
+) -> (StatusCode, Json<ApiResponse>) {
+    tracing::debug!("explorer_upgrade_chain");
+    let mut res = ApiResponse {
+        logs: Vec::new(),
+        file: None,
+        files: Vec::new(),
+        head: None,
+    };
+
+    let mut conn = match server_database.pool.get() {
+        Ok(connection) => connection,
+        Err(e) => {
+            tracing::error!("Failed to get database connection: {}", e);
+            res.logs.push("Failed to get database connection".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    // every hash of the chain changes, only the owner may do that
+    let mut page = match authorize_file_access(&user, input.file_id, FileAccess::Owner, &mut conn)
+    {
+        Ok(page) => page,
+        Err(e) => {
+            tracing::error!("Authorization failed: {}", e);
+            res.logs.push(format!("Error : {}", e));
+            return (e.status_code(), Json(res));
+        }
+    };
+
+    let doc = match AquaDocument::parse(page.page_data.as_bytes()) {
+        Ok(AquaDocument::V1_2(doc)) => doc,
+        Ok(AquaDocument::V1_3(_)) => {
+            res.logs.push("Error : chain is already v1.3".to_string());
+            return (StatusCode::BAD_REQUEST, Json(res));
+        }
+        Err(e) => {
+            tracing::error!("Failed to parse page data record: {}", e);
+            res.logs
+                .push("Error : Failure to parse Page Data Object".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    let chain = match doc.pages.first() {
+        Some(chain) => chain,
+        None => {
+            res.logs.push("Error : file has no chain".to_string());
+            return (StatusCode::BAD_REQUEST, Json(res));
+        }
+    };
+    let (file_hash, file_content) = match genesis_file(&doc) {
+        Some(file) => file,
+        None => {
+            res.logs
+                .push("Error : genesis revision does not contain file info".to_string());
+            return (StatusCode::BAD_REQUEST, Json(res));
+        }
+    };
+
+    // chains written before the blob store keep the file inline
+    let inline_data: &[u8] = file_content.data.as_ref();
+    let bytes = if inline_data.is_empty() {
+        match read_blob(&file_hash) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                tracing::error!("Failed to read file: {}", e);
+                res.logs.push("Error : file content not found".to_string());
+                return (StatusCode::NOT_FOUND, Json(res));
+            }
+        }
+    } else {
+        inline_data.to_vec()
+    };
+    let file_sha256 = match store_blob_sha256(&bytes) {
+        Ok(key) => key,
+        Err(e) => {
+            tracing::error!("Failed to store file: {}", e);
+            res.logs.push(format!("Failed to store file: {}", e));
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    let schemes = match load_signature_schemes(chain, &mut conn) {
+        Ok(schemes) => schemes,
+        Err(e) => {
+            tracing::error!("Failed to load signature modes: {}", e);
+            res.logs.push(format!("Failed to load signature modes: {}", e));
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    let upgraded =
+        match v1_3::upgrade_chain(chain, &file_content.filename, &file_sha256, &schemes) {
+            Ok(upgraded) => upgraded,
+            Err(e) => {
+                res.logs.push(format!("Error : {}", e));
+                return (StatusCode::BAD_REQUEST, Json(res));
+            }
+        };
+    if let Err(e) = upgraded.checked_file_hash() {
+        res.logs.push(format!("Error : {}", e));
+        return (StatusCode::BAD_REQUEST, Json(res));
+    }
+
+    page.page_data = match AquaDocument::V1_3(upgraded).to_storage() {
+        Ok(json) => json,
+        Err(e) => {
+            tracing::error!("Failed to serialize page data: {}", e);
+            res.logs.push(e);
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    if let Err(e) = update_page_data(page.clone(), &mut conn) {
+        tracing::error!("Failed to update page data: {}", e);
+        res.logs.push(format!("Error : {}", e));
+        res.head = e.head();
+        return (e.status_code(), Json(res));
+    }
+    // the file is stored under its SHA-256 now
+    remove_unused_blobs(&[file_hash.to_string()], &mut conn);
+
+    res.logs.push("Success : chain upgraded to v1.3".to_string());
+    res.file = Some(FileInfo {
+        id: page.id as i64,
+        name: page.name,
+        extension: page.extension,
+        page_data: page_data_for_response(page.page_data),
+        mode: page.mode,
+        owner: page.owner,
+    });
+    (StatusCode::OK, Json(res))
+}
@@ -1,4 +1,11 @@
+use crate::auth::permissions::{authorize_file_access, FileAccess, WRITE_ACCESS};
+use crate::auth::session::AuthenticatedUser;
+use crate::blob_store::page_data_for_response;
+use crate::db::file_access::{
+    delete_file_access, fetch_file_access_for_address, insert_file_access,
+};
 use crate::db::share::insert_share_data;
+use crate::models::FileAccessTable;
 use crate::models::page_data::ApiResponse;
 use crate::{db::share::fetch_share_data_by_address, models::ShareDataTable};
 
@@ -6,7 +13,7 @@ use crate::db::pages_db::{
      delete_all_data, delete_all_user_files, delete_page_data,
     fetch_all_pages_data_per_user, fetch_page_data, insert_page_data, update_page_data,
 };
-use crate::models::share_data::{CreateShareData, ShareDataResponse};
+use crate::models::share_data::{CreateShareData, FileAccessInput, ShareDataResponse};
 use crate::util::{
     check_if_page_data_revision_are_okay, check_or_generate_domain, compute_content_hash,
     get_content_type, get_file_info, make_empty_hash, update_env_file,
@@ -21,7 +28,7 @@ use axum::{
     routing::{get, post},
     BoxError, Form, Json, Router,
 };
-use chrono::Utc;
+use chrono::{NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use tracing_subscriber::fmt::format;
@@ -29,6 +36,7 @@ use tracing_subscriber::fmt::format;
 // Handler with path and query parameters combined
 pub async fn get_share_data(
     State(server_database): State<Db>,
+    _user: AuthenticatedUser,
     Path(share_identifier): Path<String>,
 ) -> (StatusCode, Json<ShareDataResponse>) {
     let mut log_data: Vec<String> = Vec::new();
@@ -86,7 +94,8 @@ pub async fn get_share_data(
 
         return (StatusCode::NOT_FOUND, Json(res));
     }
-    let page_data = page_data_result.unwrap();
+    let mut page_data = page_data_result.unwrap();
+    page_data.page_data = page_data_for_response(page_data.page_data);
 
     res.file_data = Some(page_data);
     res.share_data = Some(firs_share_payload_data.clone());
@@ -96,6 +105,7 @@ pub async fn get_share_data(
 
 pub async fn save_share_data(
     State(server_database): State<Db>,
+    user: AuthenticatedUser,
     Form(input): Form<CreateShareData>,
 ) -> (StatusCode, Json<ApiResponse>) {
     tracing::debug!("explorer_delete_file");
@@ -105,6 +115,7 @@ pub async fn save_share_data(
         logs: log_data,
         file: None,
         files: Vec::new(),
+        head: None,
     };
 
     // Get the name parameter from the input
@@ -136,17 +147,16 @@ pub async fn save_share_data(
         }
     };
 
-    // confirm if page data exist
+    // confirm if page data exist and belongs to the caller
+    let page_data_result =
+        authorize_file_access(&user, input.file_id, FileAccess::Owner, &mut conn);
 
-    let page_data_result = fetch_page_data(input.file_id.clone(), &mut conn);
+    if let Err(e) = &page_data_result {
+        tracing::error!("Authorization failed: {}", e);
 
-    if page_data_result.is_err() {
-        tracing::error!("Failed not found ",);
-
-        res.logs
-            .push("Failed data not found in database".to_string());
+        res.logs.push(format!("Error : {}", e));
 
-        return (StatusCode::NOT_FOUND, Json(res));
+        return (e.status_code(), Json(res));
     }
     
     // 1. Get current UTC time as a DateTime<Utc>
@@ -179,13 +189,145 @@ pub async fn save_share_data(
     // update db file is  shared 
     let  update_result =  update_page_data(page_data, & mut conn);
 
-    if update_result.is_err(){
+    if let Err(e) = update_result {
         
         res.logs
-            .push(format!("error updating system  {:#?}",update_result.err() ));
+            .push(format!("error updating system  {}", e));
 
-        return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        res.head = e.head();
+        return (e.status_code(), Json(res));
     }
 
     return (StatusCode::OK, Json(res));
 }
+
+pub async fn explorer_grant_file_access(
+    State(server_database): State<Db>,
+    user: AuthenticatedUser,
+    Form(input): Form<FileAccessInput>,
+) -> (StatusCode, Json<ApiResponse>) {
+    tracing::debug!("explorer_grant_file_access");
+
+    let mut res: ApiResponse = ApiResponse {
+        logs: Vec::new(),
+        file: None,
+        files: Vec::new(),
+        head: None,
+    };
+
+    if input.file_id == 0 || input.address.is_empty() {
+        res.logs.push("Error : file id or address is empty".to_string());
+
+        return (StatusCode::BAD_REQUEST, Json(res));
+    }
+
+    if input.address.parse::<ethaddr::Address>().is_err() {
+        res.logs.push(format!("Error : invalid wallet address {}", input.address));
+
+        return (StatusCode::BAD_REQUEST, Json(res));
+    }
+
+    let mut conn = match server_database.pool.get() {
+        Ok(connection) => connection,
+        Err(e) => {
+            res.logs.push("Failed to get database connection".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    if let Err(e) = authorize_file_access(&user, input.file_id, FileAccess::Owner, &mut conn) {
+        tracing::error!("Authorization failed: {}", e);
+        res.logs.push(format!("Error : {}", e));
+
+        return (e.status_code(), Json(res));
+    }
+
+    match fetch_file_access_for_address(input.file_id, &input.address, &mut conn) {
+        Ok(grants) if grants.iter().any(|grant| grant.access == WRITE_ACCESS) => {
+            res.logs.push("Address already has write access".to_string());
+            return (StatusCode::OK, Json(res));
+        }
+        Ok(_) => {}
+        Err(e) => {
+            res.logs.push(format!("Error : {}", e));
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    }
+
+    let naive_datetime: NaiveDateTime = Utc::now().naive_utc();
+    let grant = FileAccessTable {
+        id: None,
+        file_id: input.file_id,
+        address: input.address.clone(),
+        access: WRITE_ACCESS.to_string(),
+        created_time: naive_datetime.format("%Y-%m-%d %H:%M:%S").to_string(),
+    };
+
+    if let Err(e) = insert_file_access(grant, &mut conn) {
+        tracing::error!("Failed to grant file access: {}", e);
+        res.logs.push(format!("Error : {}", e));
+
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+    }
+
+    res.logs.push(format!(
+        "Write access to file {} granted to {}",
+        input.file_id, input.address
+    ));
+    (StatusCode::CREATED, Json(res))
+}
+
+pub async fn explorer_revoke_file_access(
+    State(server_database): State<Db>,
+    user: AuthenticatedUser,
+    Form(input): Form<FileAccessInput>,
+) -> (StatusCode, Json<ApiResponse>) {
+    tracing::debug!("explorer_revoke_file_access");
+
+    let mut res: ApiResponse = ApiResponse {
+        logs: Vec::new(),
+        file: None,
+        files: Vec::new(),
+        head: None,
+    };
+
+    if input.file_id == 0 || input.address.is_empty() {
+        res.logs.push("Error : file id or address is empty".to_string());
+
+        return (StatusCode::BAD_REQUEST, Json(res));
+    }
+
+    let mut conn = match server_database.pool.get() {
+        Ok(connection) => connection,
+        Err(e) => {
+            res.logs.push("Failed to get database connection".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    if let Err(e) = authorize_file_access(&user, input.file_id, FileAccess::Owner, &mut conn) {
+        tracing::error!("Authorization failed: {}", e);
+        res.logs.push(format!("Error : {}", e));
+
+        return (e.status_code(), Json(res));
+    }
+
+    match delete_file_access(input.file_id, &input.address, &mut conn) {
+        Ok(0) => {
+            res.logs.push("No access grant found to revoke".to_string());
+            (StatusCode::NOT_FOUND, Json(res))
+        }
+        Ok(_) => {
+            res.logs.push(format!(
+                "Write access to file {} revoked for {}",
+                input.file_id, input.address
+            ));
+            (StatusCode::OK, Json(res))
+        }
+        Err(e) => {
+            tracing::error!("Failed to revoke file access: {}", e);
+            res.logs.push(format!("Error : {}", e));
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(res))
+        }
+    }
+}
@@ -0,0 +1,764 @@
+use crate::auth::nonce::session_is_expired;
+use crate::auth::permissions::{authorize_file_access, FileAccess};
+use crate::auth::session::AuthenticatedUser;
+use crate::blob_store::page_data_for_response;
+use crate::db::pages_db::{fetch_page_data, update_page_data, PageUpdateError};
+use crate::db::signature_requests::{
+    fetch_pending_signature_requests, fetch_signature_request, fetch_signature_requests_by_file,
+    fetch_signature_requests_for_signer, insert_signature_request,
+    update_signature_request_status,
+};
+use crate::eip712::typed_data_request;
+use crate::models::file::FileInfo;
+use crate::models::input::ServerSignInput;
+use crate::models::page_data::{ApiResponse, SignatureModesApiResponse, TypedDataApiResponse};
+use crate::models::signature_requests::{
+    CreateSignatureRequestInput, SignRequestInput, SignatureRequestApiResponse,
+    SignatureRequestInfo, SIGNATURE_REQUEST_DECLINED, SIGNATURE_REQUEST_EXPIRED,
+    SIGNATURE_REQUEST_PENDING, SIGNATURE_REQUEST_SIGNED,
+};
+use crate::models::{PagesTable, SignatureRequestTable};
+use crate::protocol::{AquaDocument, ProtocolVersion};
+use crate::server_signer::server_signer;
+use crate::signing::{
+    append_document_signature, export_signature_modes, load_document_signature_schemes,
+    record_signature_scheme, signature_scheme, typed_data_for, ExportedSignatureModes,
+};
+use crate::verification::{check_wallet_signature, SignatureScheme};
+use crate::Db;
+use aqua_verifier_rs_types::models::public_key::PublicKey;
+use aqua_verifier_rs_types::models::signature::Signature;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Form, Json,
+};
+use chrono::{Duration, Utc};
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use diesel::Connection;
+use crate::db::DbConnection;
+extern crate serde_json_path_to_error as serde_json;
+
+/// Longest a signature request may stay open, in seconds (one year).
+const MAX_SIGNATURE_REQUEST_LIFETIME: i64 = 365 * 24 * 60 * 60;
+
+/// Signs the latest revision of a file with the container-managed key.
+/// Restricted to signed in addresses listed in `SERVER_SIGNER_ALLOWED_ADDRESSES`.
+pub async fn explorer_server_sign_revision(
+    State(server_database): State<Db>,
+    user: AuthenticatedUser,
+    Form(input): Form<ServerSignInput>,
+) -> (StatusCode, Json<ApiResponse>) {
+    tracing::debug!("explorer_server_sign_revision");
+    let mut res = ApiResponse {
+        logs: Vec::new(),
+        file: None,
+        files: Vec::new(),
+        head: None,
+    };
+
+    let signer = match server_signer() {
+        Some(signer) => signer,
+        None => {
+            res.logs
+                .push("Error : server signing is not configured".to_string());
+            return (StatusCode::NOT_IMPLEMENTED, Json(res));
+        }
+    };
+
+    if !signer.is_allowed(&user.address) {
+        tracing::error!("{} is not allowed to use the server signer", user.address);
+        res.logs.push(format!(
+            "Error : {} is not allowed to use the server signer",
+            user.address
+        ));
+        return (StatusCode::FORBIDDEN, Json(res));
+    }
+
+    let mut conn = match server_database.pool.get() {
+        Ok(connection) => connection,
+        Err(e) => {
+            tracing::error!("Failed to get database connection: {}", e);
+            res.logs.push("Failed to get database connection".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    let mut page_data =
+        match authorize_file_access(&user, input.file_id, FileAccess::Write, &mut conn) {
+            Ok(page) => page,
+            Err(e) => {
+                tracing::error!("Authorization failed: {}", e);
+                res.logs.push(format!("Error : {}", e));
+                return (e.status_code(), Json(res));
+            }
+        };
+
+    let mut document = match AquaDocument::parse(page_data.page_data.as_bytes()) {
+        Ok(document) => document,
+        Err(e) => {
+            tracing::error!("Failed to parse page data record: {}", e);
+            res.logs
+                .push("Error : Failure to parse Page Data Object".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    let previous_hash = match document.latest_verification_hash() {
+        Some(hash) => hash,
+        None => {
+            res.logs.push("Error : chain has no revisions".to_string());
+            return (StatusCode::BAD_REQUEST, Json(res));
+        }
+    };
+
+    let signed = match signer.sign(&previous_hash).await {
+        Ok(signed) => signed,
+        Err(e) => {
+            tracing::error!("Server signing failed: {}", e);
+            res.logs.push(format!("Error : {}", e));
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    // the server signs with personal_sign, which needs no recorded scheme
+    let appended = append_document_signature(
+        &mut document,
+        signed.signature,
+        signed.public_key,
+        signed.wallet_address,
+        &SignatureScheme::PersonalSign,
+    )
+    .and_then(|_| document.to_storage());
+    page_data.page_data = match appended {
+        Ok(data) => data,
+        Err(e) => {
+            tracing::error!("Failed to append signature revision: {}", e);
+            res.logs
+                .push(format!("Error : failed to append signature revision: {}", e));
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    if let Err(e) = update_page_data(page_data.clone(), &mut conn) {
+        tracing::error!("Failed to update page data: {}", e);
+        res.logs.push(format!("Failed to update page data : {}", e));
+        res.head = e.head();
+        return (e.status_code(), Json(res));
+    }
+
+    res.logs
+        .push(format!("Success : revision signed by {}", signer.address()));
+    res.file = Some(FileInfo {
+        id: page_data.id as i64,
+        name: page_data.name,
+        extension: page_data.extension,
+        page_data: page_data_for_response(page_data.page_data),
+        owner: page_data.owner,
+        mode: page_data.mode,
+    });
+    (StatusCode::OK, Json(res))
+}
+
+fn current_time() -> String {
+    Utc::now().naive_utc().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// Marks a pending request past its `expires_at` as expired.
+fn refresh_expiry(
+    request: &mut SignatureRequestTable,
+    conn: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<(), String> {
+    if request.status != SIGNATURE_REQUEST_PENDING
+        || !session_is_expired(&request.expires_at, Utc::now())
+    {
+        return Ok(());
+    }
+
+    let id = request.id.unwrap_or_default();
+    let now = current_time();
+    update_signature_request_status(id, SIGNATURE_REQUEST_EXPIRED, None, &now, conn)?;
+    request.status = SIGNATURE_REQUEST_EXPIRED.to_string();
+    request.updated_time = now;
+    Ok(())
+}
+
+fn request_info(request: SignatureRequestTable, page: Option<&PagesTable>) -> SignatureRequestInfo {
+    let previous_verification_hash = page
+        .filter(|_| request.status == SIGNATURE_REQUEST_PENDING)
+        .and_then(|page| AquaDocument::parse(page.page_data.as_bytes()).ok())
+        .and_then(|document| document.latest_verification_hash());
+
+    SignatureRequestInfo {
+        request,
+        file_name: page.map(|page| page.name.clone()).unwrap_or_default(),
+        previous_verification_hash,
+    }
+}
+
+/// Loads requests with their files, expired ones are updated on the way.
+fn load_request_infos(
+    requests: Vec<SignatureRequestTable>,
+    conn: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<Vec<SignatureRequestInfo>, String> {
+    let mut infos = Vec::new();
+    for mut request in requests {
+        refresh_expiry(&mut request, conn)?;
+        let page = fetch_page_data(request.file_id, conn).ok();
+        infos.push(request_info(request, page.as_ref()));
+    }
+    Ok(infos)
+}
+
+/// Asks the listed wallet addresses to sign a file, only the owner may do this.
+pub async fn explorer_create_signature_requests(
+    State(server_database): State<Db>,
+    user: AuthenticatedUser,
+    Json(input): Json<CreateSignatureRequestInput>,
+) -> (StatusCode, Json<SignatureRequestApiResponse>) {
+    tracing::debug!("explorer_create_signature_requests");
+    let mut res = SignatureRequestApiResponse {
+        logs: Vec::new(),
+        requests: Vec::new(),
+    };
+
+    if input.signers.is_empty() {
+        res.logs.push("Error : no signers given".to_string());
+        return (StatusCode::BAD_REQUEST, Json(res));
+    }
+
+    let mut conn = match server_database.pool.get() {
+        Ok(connection) => connection,
+        Err(e) => {
+            tracing::error!("Failed to get database connection: {}", e);
+            res.logs.push("Failed to get database connection".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    if let Err(e) = authorize_file_access(&user, input.file_id, FileAccess::Owner, &mut conn) {
+        tracing::error!("Authorization failed: {}", e);
+        res.logs.push(format!("Error : {}", e));
+        return (e.status_code(), Json(res));
+    }
+
+    let expires_at = match input.expires_in_seconds {
+        Some(seconds) => {
+            let expires_at = Some(seconds)
+                .filter(|seconds| (1..=MAX_SIGNATURE_REQUEST_LIFETIME).contains(seconds))
+                .and_then(Duration::try_seconds)
+                .and_then(|lifetime| Utc::now().checked_add_signed(lifetime));
+            match expires_at {
+                Some(expires_at) => Some(expires_at.to_rfc3339()),
+                None => {
+                    res.logs.push(format!(
+                        "Error : expires_in_seconds must be between 1 and {}",
+                        MAX_SIGNATURE_REQUEST_LIFETIME
+                    ));
+                    return (StatusCode::BAD_REQUEST, Json(res));
+                }
+            }
+        }
+        None => None,
+    };
+
+    for signer in input.signers.iter() {
+        let signer = match signer.trim().parse::<ethaddr::Address>() {
+            Ok(address) => address.to_string(),
+            Err(e) => {
+                res.logs
+                    .push(format!("Error : invalid wallet address {}: {:?}", signer, e));
+                return (StatusCode::BAD_REQUEST, Json(res));
+            }
+        };
+
+        if user.is(&signer) {
+            res.logs
+                .push("Skipped : the owner signs through explorer_sign_revision".to_string());
+            continue;
+        }
+
+        match fetch_pending_signature_requests(input.file_id, &signer, &mut conn) {
+            Ok(pending) if !pending.is_empty() => {
+                res.logs
+                    .push(format!("Skipped : {} already has a pending request", signer));
+                continue;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                res.logs.push(format!("Error : {}", e));
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+            }
+        }
+
+        let now = current_time();
+        let request = SignatureRequestTable {
+            id: None,
+            file_id: input.file_id,
+            requester: user.address.clone(),
+            signer: signer.clone(),
+            status: SIGNATURE_REQUEST_PENDING.to_string(),
+            created_time: now.clone(),
+            updated_time: now,
+            expires_at: expires_at.clone(),
+            verification_hash: None,
+        };
+        if let Err(e) = insert_signature_request(request, &mut conn) {
+            tracing::error!("Failed to save signature request: {}", e);
+            res.logs.push(format!("Error : {}", e));
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+        res.logs
+            .push(format!("Success : signature requested from {}", signer));
+    }
+
+    match fetch_signature_requests_by_file(input.file_id, &mut conn)
+        .and_then(|requests| load_request_infos(requests, &mut conn))
+    {
+        Ok(requests) => res.requests = requests,
+        Err(e) => {
+            res.logs.push(format!("Error : {}", e));
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    }
+    (StatusCode::CREATED, Json(res))
+}
+
+/// Pending requests addressed to the signed in wallet.
+pub async fn explorer_incoming_signature_requests(
+    State(server_database): State<Db>,
+    user: AuthenticatedUser,
+) -> (StatusCode, Json<SignatureRequestApiResponse>) {
+    tracing::debug!("explorer_incoming_signature_requests");
+    let mut res = SignatureRequestApiResponse {
+        logs: Vec::new(),
+        requests: Vec::new(),
+    };
+
+    let mut conn = match server_database.pool.get() {
+        Ok(connection) => connection,
+        Err(e) => {
+            tracing::error!("Failed to get database connection: {}", e);
+            res.logs.push("Failed to get database connection".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    match fetch_signature_requests_for_signer(&user.address, &mut conn)
+        .and_then(|requests| load_request_infos(requests, &mut conn))
+    {
+        Ok(requests) => {
+            res.requests = requests
+                .into_iter()
+                .filter(|info| info.request.status == SIGNATURE_REQUEST_PENDING)
+                .collect();
+        }
+        Err(e) => {
+            res.logs.push(format!("Error : {}", e));
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    }
+    (StatusCode::OK, Json(res))
+}
+
+/// Every request made for a file with its status, for the owner to track.
+pub async fn explorer_file_signature_requests(
+    State(server_database): State<Db>,
+    user: AuthenticatedUser,
+    Path(file_id): Path<i32>,
+) -> (StatusCode, Json<SignatureRequestApiResponse>) {
+    tracing::debug!("explorer_file_signature_requests {}", file_id);
+    let mut res = SignatureRequestApiResponse {
+        logs: Vec::new(),
+        requests: Vec::new(),
+    };
+
+    let mut conn = match server_database.pool.get() {
+        Ok(connection) => connection,
+        Err(e) => {
+            tracing::error!("Failed to get database connection: {}", e);
+            res.logs.push("Failed to get database connection".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    if let Err(e) = authorize_file_access(&user, file_id, FileAccess::Owner, &mut conn) {
+        tracing::error!("Authorization failed: {}", e);
+        res.logs.push(format!("Error : {}", e));
+        return (e.status_code(), Json(res));
+    }
+
+    match fetch_signature_requests_by_file(file_id, &mut conn)
+        .and_then(|requests| load_request_infos(requests, &mut conn))
+    {
+        Ok(requests) => res.requests = requests,
+        Err(e) => {
+            res.logs.push(format!("Error : {}", e));
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    }
+    (StatusCode::OK, Json(res))
+}
+
+/// Loads a request addressed to `user` that can still be answered.
+fn pending_request_for(
+    user: &AuthenticatedUser,
+    request_id: i32,
+    conn: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<SignatureRequestTable, (StatusCode, String)> {
+    let mut request = fetch_signature_request(request_id, conn).map_err(|e| {
+        tracing::error!("{}", e);
+        (
+            StatusCode::NOT_FOUND,
+            format!("Error : signature request {} not found", request_id),
+        )
+    })?;
+
+    if !user.is(&request.signer) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            format!(
+                "Error : signature request {} is not addressed to {}",
+                request_id, user.address
+            ),
+        ));
+    }
+
+    refresh_expiry(&mut request, conn)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Error : {}", e)))?;
+    if request.status != SIGNATURE_REQUEST_PENDING {
+        return Err((
+            StatusCode::CONFLICT,
+            format!(
+                "Error : signature request {} is {}",
+                request_id, request.status
+            ),
+        ));
+    }
+    Ok(request)
+}
+
+/// Signs the requested file from the invitee's wallet, the signature lands on the owner's chain.
+pub async fn explorer_sign_signature_request(
+    State(server_database): State<Db>,
+    user: AuthenticatedUser,
+    Path(request_id): Path<i32>,
+    Form(input): Form<SignRequestInput>,
+) -> (StatusCode, Json<ApiResponse>) {
+    tracing::debug!("explorer_sign_signature_request {}", request_id);
+    let mut res = ApiResponse {
+        logs: Vec::new(),
+        file: None,
+        files: Vec::new(),
+        head: None,
+    };
+
+    let mut conn = match server_database.pool.get() {
+        Ok(connection) => connection,
+        Err(e) => {
+            tracing::error!("Failed to get database connection: {}", e);
+            res.logs.push("Failed to get database connection".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    let request = match pending_request_for(&user, request_id, &mut conn) {
+        Ok(request) => request,
+        Err((status, message)) => {
+            res.logs.push(message);
+            return (status, Json(res));
+        }
+    };
+
+    let mut page_data = match fetch_page_data(request.file_id, &mut conn) {
+        Ok(page) => page,
+        Err(e) => {
+            tracing::error!("Failed to fetch page data: {}", e);
+            res.logs
+                .push(format!("Error : file {} not found", request.file_id));
+            return (StatusCode::NOT_FOUND, Json(res));
+        }
+    };
+
+    let mut document = match AquaDocument::parse(page_data.page_data.as_bytes()) {
+        Ok(document) => document,
+        Err(e) => {
+            tracing::error!("Failed to parse page data record: {}", e);
+            res.logs
+                .push("Error : Failure to parse Page Data Object".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    let previous_hash = match document.latest_verification_hash() {
+        Some(hash) => hash,
+        None => {
+            res.logs.push("Error : chain has no revisions".to_string());
+            return (StatusCode::BAD_REQUEST, Json(res));
+        }
+    };
+
+    let signature = match input.signature.parse::<Signature>() {
+        Ok(signature) => signature,
+        Err(e) => {
+            res.logs
+                .push(format!("error : Failed to parse  signature: {:?}", e));
+            return (StatusCode::BAD_REQUEST, Json(res));
+        }
+    };
+    let public_key = match input.publickey.parse::<PublicKey>() {
+        Ok(public_key) => public_key,
+        Err(e) => {
+            res.logs
+                .push(format!("error : Failed to parse  public key: {:?}", e));
+            return (StatusCode::BAD_REQUEST, Json(res));
+        }
+    };
+    let wallet_address = match ethaddr::Address::from_str_checksum(&input.wallet_address) {
+        Ok(address) => address,
+        Err(e) => {
+            res.logs
+                .push(format!("Failed to parse wallet address: {:?}", e));
+            return (StatusCode::BAD_REQUEST, Json(res));
+        }
+    };
+
+    if !user.is(&wallet_address.to_string()) {
+        res.logs.push(format!(
+            "Error : {} cannot sign for {}",
+            user.address, wallet_address
+        ));
+        return (StatusCode::FORBIDDEN, Json(res));
+    }
+
+    let scheme = match signature_scheme(
+        input.signing_mode.as_deref(),
+        input.timestamp,
+        &document,
+    ) {
+        Ok(scheme) => scheme,
+        Err(e) => {
+            res.logs.push(format!("Error : {}", e));
+            return (StatusCode::BAD_REQUEST, Json(res));
+        }
+    };
+
+    if let Err(e) = check_wallet_signature(
+        &signature,
+        &public_key,
+        &wallet_address,
+        &previous_hash,
+        &scheme,
+    )
+    .await
+    {
+        tracing::error!("Signature rejected: {}", e);
+        res.logs.push(format!("Error : signature rejected: {}", e));
+        return (e.status_code(), Json(res));
+    }
+
+    let appended =
+        append_document_signature(&mut document, signature, public_key, wallet_address, &scheme)
+            .and_then(|hash| document.to_storage().map(|data| (hash, data)));
+    let verification_hash = match appended {
+        Ok((hash, data)) => {
+            page_data.page_data = data;
+            hash
+        }
+        Err(e) => {
+            tracing::error!("Failed to append signature revision: {}", e);
+            res.logs
+                .push(format!("Error : failed to append signature revision: {}", e));
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    // the revision, its signing mode and the request status change together
+    let update_result = conn.transaction::<_, PageUpdateError, _>(|conn| {
+        update_page_data(page_data.clone(), conn)?;
+        if document.version() == ProtocolVersion::V1_2 {
+            record_signature_scheme(&verification_hash, &scheme, conn)?;
+        }
+        update_signature_request_status(
+            request_id,
+            SIGNATURE_REQUEST_SIGNED,
+            Some(verification_hash.clone()),
+            &current_time(),
+            conn,
+        )?;
+        Ok(())
+    });
+    if let Err(e) = update_result {
+        tracing::error!("Failed to save signed request: {}", e);
+        res.logs.push(format!("Failed to update page data : {}", e));
+        res.head = e.head();
+        return (e.status_code(), Json(res));
+    }
+
+    res.logs.push(format!(
+        "Success : signature request {} signed by {}",
+        request_id, user.address
+    ));
+    res.file = Some(FileInfo {
+        id: page_data.id as i64,
+        name: page_data.name,
+        extension: page_data.extension,
+        page_data: page_data_for_response(page_data.page_data),
+        owner: page_data.owner,
+        mode: page_data.mode,
+    });
+    (StatusCode::OK, Json(res))
+}
+
+pub async fn explorer_decline_signature_request(
+    State(server_database): State<Db>,
+    user: AuthenticatedUser,
+    Path(request_id): Path<i32>,
+) -> (StatusCode, Json<SignatureRequestApiResponse>) {
+    tracing::debug!("explorer_decline_signature_request {}", request_id);
+    let mut res = SignatureRequestApiResponse {
+        logs: Vec::new(),
+        requests: Vec::new(),
+    };
+
+    let mut conn = match server_database.pool.get() {
+        Ok(connection) => connection,
+        Err(e) => {
+            tracing::error!("Failed to get database connection: {}", e);
+            res.logs.push("Failed to get database connection".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    let mut request = match pending_request_for(&user, request_id, &mut conn) {
+        Ok(request) => request,
+        Err((status, message)) => {
+            res.logs.push(message);
+            return (status, Json(res));
+        }
+    };
+
+    let now = current_time();
+    if let Err(e) =
+        update_signature_request_status(request_id, SIGNATURE_REQUEST_DECLINED, None, &now, &mut conn)
+    {
+        res.logs.push(format!("Error : {}", e));
+        return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+    }
+    request.status = SIGNATURE_REQUEST_DECLINED.to_string();
+    request.updated_time = now;
+
+    res.logs
+        .push(format!("Success : signature request {} declined", request_id));
+    res.requests.push(request_info(request, None));
+    (StatusCode::OK, Json(res))
+}
+
+/// The EIP-712 typed data for signing the latest revision of a file, for wallets that
+/// sign with `eth_signTypedData_v4` instead of `personal_sign`.
+pub async fn explorer_sign_typed_data(
+    State(server_database): State<Db>,
+    user: AuthenticatedUser,
+    Path(file_id): Path<i32>,
+) -> (StatusCode, Json<TypedDataApiResponse>) {
+    tracing::debug!("explorer_sign_typed_data {}", file_id);
+    let mut res = TypedDataApiResponse {
+        logs: Vec::new(),
+        typed_data: None,
+    };
+
+    let mut conn = match server_database.pool.get() {
+        Ok(connection) => connection,
+        Err(e) => {
+            tracing::error!("Failed to get database connection: {}", e);
+            res.logs.push("Failed to get database connection".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    // invited signers only have read access
+    let page_data = match authorize_file_access(&user, file_id, FileAccess::Read, &mut conn) {
+        Ok(page) => page,
+        Err(e) => {
+            tracing::error!("Authorization failed: {}", e);
+            res.logs.push(format!("Error : {}", e));
+            return (e.status_code(), Json(res));
+        }
+    };
+
+    let document = match AquaDocument::parse(page_data.page_data.as_bytes()) {
+        Ok(document) => document,
+        Err(e) => {
+            tracing::error!("Failed to parse page data record: {}", e);
+            res.logs
+                .push("Error : Failure to parse Page Data Object".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    let timestamp = Utc::now().timestamp().max(0) as u64;
+    match typed_data_for(&document, timestamp) {
+        Some(data) => {
+            res.typed_data = Some(typed_data_request(&data));
+            (StatusCode::OK, Json(res))
+        }
+        None => {
+            res.logs.push("Error : chain has no revisions".to_string());
+            (StatusCode::BAD_REQUEST, Json(res))
+        }
+    }
+}
+
+/// How the EIP-712 signatures of a file were signed. The chain JSON does not carry this, so
+/// it is exported next to it for verifying or importing the chain elsewhere.
+pub async fn explorer_file_signature_modes(
+    State(server_database): State<Db>,
+    user: AuthenticatedUser,
+    Path(file_id): Path<i32>,
+) -> (StatusCode, Json<SignatureModesApiResponse>) {
+    tracing::debug!("explorer_file_signature_modes {}", file_id);
+    let mut res = SignatureModesApiResponse {
+        logs: Vec::new(),
+        signature_modes: ExportedSignatureModes::new(),
+    };
+
+    let mut conn = match server_database.pool.get() {
+        Ok(connection) => connection,
+        Err(e) => {
+            tracing::error!("Failed to get database connection: {}", e);
+            res.logs.push("Failed to get database connection".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    let page_data = match authorize_file_access(&user, file_id, FileAccess::Read, &mut conn) {
+        Ok(page) => page,
+        Err(e) => {
+            tracing::error!("Authorization failed: {}", e);
+            res.logs.push(format!("Error : {}", e));
+            return (e.status_code(), Json(res));
+        }
+    };
+
+    let document = match AquaDocument::parse(page_data.page_data.as_bytes()) {
+        Ok(document) => document,
+        Err(e) => {
+            tracing::error!("Failed to parse page data record: {}", e);
+            res.logs
+                .push("Error : Failure to parse Page Data Object".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    match load_document_signature_schemes(&document, &mut conn) {
+        Ok(schemes) => {
+            res.signature_modes = export_signature_modes(&schemes);
+            (StatusCode::OK, Json(res))
+        }
+        Err(e) => {
+            tracing::error!("Failed to load signature modes: {}", e);
+            res.logs.push(format!("Failed to load signature modes: {}", e));
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(res))
+        }
+    }
+}
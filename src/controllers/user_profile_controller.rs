@@ -1,8 +1,10 @@
+use crate::auth::session::AuthenticatedUser;
 use crate::db::user_profiles::{fetch_user_profile, update_user_profile};
 use crate::models::input::{DeleteInput, RevisionInput, UpdateConfigurationInput, WitnessInput};
 use crate::models::page_data::{ApiResponse, PageDataContainer};
 use crate::models::user_profiles::UserProfileApiResponse;
 use crate::models::UserProfilesTable;
+use crate::protocol::ProtocolVersion;
 use crate::models::{file::FileInfo, page_data};
 use crate::util::{
     check_if_page_data_revision_are_okay, check_or_generate_domain, compute_content_hash,
@@ -62,7 +64,7 @@ use tracing_subscriber::{fmt::format, layer::SubscriberExt, util::SubscriberInit
 // We parse the .env file directly
 pub async fn explorer_fetch_user_profile(
     State(server_database): State<Db>,
-    headers: HeaderMap,
+    user: AuthenticatedUser,
 ) -> (StatusCode, Json<UserProfileApiResponse>) {
     // let mut config_data = HashMap::new();
 
@@ -72,27 +74,6 @@ pub async fn explorer_fetch_user_profile(
         user_profile: None,
     };
 
-    let metamask_address = match headers.get("metamask_address") {
-        Some(value) => match value.to_str() {
-            Ok(key) => key,
-            Err(err) => {
-                tracing::error!("headers get error {} ", err);
-                // return (StatusCode::BAD_REQUEST,  Json(json!({"error": "Invalid metamask_address header"})))
-
-                res.logs
-                    .push(format!("Error: Meta mask public key  error: {:?}", err));
-                return (StatusCode::BAD_REQUEST, Json(res));
-            }
-        },
-        None => {
-            tracing::debug!("metamask_address header missing ");
-            // return (StatusCode::BAD_REQUEST, Json(json!({"error": "metamask_address header missing"})))
-            res.logs
-                .push("Error: Meta mask public key  missing".to_string());
-            return (StatusCode::BAD_REQUEST, Json(res));
-        }
-    };
-
     let mut conn = match server_database.pool.get() {
         Ok(connection) => connection,
         Err(e) => {
@@ -103,7 +84,7 @@ pub async fn explorer_fetch_user_profile(
         }
     };
 
-    let user_profile = fetch_user_profile(metamask_address.to_owned(), &mut conn);
+    let user_profile = fetch_user_profile(user.address.clone(), &mut conn);
     println!("Fetched user profile: {:?}", user_profile);
 
     if user_profile.is_err() {
@@ -120,8 +101,12 @@ pub async fn explorer_fetch_user_profile(
 
 pub async fn explorer_update_user_profile(
     State(server_database): State<Db>,
-    Form(input): Form<UserProfilesTable>,
+    user: AuthenticatedUser,
+    Form(mut input): Form<UserProfilesTable>,
 ) -> (StatusCode, Json<UserProfileApiResponse>) {
+    // a session may only update its own profile
+    input.address = user.address.clone();
+
     let mut log_data: Vec<String> = Vec::new();
     let mut res: UserProfileApiResponse = UserProfileApiResponse {
         logs: log_data.clone(),
@@ -140,8 +125,21 @@ pub async fn explorer_update_user_profile(
         }
     };
 
-    // let insert_result = insert_page_data(db_data_model.clone(), & mut conn);
-    // let page_data_result = fetch_page_data(input.filename, & mut conn);
+    // forms without a protocol version keep the one already chosen
+    input.protocol_version = if input.protocol_version.trim().is_empty() {
+        let current = fetch_user_profile(input.address.clone(), &mut conn).ok();
+        ProtocolVersion::for_profile(current.as_ref()).to_string()
+    } else {
+        match input.protocol_version.parse::<ProtocolVersion>() {
+            Ok(version) => version.to_string(),
+            Err(e) => {
+                log_data.push(format!("Error : {}", e));
+                res.logs = log_data;
+                return (StatusCode::BAD_REQUEST, Json(res));
+            }
+        }
+    };
+    res.user_profile = Some(input.clone());
 
     let update_result = update_user_profile(input.clone(), &mut conn);
 
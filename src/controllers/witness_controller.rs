@@ -0,0 +1,355 @@
+use crate::auth::permissions::{authorize_file_access, FileAccess};
+use crate::auth::session::AuthenticatedUser;
+use crate::blob_store::page_data_for_response;
+use crate::db::pages_db::{update_page_data, PageUpdateError};
+use crate::models::file::FileInfo;
+use crate::models::input::{WitnessBatchInput, WitnessBatchRootInput};
+use crate::models::page_data::{ApiResponse, WitnessBatchRootResponse};
+use crate::models::PagesTable;
+use crate::protocol::v1_3::{self, MerkleTreeV13};
+use crate::protocol::{AquaDocument, ProtocolVersion};
+use crate::util::make_empty_hash;
+use crate::witness::{
+    append_witness_revision, build_merkle_tree, latest_verification_hash,
+    witness_event_verification_hash, MerkleTree,
+};
+use crate::witness_confirmation::{confirm_witness, witness_contract_address};
+use crate::networks::network_registry;
+use crate::db::user_profiles::fetch_user_profile;
+use crate::Db;
+use aqua_verifier_rs_types::models::tx_hash::TxHash;
+use axum::{extract::State, http::StatusCode, Json};
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use diesel::Connection;
+use crate::db::DbConnection;
+extern crate serde_json_path_to_error as serde_json;
+
+/// Upper bound on files per batch, keeps a single request from rewriting the whole database.
+const MAX_BATCH_SIZE: usize = 1000;
+
+struct BatchEntry {
+    page: PagesTable,
+    document: AquaDocument,
+}
+
+/// Merkle tree of a batch, in the protocol version all of its chains share.
+enum BatchTree {
+    V1_2(MerkleTree),
+    V1_3(MerkleTreeV13),
+}
+
+impl BatchTree {
+    fn root(&self) -> String {
+        match self {
+            BatchTree::V1_2(tree) => tree.root.to_string(),
+            BatchTree::V1_3(tree) => tree.root.clone(),
+        }
+    }
+
+    /// What the witness transaction publishes: the witness event verification hash over a
+    /// v1.2 root, a v1.3 root as it is.
+    fn published_hash(&self) -> String {
+        match self {
+            BatchTree::V1_2(tree) => {
+                witness_event_verification_hash(&make_empty_hash(), &tree.root).to_string()
+            }
+            BatchTree::V1_3(tree) => tree.root.clone(),
+        }
+    }
+
+    fn leaves(&self) -> Vec<String> {
+        match self {
+            BatchTree::V1_2(tree) => tree.leaves.iter().map(|leaf| leaf.to_string()).collect(),
+            BatchTree::V1_3(tree) => tree.leaves.clone(),
+        }
+    }
+}
+
+/// Loads every file of the batch the caller may witness, in request order without duplicates.
+fn load_batch(
+    user: &AuthenticatedUser,
+    file_ids: &[i32],
+    conn: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<Vec<BatchEntry>, (StatusCode, String)> {
+    if file_ids.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "Error : no files to witness".to_string()));
+    }
+    if file_ids.len() > MAX_BATCH_SIZE {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Error : a batch holds at most {} files", MAX_BATCH_SIZE),
+        ));
+    }
+
+    let mut entries: Vec<BatchEntry> = Vec::new();
+    for file_id in file_ids {
+        if entries.iter().any(|entry| entry.page.id == *file_id) {
+            continue;
+        }
+
+        let page = authorize_file_access(user, *file_id, FileAccess::Write, conn)
+            .map_err(|e| (e.status_code(), format!("Error : {}", e)))?;
+        let document = AquaDocument::parse(page.page_data.as_bytes()).map_err(|e| {
+            tracing::error!("Failed to parse page data record: {}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error : Failure to parse Page Data Object of file {}", file_id),
+            )
+        })?;
+        entries.push(BatchEntry { page, document });
+    }
+    Ok(entries)
+}
+
+/// The tree over the latest revision of every chain, which must all be in one protocol version
+/// as v1.2 and v1.3 hash their trees differently.
+fn batch_tree(entries: &[BatchEntry]) -> Result<BatchTree, (StatusCode, String)> {
+    let version = entries
+        .first()
+        .map(|entry| entry.document.version())
+        .unwrap_or_default();
+    if entries.iter().any(|entry| entry.document.version() != version) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Error : a batch cannot mix v1.2 and v1.3 chains".to_string(),
+        ));
+    }
+    let no_revisions = |entry: &BatchEntry| {
+        (
+            StatusCode::BAD_REQUEST,
+            format!("Error : file {} has no revisions", entry.page.id),
+        )
+    };
+
+    let tree = match version {
+        ProtocolVersion::V1_2 => {
+            let leaves = entries
+                .iter()
+                .map(|entry| {
+                    let leaf = match &entry.document {
+                        AquaDocument::V1_2(doc) => {
+                            doc.pages.first().and_then(latest_verification_hash)
+                        }
+                        AquaDocument::V1_3(_) => None,
+                    };
+                    leaf.ok_or_else(|| no_revisions(entry))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            build_merkle_tree(&leaves).map(BatchTree::V1_2)
+        }
+        ProtocolVersion::V1_3 => {
+            let leaves = entries
+                .iter()
+                .map(|entry| {
+                    let leaf = match &entry.document {
+                        AquaDocument::V1_3(chain) => chain.latest_verification_hash(),
+                        AquaDocument::V1_2(_) => None,
+                    };
+                    leaf.ok_or_else(|| no_revisions(entry))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            v1_3::build_merkle_tree(&leaves).map(BatchTree::V1_3)
+        }
+    };
+    tree.ok_or_else(|| (StatusCode::BAD_REQUEST, "Error : no files to witness".to_string()))
+}
+
+/// Returns the Merkle root over the latest revision of each file, to be published in one transaction.
+pub async fn explorer_witness_batch_root(
+    State(server_database): State<Db>,
+    user: AuthenticatedUser,
+    Json(input): Json<WitnessBatchRootInput>,
+) -> (StatusCode, Json<WitnessBatchRootResponse>) {
+    tracing::debug!("explorer_witness_batch_root");
+    let mut res = WitnessBatchRootResponse {
+        logs: Vec::new(),
+        merkle_root: None,
+        witness_event_verification_hash: None,
+        leaves: Vec::new(),
+    };
+
+    let mut conn = match server_database.pool.get() {
+        Ok(connection) => connection,
+        Err(e) => {
+            tracing::error!("Failed to get database connection: {}", e);
+            res.logs.push("Failed to get database connection".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    let tree = match load_batch(&user, &input.file_ids, &mut conn)
+        .and_then(|entries| batch_tree(&entries))
+    {
+        Ok(tree) => tree,
+        Err((status, message)) => {
+            res.logs.push(message);
+            return (status, Json(res));
+        }
+    };
+
+    res.leaves = tree.leaves();
+    res.logs.push(format!(
+        "Success : Merkle root computed over {} files",
+        res.leaves.len()
+    ));
+    res.witness_event_verification_hash = Some(tree.published_hash());
+    res.merkle_root = Some(tree.root());
+    (StatusCode::OK, Json(res))
+}
+
+/// Appends a witness revision to every file of the batch, all anchored by one transaction.
+pub async fn explorer_witness_batch(
+    State(server_database): State<Db>,
+    user: AuthenticatedUser,
+    Json(input): Json<WitnessBatchInput>,
+) -> (StatusCode, Json<ApiResponse>) {
+    tracing::debug!("explorer_witness_batch");
+    let mut res = ApiResponse {
+        logs: Vec::new(),
+        file: None,
+        files: Vec::new(),
+        head: None,
+    };
+
+    let tx_hash = match input.tx_hash.parse::<TxHash>() {
+        Ok(tx_hash) => tx_hash,
+        Err(e) => {
+            tracing::error!("Failed to parse tx hash: {:?}", e);
+            res.logs
+                .push(format!("Error :  Failed to to parse tx hash: {:?}", e));
+            return (StatusCode::BAD_REQUEST, Json(res));
+        }
+    };
+
+    let wallet_address = match ethaddr::Address::from_str_checksum(&input.wallet_address) {
+        Ok(address) => address,
+        Err(e) => {
+            tracing::error!("Failed to parse wallet address: {:?}", e);
+            res.logs
+                .push(format!("Error :  Failed to parse wallet address: {:?}", e));
+            return (StatusCode::BAD_REQUEST, Json(res));
+        }
+    };
+
+    let mut conn = match server_database.pool.get() {
+        Ok(connection) => connection,
+        Err(e) => {
+            tracing::error!("Failed to get database connection: {}", e);
+            res.logs.push("Failed to get database connection".to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+        }
+    };
+
+    let (mut entries, tree) = match load_batch(&user, &input.file_ids, &mut conn)
+        .and_then(|entries| batch_tree(&entries).map(|tree| (entries, tree)))
+    {
+        Ok(batch) => batch,
+        Err((status, message)) => {
+            res.logs.push(message);
+            return (status, Json(res));
+        }
+    };
+
+    if let Some(expected_root) = &input.merkle_root {
+        if expected_root.trim_start_matches("0x") != tree.root() {
+            res.logs.push(format!(
+                "Error : merkle root {} no longer matches the files, a chain changed since it was computed",
+                expected_root
+            ));
+            return (StatusCode::CONFLICT, Json(res));
+        }
+    }
+
+    // an empty network falls back to the chain of the caller's profile
+    let profile = fetch_user_profile(user.address.clone(), &mut conn).ok();
+    let network = match network_registry()
+        .resolve(&input.network, profile.as_ref().map(|p| p.chain.as_str()))
+    {
+        Ok(network) => network,
+        Err(e) => {
+            tracing::error!("Invalid witness network: {}", e);
+            res.logs.push(format!("Error : {}", e));
+            return (StatusCode::BAD_REQUEST, Json(res));
+        }
+    };
+
+    let contract_address = witness_contract_address(network, profile.as_ref());
+    let published_hash = tree.published_hash();
+    if let Err(e) = confirm_witness(network, &tx_hash, &contract_address, &published_hash).await
+    {
+        tracing::error!("Witness transaction not confirmed: {}", e);
+        res.logs
+            .push(format!("Error : witness transaction not confirmed: {}", e));
+        return (e.status_code(), Json(res));
+    }
+
+    // v1.2 witnesses have no field for the sender, v1.3 ones keep it
+    tracing::info!("Witness transaction {} sent by {}", tx_hash, wallet_address);
+    for (index, entry) in entries.iter_mut().enumerate() {
+        let appended = match (&mut entry.document, &tree) {
+            (AquaDocument::V1_2(doc), BatchTree::V1_2(tree)) => append_witness_revision(
+                &mut doc.pages[0],
+                tree.root,
+                tree.proofs[index].clone(),
+                &network.name,
+                tx_hash,
+            )
+            .map(|_| ()),
+            (AquaDocument::V1_3(chain), BatchTree::V1_3(tree)) => v1_3::append_witness_revision(
+                chain,
+                v1_3::WitnessV13 {
+                    merkle_root: tree.root.clone(),
+                    merkle_proof: tree.proofs[index].clone(),
+                    network: network.name.clone(),
+                    transaction_hash: tx_hash.to_string(),
+                    smart_contract_address: contract_address.clone(),
+                    sender_account_address: wallet_address.to_string(),
+                },
+            )
+            .map(|_| ()),
+            _ => Err("protocol version does not match the batch".to_string()),
+        };
+
+        match appended.and_then(|_| entry.document.to_storage()) {
+            Ok(page_data) => entry.page.page_data = page_data,
+            Err(e) => {
+                tracing::error!("Failed to witness file {}: {}", entry.page.id, e);
+                res.logs
+                    .push(format!("Error : failed to witness file {}: {}", entry.page.id, e));
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(res));
+            }
+        }
+    }
+
+    // all chains are witnessed by the same transaction, so they are updated together
+    let update_result = conn.transaction::<_, PageUpdateError, _>(|conn| {
+        for entry in entries.iter() {
+            update_page_data(entry.page.clone(), conn)?;
+        }
+        Ok(())
+    });
+    if let Err(e) = update_result {
+        tracing::error!("Failed to update page data: {}", e);
+        res.logs.push(format!("Failed to update page data : {}", e));
+        res.head = e.head();
+        return (e.status_code(), Json(res));
+    }
+
+    res.logs.push(format!(
+        "Success : witnessed {} files under merkle root {}",
+        entries.len(),
+        tree.root()
+    ));
+    res.files = entries
+        .into_iter()
+        .map(|entry| FileInfo {
+            id: entry.page.id as i64,
+            name: entry.page.name,
+            extension: entry.page.extension,
+            page_data: page_data_for_response(entry.page.page_data),
+            owner: entry.page.owner,
+            mode: entry.page.mode,
+        })
+        .collect();
+    (StatusCode::OK, Json(res))
+}
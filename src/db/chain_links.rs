@@ -0,0 +1,60 @@
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use crate::db::DbConnection;
+
+use crate::models::ChainLinkTable;
+
+pub fn insert_chain_link(
+    data: ChainLinkTable,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<i64, String> {
+    let inserted_id: i32 = diesel::insert_into(crate::schema::chain_links::table)
+        .values(data)
+        .returning(crate::schema::chain_links::dsl::id)
+        .get_result::<Option<i32>>(db_connection)
+        .map_err(|e| format!("Error saving chain link: {}", e))?
+        .unwrap_or(-1);
+
+    Ok(inserted_id as i64)
+}
+
+/// Links made by the chain of `file_id_param`, oldest first.
+pub fn fetch_chain_links_by_file(
+    file_id_param: i32,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<Vec<ChainLinkTable>, String> {
+    use crate::schema::chain_links::dsl::*;
+
+    chain_links
+        .filter(file_id.eq(file_id_param))
+        .order(id.asc())
+        .select(ChainLinkTable::as_select())
+        .load::<ChainLinkTable>(db_connection)
+        .map_err(|e| format!("Error fetching chain links: {}", e))
+}
+
+/// Links recorded for the given link revisions, whichever file holds them.
+pub fn fetch_chain_links_by_revisions(
+    revision_hashes: &[String],
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<Vec<ChainLinkTable>, String> {
+    use crate::schema::chain_links::dsl::*;
+
+    chain_links
+        .filter(revision_hash.eq_any(revision_hashes))
+        .select(ChainLinkTable::as_select())
+        .load::<ChainLinkTable>(db_connection)
+        .map_err(|e| format!("Error fetching chain links: {}", e))
+}
+
+/// Removes the links made by files, links pointing at them stay so verification can report them.
+pub fn delete_chain_links_by_files(
+    file_ids: &[i32],
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<usize, String> {
+    use crate::schema::chain_links::dsl::*;
+
+    diesel::delete(chain_links.filter(file_id.eq_any(file_ids)))
+        .execute(db_connection)
+        .map_err(|e| format!("Error deleting chain links: {}", e))
+}
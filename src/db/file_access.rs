@@ -0,0 +1,76 @@
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use crate::db::{lower, DbConnection};
+
+use crate::models::FileAccessTable;
+
+pub fn insert_file_access(
+    data: FileAccessTable,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<i64, String> {
+    let inserted_id: i32 = diesel::insert_into(crate::schema::file_access::table)
+        .values(data)
+        .returning(crate::schema::file_access::dsl::id)
+        .get_result::<Option<i32>>(db_connection)
+        .map_err(|e| format!("Error saving file access: {}", e))?
+        .unwrap_or(-1);
+
+    Ok(inserted_id as i64)
+}
+
+pub fn fetch_file_access_for_address(
+    file_id_param: i32,
+    address_param: &str,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<Vec<FileAccessTable>, String> {
+    use crate::schema::file_access::dsl::*;
+
+    // addresses are compared case insensitively, checksummed and lower case forms are both in use
+    file_access
+        .filter(file_id.eq(file_id_param))
+        .filter(lower(address).eq(address_param.to_lowercase()))
+        .select(FileAccessTable::as_select())
+        .load::<FileAccessTable>(db_connection)
+        .map_err(|e| format!("Error fetching file access: {}", e))
+}
+
+pub fn fetch_file_access_by_file(
+    file_id_param: i32,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<Vec<FileAccessTable>, String> {
+    use crate::schema::file_access::dsl::*;
+
+    file_access
+        .filter(file_id.eq(file_id_param))
+        .select(FileAccessTable::as_select())
+        .load::<FileAccessTable>(db_connection)
+        .map_err(|e| format!("Error fetching file access: {}", e))
+}
+
+pub fn delete_file_access(
+    file_id_param: i32,
+    address_param: &str,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<usize, String> {
+    use crate::schema::file_access::dsl::*;
+
+    diesel::delete(
+        file_access
+            .filter(file_id.eq(file_id_param))
+            .filter(lower(address).eq(address_param.to_lowercase())),
+    )
+    .execute(db_connection)
+    .map_err(|e| format!("Error deleting file access: {}", e))
+}
+
+/// Removes every grant on the files of `file_ids`, part of deleting them.
+pub fn delete_file_access_by_files(
+    file_ids: &[i32],
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<usize, String> {
+    use crate::schema::file_access::dsl::*;
+
+    diesel::delete(file_access.filter(file_id.eq_any(file_ids)))
+        .execute(db_connection)
+        .map_err(|e| format!("Error deleting file access: {}", e))
+}
@@ -0,0 +1,153 @@
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use diesel::sql_types::{BigInt, Integer, Text};
+use crate::db::DbConnection;
+
+/// Upper bound on words of a search, each one narrows the result.
+const MAX_SEARCH_WORDS: usize = 16;
+
+// The index is a FTS5 table keyed by rowid on SQLite and a table with a generated tsvector on
+// PostgreSQL, the queries differ per backend.
+
+#[derive(QueryableByName)]
+struct FileSearchRow {
+    #[diesel(sql_type = Integer)]
+    file_id: i32,
+}
+
+/// Words of a search the way both indexes tokenize text: runs of letters and digits.
+pub fn search_words(query: &str) -> Vec<String> {
+    query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .take(MAX_SEARCH_WORDS)
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Replaces the search entry of `file_id`. `hashes` holds the verification hashes, file hashes,
+/// signer addresses and witness transactions of the chain separated by spaces.
+pub fn index_file(
+    file_id: i32,
+    owner: &str,
+    name: &str,
+    extension: &str,
+    hashes: &str,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<(), String> {
+    delete_file_index(file_id, db_connection)?;
+    let insert = match &mut **db_connection {
+        DbConnection::Sqlite(conn) => diesel::sql_query(
+            "INSERT INTO file_search (rowid, owner, name, extension, hashes) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind::<Integer, _>(file_id)
+        .bind::<Text, _>(owner)
+        .bind::<Text, _>(name)
+        .bind::<Text, _>(extension)
+        .bind::<Text, _>(hashes)
+        .execute(conn),
+        DbConnection::Postgres(conn) => diesel::sql_query(
+            "INSERT INTO file_search (file_id, owner, name, extension, hashes) VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind::<Integer, _>(file_id)
+        .bind::<Text, _>(owner)
+        .bind::<Text, _>(name)
+        .bind::<Text, _>(extension)
+        .bind::<Text, _>(hashes)
+        .execute(conn),
+    };
+    insert
+        .map(|_| ())
+        .map_err(|e| format!("Error indexing file {}: {}", file_id, e))
+}
+
+pub fn delete_file_index(
+    file_id: i32,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<usize, String> {
+    let deleted = match &mut **db_connection {
+        DbConnection::Sqlite(conn) => diesel::sql_query("DELETE FROM file_search WHERE rowid = ?")
+            .bind::<Integer, _>(file_id)
+            .execute(conn),
+        DbConnection::Postgres(conn) => {
+            diesel::sql_query("DELETE FROM file_search WHERE file_id = $1")
+                .bind::<Integer, _>(file_id)
+                .execute(conn)
+        }
+    };
+    deleted.map_err(|e| format!("Error deleting search entry of file {}: {}", file_id, e))
+}
+
+pub fn delete_file_index_by_owner(
+    owner: &str,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<usize, String> {
+    let deleted = match &mut **db_connection {
+        DbConnection::Sqlite(conn) => diesel::sql_query("DELETE FROM file_search WHERE owner = ?")
+            .bind::<Text, _>(owner)
+            .execute(conn),
+        DbConnection::Postgres(conn) => {
+            diesel::sql_query("DELETE FROM file_search WHERE owner = $1")
+                .bind::<Text, _>(owner)
+                .execute(conn)
+        }
+    };
+    deleted.map_err(|e| format!("Error deleting search entries: {}", e))
+}
+
+pub fn delete_all_file_index(
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<usize, String> {
+    diesel::sql_query("DELETE FROM file_search")
+        .execute(db_connection)
+        .map_err(|e| format!("Error deleting search entries: {}", e))
+}
+
+/// Files of `owner` matching every word of `words`, as prefixes, best match first.
+pub fn search_file_ids(
+    owner: &str,
+    words: &[String],
+    limit: i64,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<Vec<i32>, String> {
+    if words.is_empty() {
+        return Ok(Vec::new());
+    }
+    let rows = match &mut **db_connection {
+        DbConnection::Sqlite(conn) => {
+            // words hold only letters and digits, quoting keeps FTS5 from reading them as syntax
+            let query = words
+                .iter()
+                .map(|word| format!("\"{}\"*", word))
+                .collect::<Vec<_>>()
+                .join(" ");
+            diesel::sql_query(
+                "SELECT rowid AS file_id FROM file_search \
+                 WHERE file_search MATCH ? AND owner = ? ORDER BY rank LIMIT ?",
+            )
+            .bind::<Text, _>(query)
+            .bind::<Text, _>(owner)
+            .bind::<BigInt, _>(limit)
+            .load::<FileSearchRow>(conn)
+        }
+        DbConnection::Postgres(conn) => {
+            let query = words
+                .iter()
+                .map(|word| format!("{}:*", word))
+                .collect::<Vec<_>>()
+                .join(" & ");
+            diesel::sql_query(
+                "SELECT file_id FROM file_search \
+                 WHERE document @@ to_tsquery('simple', $1) AND owner = $2 \
+                 ORDER BY ts_rank(document, to_tsquery('simple', $1)) DESC LIMIT $3",
+            )
+            .bind::<Text, _>(query)
+            .bind::<Text, _>(owner)
+            .bind::<BigInt, _>(limit)
+            .load::<FileSearchRow>(conn)
+        }
+    }
+    .map_err(|e| format!("Error searching files: {}", e))?;
+
+    Ok(rows.into_iter().map(|row| row.file_id).collect())
+}
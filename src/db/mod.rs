@@ -1,4 +1,32 @@
 pub mod pages_db;
 pub mod siwe;
 pub mod user_profiles;
-pub mod share;
\ No newline at end of file
+pub mod share;
+pub mod file_access;
+pub mod signature_requests;
+pub mod signature_modes;
+pub mod chain_links;
+pub mod revisions;
+pub mod file_search;
+
+#[cfg(test)]
+mod tests;
+
+use diesel::pg::PgConnection;
+use diesel::sql_types::Text;
+use diesel::sqlite::SqliteConnection;
+
+/// Connection to the database `DATABASE_URL` names: a `postgres://` or `postgresql://` URL
+/// selects PostgreSQL, anything else is opened as a SQLite file.
+#[derive(diesel::MultiConnection)]
+pub enum DbConnection {
+    // tried first, SQLite opens almost any string as a file
+    Postgres(PgConnection),
+    Sqlite(SqliteConnection),
+}
+
+diesel::define_sql_function! {
+    /// `LIKE` reads `%` and `_` as wildcards and ignores case on SQLite only, addresses are
+    /// compared through `lower` instead.
+    fn lower(value: Text) -> Text;
+}
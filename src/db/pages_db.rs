@@ -1,18 +1,180 @@
-use crate::models::{NewPagesTable, PagesTable};
+use crate::db::chain_links::delete_chain_links_by_files;
+use crate::db::file_access::delete_file_access_by_files;
+use crate::db::file_search::{
+    delete_all_file_index, delete_file_index, delete_file_index_by_owner, index_file,
+};
+use crate::db::signature_requests::delete_signature_requests_by_files;
+use crate::db::revisions::{
+    delete_all_revisions, delete_revisions_by_files, delete_revisions_from, fetch_revisions,
+    fetch_revisions_for_files, insert_revisions, revision_row, revision_row_v1_3, row_revision,
+    row_revision_v1_3,
+};
+use crate::models::file::{FileListCursor, FileSummary};
+use crate::models::input::{FileListInput, FileSort, SortOrder};
+use crate::models::{NewPagesTable, PagesTable, RevisionsTable};
+use crate::protocol::v1_3::AquaChainV13;
+use crate::protocol::AquaDocument;
+use crate::verification::RevisionKind;
+use aqua_verifier_rs_types::models::hash::Hash;
+use aqua_verifier_rs_types::models::revision::Revision;
 use chrono::{NaiveDateTime, Utc};
 use diesel::r2d2::{self, ConnectionManager, PooledConnection};
-use diesel::SqliteConnection;
+use crate::db::DbConnection;
 use serde::{Deserialize, Serialize};
 
 use diesel::prelude::*;
 use diesel::prelude::*;
 use diesel::result::Error as DieselError;
+use axum::http::StatusCode;
+use std::collections::BTreeMap;
+use std::fmt;
+extern crate serde_json_path_to_error as serde_json;
+
+/// Revisions `split_page_data` takes out of a chain for the `revisions` table.
+enum ChainRevisions {
+    V1_2(Vec<(Hash, Revision)>),
+    /// The whole chain, its revisions go to the table in chain order.
+    V1_3(AquaChainV13),
+}
+
+impl ChainRevisions {
+    fn rows(&self, file_id: i32) -> Result<Vec<RevisionsTable>, String> {
+        match self {
+            ChainRevisions::V1_2(revisions) => revision_rows(file_id, 0, revisions),
+            ChainRevisions::V1_3(chain) => chain
+                .ordered()?
+                .into_iter()
+                .enumerate()
+                .map(|(position, (hash, revision))| {
+                    revision_row_v1_3(file_id, position as i32, hash, revision)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Splits stored page data into the chain header kept in `pages.page_data` and the revisions
+/// kept in the `revisions` table. v1.3 chains that do not form a single line are stored whole.
+fn split_page_data(data: &str) -> Result<(String, Option<ChainRevisions>), String> {
+    let (header, revisions) = match AquaDocument::parse(data.as_bytes()) {
+        Ok(AquaDocument::V1_2(mut doc)) => {
+            let revisions = match doc.pages.first_mut() {
+                Some(chain) => std::mem::take(&mut chain.revisions),
+                None => return Ok((data.to_string(), None)),
+            };
+            let header = serde_json::to_string(&doc)
+                .map_err(|e| format!("Failed to serialize page data: {}", e))?;
+            (header, ChainRevisions::V1_2(revisions))
+        }
+        Ok(AquaDocument::V1_3(chain)) => {
+            if chain.ordered().is_err() {
+                return Ok((data.to_string(), None));
+            }
+            let header = serde_json::to_string(&AquaChainV13 {
+                revisions: BTreeMap::new(),
+                file_index: chain.file_index.clone(),
+            })
+            .map_err(|e| format!("Failed to serialize page data: {}", e))?;
+            (header, ChainRevisions::V1_3(chain))
+        }
+        Err(_) => return Ok((data.to_string(), None)),
+    };
+    Ok((header, Some(revisions)))
+}
+
+/// Puts the revisions of `rows` back into the chain header of `page`.
+fn assemble_page_data(page: &mut PagesTable, rows: &[RevisionsTable]) -> Result<(), String> {
+    if rows.is_empty() {
+        return Ok(());
+    }
+    let document = AquaDocument::parse(page.page_data.as_bytes())
+        .map_err(|e| format!("Error parsing page data of {}: {}", page.id, e))?;
+    page.page_data = match document {
+        AquaDocument::V1_2(mut doc) => {
+            let chain = doc
+                .pages
+                .first_mut()
+                .ok_or_else(|| format!("Page {} has no chain", page.id))?;
+            chain.revisions = rows.iter().map(row_revision).collect::<Result<_, _>>()?;
+            serde_json::to_string(&doc)
+        }
+        AquaDocument::V1_3(mut chain) => {
+            chain.revisions = rows.iter().map(row_revision_v1_3).collect::<Result<_, _>>()?;
+            serde_json::to_string(&chain)
+        }
+    }
+    .map_err(|e| format!("Failed to serialize page data: {}", e))?;
+    Ok(())
+}
+
+fn revision_rows(
+    file_id: i32,
+    start: usize,
+    revisions: &[(Hash, Revision)],
+) -> Result<Vec<RevisionsTable>, String> {
+    revisions
+        .iter()
+        .enumerate()
+        .skip(start)
+        .map(|(position, (hash, revision))| revision_row(file_id, position as i32, hash, revision))
+        .collect()
+}
+
+/// Brings the stored revisions of `file_id` in line with `new_rows`. Appends only add rows,
+/// anything else rewrites the chain from the first revision that differs.
+fn sync_revisions(
+    file_id: i32,
+    new_rows: Vec<RevisionsTable>,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<(), String> {
+    let stored = fetch_revisions(file_id, db_connection)?;
+
+    let unchanged = stored
+        .iter()
+        .zip(new_rows.iter())
+        .take_while(|(old, new)| {
+            old.verification_hash == new.verification_hash && old.revision_data == new.revision_data
+        })
+        .count();
+
+    if unchanged < stored.len() {
+        delete_revisions_from(file_id, unchanged as i32, db_connection)?;
+    }
+    insert_revisions(new_rows.into_iter().skip(unchanged).collect(), db_connection)?;
+    Ok(())
+}
+
+/// Hashes and addresses a search finds the page by, from its v1.2 revision rows or the
+/// search terms of a v1.3 chain, split off or kept whole in `header`.
+fn search_hashes(
+    header: &str,
+    revisions: Option<&ChainRevisions>,
+    rows: &[RevisionsTable],
+) -> String {
+    match revisions {
+        Some(ChainRevisions::V1_3(chain)) => return chain.search_terms().join(" "),
+        Some(ChainRevisions::V1_2(_)) => {}
+        None => {
+            if let Ok(AquaDocument::V1_3(chain)) = AquaDocument::parse(header.as_bytes()) {
+                return chain.search_terms().join(" ");
+            }
+        }
+    }
+    let mut hashes: Vec<String> = Vec::new();
+    for row in rows {
+        hashes.push(row.verification_hash.clone());
+        hashes.extend(row.file_hash.clone());
+        hashes.extend(row.signer.clone());
+        hashes.extend(row.witness_tx_hash.clone());
+    }
+    hashes.join(" ")
+}
 
 
 
 pub fn insert_page_data(
     data: NewPagesTable,
-    db_connection: &mut PooledConnection<ConnectionManager<SqliteConnection>>,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
 ) -> Result<i64, String> {
     // Use Utc to get the current time as a NaiveDateTime
     let naive_datetime: NaiveDateTime = Utc::now().naive_utc();
@@ -29,10 +191,44 @@ pub fn insert_page_data(
         is_shared: data.is_shared,
     };
 
-    let inserted_id: i32 = diesel::insert_into(crate::schema::pages::table)
-        .values(record)
-        .returning(crate::schema::pages::dsl::id)
-        .get_result::<i32>(db_connection)
+    let (header, revisions) = split_page_data(&record.page_data)?;
+    let record = NewPagesTable {
+        page_data: header,
+        ..record
+    };
+
+    let inserted_id: i32 = db_connection
+        .transaction::<_, DieselError, _>(|conn| {
+            let inserted_id = diesel::insert_into(crate::schema::pages::table)
+                .values(&record)
+                .returning(crate::schema::pages::dsl::id)
+                .get_result::<i32>(conn)?;
+            let rows = match &revisions {
+                Some(revisions) => revisions.rows(inserted_id).map_err(|e| {
+                    tracing::error!("Failed to prepare revisions: {}", e);
+                    DieselError::RollbackTransaction
+                })?,
+                None => Vec::new(),
+            };
+            let hashes = search_hashes(&record.page_data, revisions.as_ref(), &rows);
+            insert_revisions(rows, conn).map_err(|e| {
+                tracing::error!("Failed to save revisions: {}", e);
+                DieselError::RollbackTransaction
+            })?;
+            index_file(
+                inserted_id,
+                &record.owner,
+                &record.name,
+                &record.extension,
+                &hashes,
+                conn,
+            )
+            .map_err(|e| {
+                tracing::error!("Failed to index file: {}", e);
+                DieselError::RollbackTransaction
+            })?;
+            Ok(inserted_id)
+        })
         .map_err(|e| format!("Error saving new siwe data: {}", e))?;
         // .unwrap(); // Provide a default value if None
 
@@ -43,17 +239,19 @@ pub fn insert_page_data(
 
 pub fn fetch_page_data(
     id_par: i32,
-    db_connection: &mut PooledConnection<ConnectionManager<SqliteConnection>>,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
 ) -> Result<PagesTable, String> {
     use crate::schema::pages::dsl::*;
 
-    let result = pages
+    let mut result = pages
         .filter(id.eq(&id_par))
         .first::<PagesTable>(db_connection)
         .map_err(|e| match e {
             DieselError::NotFound => format!("No page found with id: {:#?}", id_par),
             _ => format!("Error fetching page data: {}", e),
         })?;
+    let rows = fetch_revisions(result.id, db_connection)?;
+    assemble_page_data(&mut result, &rows)?;
    
     // Ok(PagesTable {
     //     id: result.id,
@@ -69,14 +267,15 @@ pub fn fetch_page_data(
 
 pub fn fetch_all_pages_data_per_user(
     user: String,
-    db_connection: &mut PooledConnection<ConnectionManager<SqliteConnection>>,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
 ) -> Result<Vec<PagesTable>, String> {
     use crate::schema::pages::dsl::*;
 
-    let results = pages
+    let mut results = pages
         .filter(owner.eq(&user))
         .load::<PagesTable>(db_connection) // Replace `PagesDataTable` with the correct Diesel model type
         .map_err(|e| format!("Error fetching pages for user {}: {}", user, e))?;
+    assemble_pages(&mut results, db_connection)?;
 
     // Ok(PagesDataTable {
     //     id: result.id.unwrap_or(0) as i64,
@@ -89,61 +288,384 @@ pub fn fetch_all_pages_data_per_user(
     Ok(results)
 }
 
+/// A page of at most `limit + 1` files of `owner_param` without their chains, matching the
+/// filters of `input` and in its order, after `cursor`. The extra row tells whether more follow.
+/// Signature and witness filters match the revision rows of v1.2 and v1.3 chains alike, only
+/// v1.3 chains stored whole because they fork never match them.
+pub fn fetch_file_summaries(
+    owner_param: &str,
+    input: &FileListInput,
+    cursor: Option<&FileListCursor>,
+    limit: i64,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<Vec<FileSummary>, String> {
+    use crate::schema::pages::dsl::*;
+    use crate::schema::revisions;
+
+    let mut query = pages.filter(owner.eq(owner_param.to_string())).into_boxed();
+
+    if let Some(value) = input.extension.as_deref().filter(|value| !value.is_empty()) {
+        query = query.filter(extension.eq(value.to_string()));
+    }
+    if let Some(value) = input.mode.as_deref().filter(|value| !value.is_empty()) {
+        query = query.filter(mode.eq(value.to_string()));
+    }
+    if let Some(value) = input.is_shared {
+        query = query.filter(is_shared.eq(value));
+    }
+    // created_at is `YYYY-MM-DD HH:MM:SS`, it orders as text
+    if let Some(from) = input.created_from.as_deref() {
+        query = query.filter(created_at.ge(from.to_string()));
+    }
+    if let Some(before) = input.created_before.as_deref() {
+        query = query.filter(created_at.lt(before.to_string()));
+    }
+    if let Some(signed) = input.signed {
+        let signed_files = revisions::table
+            .filter(revisions::kind.eq(RevisionKind::Signature.as_str()))
+            .select(revisions::file_id);
+        query = if signed {
+            query.filter(id.eq_any(signed_files))
+        } else {
+            query.filter(id.ne_all(signed_files))
+        };
+    }
+    if let Some(witnessed) = input.witnessed {
+        let witnessed_files = revisions::table
+            .filter(revisions::kind.eq(RevisionKind::Witness.as_str()))
+            .select(revisions::file_id);
+        query = if witnessed {
+            query.filter(id.eq_any(witnessed_files))
+        } else {
+            query.filter(id.ne_all(witnessed_files))
+        };
+    }
+    if let Some(address) = input.signer.as_deref().filter(|address| !address.is_empty()) {
+        query = query.filter(
+            id.eq_any(
+                revisions::table
+                    .filter(revisions::signer.eq(address.to_lowercase()))
+                    .select(revisions::file_id),
+            ),
+        );
+    }
+    if let Some(network) = input
+        .witness_network
+        .as_deref()
+        .filter(|network| !network.is_empty())
+    {
+        query = query.filter(
+            id.eq_any(
+                revisions::table
+                    .filter(revisions::witness_network.eq(network.to_string()))
+                    .select(revisions::file_id),
+            ),
+        );
+    }
+
+    // ties on the sort key are broken by id, the cursor continues after both
+    let ascending = input.order == SortOrder::Asc;
+    match input.sort {
+        FileSort::Id => {
+            if let Some(cursor) = cursor {
+                query = if ascending {
+                    query.filter(id.gt(cursor.id))
+                } else {
+                    query.filter(id.lt(cursor.id))
+                };
+            }
+            query = if ascending {
+                query.order(id.asc())
+            } else {
+                query.order(id.desc())
+            };
+        }
+        FileSort::Name => {
+            if let Some(cursor) = cursor {
+                let key = cursor.key.clone();
+                query = if ascending {
+                    query.filter(name.gt(key.clone()).or(name.eq(key).and(id.gt(cursor.id))))
+                } else {
+                    query.filter(name.lt(key.clone()).or(name.eq(key).and(id.lt(cursor.id))))
+                };
+            }
+            query = if ascending {
+                query.order((name.asc(), id.asc()))
+            } else {
+                query.order((name.desc(), id.desc()))
+            };
+        }
+        FileSort::CreatedAt => {
+            if let Some(cursor) = cursor {
+                let key = cursor.key.clone();
+                query = if ascending {
+                    query.filter(
+                        created_at
+                            .gt(key.clone())
+                            .or(created_at.eq(key).and(id.gt(cursor.id))),
+                    )
+                } else {
+                    query.filter(
+                        created_at
+                            .lt(key.clone())
+                            .or(created_at.eq(key).and(id.lt(cursor.id))),
+                    )
+                };
+            }
+            query = if ascending {
+                query.order((created_at.asc(), id.asc()))
+            } else {
+                query.order((created_at.desc(), id.desc()))
+            };
+        }
+    }
+
+    let rows = query
+        .select((id, name, extension, mode, owner, created_at, is_shared))
+        .limit(limit + 1)
+        .load::<SummaryRow>(db_connection)
+        .map_err(|e| format!("Error fetching files for user {}: {}", owner_param, e))?;
+
+    Ok(rows.into_iter().map(file_summary).collect())
+}
+
+/// Files of `owner_param` among `file_ids` without their chains, in the order of `file_ids`.
+pub fn fetch_file_summaries_by_ids(
+    owner_param: &str,
+    file_ids: &[i32],
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<Vec<FileSummary>, String> {
+    use crate::schema::pages::dsl::*;
+
+    let rows = pages
+        .filter(owner.eq(owner_param))
+        .filter(id.eq_any(file_ids))
+        .select((id, name, extension, mode, owner, created_at, is_shared))
+        .load::<SummaryRow>(db_connection)
+        .map_err(|e| format!("Error fetching files for user {}: {}", owner_param, e))?;
+
+    let mut summaries: Vec<FileSummary> = rows.into_iter().map(file_summary).collect();
+    summaries.sort_by_key(|summary| {
+        file_ids
+            .iter()
+            .position(|file_id| *file_id as i64 == summary.id)
+    });
+    Ok(summaries)
+}
+
+/// id, name, extension, mode, owner, created_at and is_shared of a page.
+type SummaryRow = (i32, String, String, String, String, String, bool);
+
+fn file_summary(row: SummaryRow) -> FileSummary {
+    let (file_id, file_name, file_extension, file_mode, file_owner, file_created_at, shared) = row;
+    FileSummary {
+        id: file_id as i64,
+        name: file_name,
+        extension: file_extension,
+        mode: file_mode,
+        owner: file_owner,
+        created_at: file_created_at,
+        is_shared: shared,
+    }
+}
+
+/// Assembles the chains of several pages with one query for their revisions.
+fn assemble_pages(
+    pages: &mut [PagesTable],
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<(), String> {
+    let file_ids: Vec<i32> = pages.iter().map(|page| page.id).collect();
+    let rows = fetch_revisions_for_files(&file_ids, db_connection)?;
+    for page in pages.iter_mut() {
+        let page_rows: Vec<RevisionsTable> = rows
+            .iter()
+            .filter(|row| row.file_id == page.id)
+            .cloned()
+            .collect();
+        assemble_page_data(page, &page_rows)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum PageUpdateError {
+    /// The page changed since it was read, `head` is the latest verification hash now stored.
+    Conflict { head: Option<String> },
+    Database(String),
+}
+
+impl PageUpdateError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            PageUpdateError::Conflict { .. } => StatusCode::CONFLICT,
+            PageUpdateError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Head the client has to rebase on, for conflicts.
+    pub fn head(&self) -> Option<String> {
+        match self {
+            PageUpdateError::Conflict { head } => head.clone(),
+            PageUpdateError::Database(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for PageUpdateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PageUpdateError::Conflict { head } => write!(
+                f,
+                "chain was updated concurrently, current head is {}",
+                head.as_deref().unwrap_or("unknown")
+            ),
+            PageUpdateError::Database(e) => write!(f, "Error updating page data: {}", e),
+        }
+    }
+}
+
+impl From<DieselError> for PageUpdateError {
+    fn from(e: DieselError) -> Self {
+        PageUpdateError::Database(e.to_string())
+    }
+}
+
+impl From<String> for PageUpdateError {
+    fn from(e: String) -> Self {
+        PageUpdateError::Database(e)
+    }
+}
+
+impl From<PageUpdateError> for String {
+    fn from(e: PageUpdateError) -> Self {
+        e.to_string()
+    }
+}
+
+/// Writes `data` back, provided the page is still at `data.version`. Chain, revisions and
+/// version change together or not at all.
 pub fn update_page_data(
     data: PagesTable,
-    db_connection: &mut PooledConnection<ConnectionManager<SqliteConnection>>,
-) -> Result<(), String> {
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<(), PageUpdateError> {
     use crate::schema::pages::dsl::*;
     println!("Updating");
-    let res = diesel::update(pages.find(data.id))
-        .set((
-            extension.eq(&data.extension),
-            page_data.eq(&data.page_data),
-            mode.eq(&data.mode),
-            owner.eq(&data.owner),
-            name.eq(&data.name),
-            is_shared.eq(&data.is_shared)
-        ))
-        .execute(db_connection)
-        .map_err(|e| format!("Error updating page data: {}", e))?;
+    let (header, revisions) = split_page_data(&data.page_data)?;
+    let res = db_connection.transaction::<_, PageUpdateError, _>(|conn| {
+        let res = diesel::update(pages.find(data.id).filter(version.eq(data.version)))
+            .set((
+                extension.eq(&data.extension),
+                page_data.eq(&header),
+                mode.eq(&data.mode),
+                owner.eq(&data.owner),
+                name.eq(&data.name),
+                is_shared.eq(&data.is_shared),
+                version.eq(data.version + 1),
+            ))
+            .execute(conn)?;
+        if res == 0 {
+            // another request committed first, its head is what the caller has to build on
+            let current = fetch_page_data(data.id, conn)?;
+            let head = AquaDocument::parse(current.page_data.as_bytes())
+                .ok()
+                .and_then(|document| document.latest_verification_hash());
+            return Err(PageUpdateError::Conflict { head });
+        }
+        // a chain stored whole keeps no rows
+        let rows = match &revisions {
+            Some(revisions) => revisions.rows(data.id)?,
+            None => Vec::new(),
+        };
+        index_file(
+            data.id,
+            &data.owner,
+            &data.name,
+            &data.extension,
+            &search_hashes(&header, revisions.as_ref(), &rows),
+            conn,
+        )?;
+        sync_revisions(data.id, rows, conn)?;
+        Ok(res)
+    })?;
     println!("Updating result is: {:#?}", res);
     Ok(())
 }
 
+/// Deletes the rows other tables keep per file of `file_ids`: revisions, access grants,
+/// signature requests and the links the files make.
+fn delete_file_rows(
+    file_ids: &[i32],
+    conn: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<(), DieselError> {
+    let rollback = |e: String| {
+        tracing::error!("Failed to delete file data: {}", e);
+        DieselError::RollbackTransaction
+    };
+    delete_revisions_by_files(file_ids, conn).map_err(rollback)?;
+    delete_file_access_by_files(file_ids, conn).map_err(rollback)?;
+    delete_signature_requests_by_files(file_ids, conn).map_err(rollback)?;
+    delete_chain_links_by_files(file_ids, conn).map_err(rollback)?;
+    Ok(())
+}
+
+/// Deletes the page with everything kept about it in other tables, all or nothing.
 pub fn delete_page_data(
     file_id: i32,
-    db_connection: &mut PooledConnection<ConnectionManager<SqliteConnection>>,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
 ) -> Result<i8, String> {
     use crate::schema::pages::dsl::*;
 
-    let deleted_count = diesel::delete(pages.filter(id.eq(&file_id)))
-        .execute(db_connection)
+    let deleted_count = db_connection
+        .transaction::<_, DieselError, _>(|conn| {
+            let deleted_count = diesel::delete(pages.filter(id.eq(&file_id))).execute(conn)?;
+            delete_file_rows(&[file_id], conn)?;
+            delete_file_index(file_id, conn).map_err(|e| {
+                tracing::error!("Failed to delete file index: {}", e);
+                DieselError::RollbackTransaction
+            })?;
+            Ok(deleted_count)
+        })
         .map_err(|e| format!("Error deleting page data: {}", e))?;
 
     Ok(deleted_count as i8)
 }
 
+/// Deletes every page of `user_address` with everything kept about them, all or nothing.
 pub fn delete_all_user_files(
     user_address: String,
-    db_connection: &mut PooledConnection<ConnectionManager<SqliteConnection>>,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
 ) -> Result<i8, String> {
     use crate::schema::pages::dsl::*;
 
-    let deleted_count = diesel::delete(pages.filter(owner.eq(&user_address)))
-        .execute(db_connection)
+    let deleted_count = db_connection
+        .transaction::<_, DieselError, _>(|conn| {
+            let file_ids: Vec<i32> = pages
+                .filter(owner.eq(&user_address))
+                .select(id)
+                .load(conn)?;
+            let deleted_count = diesel::delete(pages.filter(owner.eq(&user_address))).execute(conn)?;
+            delete_file_rows(&file_ids, conn)?;
+            delete_file_index_by_owner(&user_address, conn).map_err(|e| {
+                tracing::error!("Failed to delete file index: {}", e);
+                DieselError::RollbackTransaction
+            })?;
+            Ok(deleted_count)
+        })
         .map_err(|e| format!("Error deleting page data: {}", e))?;
 
     Ok(deleted_count as i8)
 }
 
 pub fn delete_all_data(
-    db_connection: &mut PooledConnection<ConnectionManager<SqliteConnection>>,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
 ) -> Result<(), String> {
     use crate::schema::pages::dsl::*;
 
     diesel::delete(pages)
         .execute(db_connection)
         .map_err(|e| format!("Error deleting all page data: {}", e))?;
+    delete_all_revisions(db_connection)?;
+    delete_all_file_index(db_connection)?;
 
     Ok(())
 }
@@ -0,0 +1,305 @@
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use crate::db::DbConnection;
+
+use crate::models::RevisionsTable;
+use crate::protocol::v1_3::{revision_kind, RevisionV13};
+use crate::verification::RevisionKind;
+use aqua_verifier_rs_types::models::hash::Hash;
+use aqua_verifier_rs_types::models::revision::Revision;
+use serde::Serialize;
+use serde_json::Value;
+extern crate serde_json_path_to_error as serde_json;
+
+/// The JSON string form of a value, the form hashes take as keys of the stored chain.
+fn json_text<T: Serialize>(value: &T) -> Result<String, String> {
+    match serde_json::to_value(value).map_err(|e| format!("Failed to serialize revision: {}", e))? {
+        Value::String(text) => Ok(text),
+        other => Ok(other.to_string()),
+    }
+}
+
+/// The row of the revision at `position` of the chain of `file_id`.
+pub fn revision_row(
+    file_id: i32,
+    position: i32,
+    verification_hash: &Hash,
+    revision: &Revision,
+) -> Result<RevisionsTable, String> {
+    let metadata = &revision.metadata;
+    Ok(RevisionsTable {
+        id: None,
+        file_id,
+        position,
+        verification_hash: json_text(verification_hash)?,
+        previous_verification_hash: metadata
+            .previous_verification_hash
+            .as_ref()
+            .map(json_text)
+            .transpose()?,
+        kind: RevisionKind::of(revision).as_str().to_string(),
+        domain_id: metadata.domain_id.clone(),
+        time_stamp: json_text(&metadata.time_stamp)?,
+        metadata_hash: json_text(&metadata.metadata_hash)?,
+        content_hash: json_text(&revision.content.content_hash)?,
+        file_hash: Some(json_text(&revision.content.content.file_hash)?),
+        signer: revision
+            .signature
+            .as_ref()
+            .map(|signature| json_text(&signature.wallet_address))
+            .transpose()?
+            .map(|address| address.to_lowercase()),
+        witness_network: revision
+            .witness
+            .as_ref()
+            .map(|witness| witness.witness_network.clone()),
+        witness_tx_hash: revision
+            .witness
+            .as_ref()
+            .map(|witness| json_text(&witness.witness_event_transaction_hash))
+            .transpose()?,
+        revision_data: serde_json::to_string(revision)
+            .map_err(|e| format!("Failed to serialize revision: {}", e))?,
+    })
+}
+
+/// The verification hash and revision a row was made from.
+pub fn row_revision(row: &RevisionsTable) -> Result<(Hash, Revision), String> {
+    let hash: Hash = serde_json::from_value(Value::String(row.verification_hash.clone()))
+        .map_err(|e| format!("Invalid verification hash {}: {}", row.verification_hash, e))?;
+    let revision: Revision = serde_json::from_str(&row.revision_data)
+        .map_err(|e| format!("Invalid revision {}: {}", row.verification_hash, e))?;
+    Ok((hash, revision))
+}
+
+/// The row of the v1.3 revision at `position` of the chain of `file_id`. v1.3 revisions have
+/// no domain, metadata or content hash, those columns stay empty.
+pub fn revision_row_v1_3(
+    file_id: i32,
+    position: i32,
+    verification_hash: &str,
+    revision: &RevisionV13,
+) -> Result<RevisionsTable, String> {
+    let field = |name: &str| {
+        revision
+            .field(name)
+            .filter(|value| !value.is_empty())
+            .map(str::to_string)
+    };
+    Ok(RevisionsTable {
+        id: None,
+        file_id,
+        position,
+        verification_hash: verification_hash.to_string(),
+        previous_verification_hash: revision.previous_verification_hash().map(str::to_string),
+        kind: revision_kind(revision).as_str().to_string(),
+        domain_id: String::new(),
+        time_stamp: field("local_timestamp").unwrap_or_default(),
+        metadata_hash: String::new(),
+        content_hash: String::new(),
+        file_hash: field("file_hash"),
+        signer: field("signature_wallet_address").map(|address| address.to_lowercase()),
+        witness_network: field("witness_network"),
+        witness_tx_hash: field("witness_transaction_hash"),
+        revision_data: serde_json::to_string(revision)
+            .map_err(|e| format!("Failed to serialize revision: {}", e))?,
+    })
+}
+
+/// The verification hash and v1.3 revision a row was made from.
+pub fn row_revision_v1_3(row: &RevisionsTable) -> Result<(String, RevisionV13), String> {
+    let revision: RevisionV13 = serde_json::from_str(&row.revision_data)
+        .map_err(|e| format!("Invalid revision {}: {}", row.verification_hash, e))?;
+    Ok((row.verification_hash.clone(), revision))
+}
+
+pub fn insert_revisions(
+    rows: Vec<RevisionsTable>,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<usize, String> {
+    if rows.is_empty() {
+        return Ok(0);
+    }
+    diesel::insert_into(crate::schema::revisions::table)
+        .values(rows)
+        .execute(db_connection)
+        .map_err(|e| format!("Error saving revisions: {}", e))
+}
+
+/// Revisions of the chain of `file_id_param` in chain order.
+pub fn fetch_revisions(
+    file_id_param: i32,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<Vec<RevisionsTable>, String> {
+    use crate::schema::revisions::dsl::*;
+
+    revisions
+        .filter(file_id.eq(file_id_param))
+        .order(position.asc())
+        .select(RevisionsTable::as_select())
+        .load::<RevisionsTable>(db_connection)
+        .map_err(|e| format!("Error fetching revisions: {}", e))
+}
+
+/// Revisions of several chains, grouped by file and in chain order.
+pub fn fetch_revisions_for_files(
+    file_ids: &[i32],
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<Vec<RevisionsTable>, String> {
+    use crate::schema::revisions::dsl::*;
+
+    revisions
+        .filter(file_id.eq_any(file_ids))
+        .order((file_id.asc(), position.asc()))
+        .select(RevisionsTable::as_select())
+        .load::<RevisionsTable>(db_connection)
+        .map_err(|e| format!("Error fetching revisions: {}", e))
+}
+
+/// Drops the revisions from `position_param` on, used when a chain is rewritten.
+pub fn delete_revisions_from(
+    file_id_param: i32,
+    position_param: i32,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<usize, String> {
+    use crate::schema::revisions::dsl::*;
+
+    diesel::delete(
+        revisions
+            .filter(file_id.eq(file_id_param))
+            .filter(position.ge(position_param)),
+    )
+    .execute(db_connection)
+    .map_err(|e| format!("Error deleting revisions: {}", e))
+}
+
+pub fn delete_revisions_by_files(
+    file_ids: &[i32],
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<usize, String> {
+    use crate::schema::revisions::dsl::*;
+
+    diesel::delete(revisions.filter(file_id.eq_any(file_ids)))
+        .execute(db_connection)
+        .map_err(|e| format!("Error deleting revisions: {}", e))
+}
+
+pub fn delete_all_revisions(
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<usize, String> {
+    use crate::schema::revisions::dsl::*;
+
+    diesel::delete(revisions)
+        .execute(db_connection)
+        .map_err(|e| format!("Error deleting revisions: {}", e))
+}
+
+/// Files with a signature revision by `address`, compared case-insensitively.
+pub fn fetch_file_ids_signed_by(
+    address: &str,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<Vec<i32>, String> {
+    use crate::schema::revisions::dsl::*;
+
+    revisions
+        .filter(signer.eq(address.to_lowercase()))
+        .select(file_id)
+        .distinct()
+        .load::<i32>(db_connection)
+        .map_err(|e| format!("Error fetching signed files: {}", e))
+}
+
+/// Files with a witness revision on `network`.
+pub fn fetch_file_ids_witnessed_on(
+    network: &str,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<Vec<i32>, String> {
+    use crate::schema::revisions::dsl::*;
+
+    revisions
+        .filter(witness_network.eq(network))
+        .select(file_id)
+        .distinct()
+        .load::<i32>(db_connection)
+        .map_err(|e| format!("Error fetching witnessed files: {}", e))
+}
+
+/// Whether a chain of `owner_address` starts with the file `key`. Only the genesis row counts,
+/// later revisions carry whatever file hash their chain claims.
+pub fn owner_has_file(
+    owner_address: &str,
+    key: &str,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<bool, String> {
+    use crate::schema::{pages, revisions};
+
+    revisions::table
+        .filter(revisions::position.eq(0))
+        .filter(revisions::file_hash.eq(key))
+        .filter(
+            revisions::file_id.eq_any(
+                pages::table
+                    .filter(pages::owner.eq(owner_address))
+                    .select(pages::id),
+            ),
+        )
+        .select(revisions::file_id)
+        .first::<i32>(db_connection)
+        .optional()
+        .map(|found| found.is_some())
+        .map_err(|e| format!("Error fetching file owners: {}", e))
+}
+
+/// File hashes referenced by the chains of `file_ids`, read before they are deleted.
+pub fn fetch_file_hashes(
+    file_ids: &[i32],
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<Vec<String>, String> {
+    use crate::schema::revisions::dsl::*;
+
+    revisions
+        .filter(file_id.eq_any(file_ids))
+        .select(file_hash)
+        .distinct()
+        .load::<Option<String>>(db_connection)
+        .map(|hashes| hashes.into_iter().flatten().collect())
+        .map_err(|e| format!("Error fetching file hashes: {}", e))
+}
+
+/// File hashes referenced by the chains of `owner_address`.
+pub fn fetch_owner_file_hashes(
+    owner_address: &str,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<Vec<String>, String> {
+    use crate::schema::{pages, revisions};
+
+    revisions::table
+        .filter(
+            revisions::file_id.eq_any(
+                pages::table
+                    .filter(pages::owner.eq(owner_address))
+                    .select(pages::id),
+            ),
+        )
+        .select(revisions::file_hash)
+        .distinct()
+        .load::<Option<String>>(db_connection)
+        .map(|hashes| hashes.into_iter().flatten().collect())
+        .map_err(|e| format!("Error fetching file hashes: {}", e))
+}
+
+/// Whether any stored chain still references the file `key`.
+pub fn file_hash_referenced(
+    key: &str,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<bool, String> {
+    use crate::schema::revisions::dsl::*;
+
+    revisions
+        .filter(file_hash.eq(key))
+        .select(file_id)
+        .first::<i32>(db_connection)
+        .optional()
+        .map(|found| found.is_some())
+        .map_err(|e| format!("Error fetching file hashes: {}", e))
+}
@@ -1,12 +1,13 @@
 use diesel::prelude::*;
 use diesel::r2d2::{ConnectionManager, PooledConnection}; // This will import RunQueryDsl
+use crate::db::DbConnection;
 
 use crate::models::{ShareDataTable, DB_POOL};
 use serde::{Deserialize, Serialize};
 
 pub fn insert_share_data(
     data: ShareDataTable, 
-    db_connection: &mut PooledConnection<ConnectionManager<SqliteConnection>>
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>
 ) -> Result<i64, String> {
   
     let inserted_id: i32 = diesel::insert_into(crate::schema::share_data::table)
@@ -20,7 +21,7 @@ pub fn insert_share_data(
 }
 
 pub fn fetch_all_share_data(
-    db_connection: &mut PooledConnection<ConnectionManager<SqliteConnection>>
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>
 ) -> Result<Vec<ShareDataTable>, String> {
     use crate::schema::share_data::dsl::*;
 
@@ -32,7 +33,7 @@ pub fn fetch_all_share_data(
 
 pub fn fetch_share_data_by_address(
     identifier_param: &str,
-    db_connection: &mut PooledConnection<ConnectionManager<SqliteConnection>>
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>
 ) -> Result<Vec<ShareDataTable>, String> {
     use crate::schema::share_data::dsl::*;
 
@@ -45,7 +46,7 @@ pub fn fetch_share_data_by_address(
 
 // pub fn fetch_share_session_by_nonce(
 //     nonce_value: &str,
-//     db_connection: &mut PooledConnection<ConnectionManager<SqliteConnection>>,
+//     db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
 // ) -> Result<ShareDataTable, String> {
 //     use crate::schema::siwe_sessions::dsl::*;
 
@@ -57,7 +58,7 @@ pub fn fetch_share_data_by_address(
 
 pub fn delete_share_session_by_nonce(
     identifier_param: &str,
-    db_connection: &mut PooledConnection<ConnectionManager<SqliteConnection>>,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
 ) -> Result<usize, String> {
     use crate::schema::share_data::dsl::*;
 
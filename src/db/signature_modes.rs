@@ -0,0 +1,32 @@
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use crate::db::DbConnection;
+
+use crate::models::SignatureModeTable;
+
+pub fn insert_signature_mode(
+    data: SignatureModeTable,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<i64, String> {
+    let inserted_id: i32 = diesel::insert_into(crate::schema::signature_modes::table)
+        .values(data)
+        .returning(crate::schema::signature_modes::dsl::id)
+        .get_result::<Option<i32>>(db_connection)
+        .map_err(|e| format!("Error saving signature mode: {}", e))?
+        .unwrap_or(-1);
+
+    Ok(inserted_id as i64)
+}
+
+pub fn fetch_signature_modes(
+    verification_hashes: &[String],
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<Vec<SignatureModeTable>, String> {
+    use crate::schema::signature_modes::dsl::*;
+
+    signature_modes
+        .filter(verification_hash.eq_any(verification_hashes))
+        .select(SignatureModeTable::as_select())
+        .load::<SignatureModeTable>(db_connection)
+        .map_err(|e| format!("Error fetching signature modes: {}", e))
+}
@@ -0,0 +1,109 @@
+use diesel::prelude::*;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use crate::db::{lower, DbConnection};
+
+use crate::models::signature_requests::SIGNATURE_REQUEST_PENDING;
+use crate::models::SignatureRequestTable;
+
+pub fn insert_signature_request(
+    data: SignatureRequestTable,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<i64, String> {
+    let inserted_id: i32 = diesel::insert_into(crate::schema::signature_requests::table)
+        .values(data)
+        .returning(crate::schema::signature_requests::dsl::id)
+        .get_result::<Option<i32>>(db_connection)
+        .map_err(|e| format!("Error saving signature request: {}", e))?
+        .unwrap_or(-1);
+
+    Ok(inserted_id as i64)
+}
+
+pub fn fetch_signature_request(
+    request_id: i32,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<SignatureRequestTable, String> {
+    use crate::schema::signature_requests::dsl::*;
+
+    signature_requests
+        .filter(id.eq(request_id))
+        .select(SignatureRequestTable::as_select())
+        .first::<SignatureRequestTable>(db_connection)
+        .map_err(|e| format!("Error fetching signature request: {}", e))
+}
+
+pub fn fetch_signature_requests_for_signer(
+    address_param: &str,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<Vec<SignatureRequestTable>, String> {
+    use crate::schema::signature_requests::dsl::*;
+
+    // addresses are compared case insensitively, checksummed and lower case forms are both in use
+    signature_requests
+        .filter(lower(signer).eq(address_param.to_lowercase()))
+        .order(id.desc())
+        .select(SignatureRequestTable::as_select())
+        .load::<SignatureRequestTable>(db_connection)
+        .map_err(|e| format!("Error fetching signature requests: {}", e))
+}
+
+pub fn fetch_signature_requests_by_file(
+    file_id_param: i32,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<Vec<SignatureRequestTable>, String> {
+    use crate::schema::signature_requests::dsl::*;
+
+    signature_requests
+        .filter(file_id.eq(file_id_param))
+        .order(id.desc())
+        .select(SignatureRequestTable::as_select())
+        .load::<SignatureRequestTable>(db_connection)
+        .map_err(|e| format!("Error fetching signature requests: {}", e))
+}
+
+pub fn fetch_pending_signature_requests(
+    file_id_param: i32,
+    address_param: &str,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<Vec<SignatureRequestTable>, String> {
+    use crate::schema::signature_requests::dsl::*;
+
+    signature_requests
+        .filter(file_id.eq(file_id_param))
+        .filter(lower(signer).eq(address_param.to_lowercase()))
+        .filter(status.eq(SIGNATURE_REQUEST_PENDING))
+        .select(SignatureRequestTable::as_select())
+        .load::<SignatureRequestTable>(db_connection)
+        .map_err(|e| format!("Error fetching signature requests: {}", e))
+}
+
+pub fn update_signature_request_status(
+    request_id: i32,
+    status_param: &str,
+    verification_hash_param: Option<String>,
+    updated_time_param: &str,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<(), String> {
+    use crate::schema::signature_requests::dsl::*;
+
+    diesel::update(signature_requests.filter(id.eq(request_id)))
+        .set((
+            status.eq(status_param),
+            verification_hash.eq(verification_hash_param),
+            updated_time.eq(updated_time_param),
+        ))
+        .execute(db_connection)
+        .map_err(|e| format!("Error updating signature request: {}", e))?;
+    Ok(())
+}
+
+pub fn delete_signature_requests_by_files(
+    file_ids: &[i32],
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<usize, String> {
+    use crate::schema::signature_requests::dsl::*;
+
+    diesel::delete(signature_requests.filter(file_id.eq_any(file_ids)))
+        .execute(db_connection)
+        .map_err(|e| format!("Error deleting signature requests: {}", e))
+}
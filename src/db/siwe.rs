@@ -1,15 +1,18 @@
 use diesel::prelude::*;
 use diesel::r2d2::{ConnectionManager, PooledConnection}; // This will import RunQueryDsl
+use crate::db::DbConnection;
+use crate::auth::nonce::siwe_session_expires_at;
 use crate::auth::SiweSession;
-use crate::models::{SiweSessionsTable, DB_POOL};
+use crate::models::{SiweNonceTable, SiweSessionsTable, DB_POOL};
 use serde::{Deserialize, Serialize};
 
 pub fn insert_siwe_data(
     data: SiweSession, 
-    db_connection: &mut PooledConnection<ConnectionManager<SqliteConnection>>
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>
 ) -> Result<i64, String> {
     let record =  &SiweSessionsTable {
         id: None,
+        expires_at: siwe_session_expires_at(&data.issued_at, &data.expiration_time),
         address: data.address,
         nonce: data.nonce,
         issued_at: data.issued_at,
@@ -25,7 +28,7 @@ pub fn insert_siwe_data(
     Ok(inserted_id as i64)
 }
 pub fn fetch_siwe_data(
-    db_connection: &mut PooledConnection<ConnectionManager<SqliteConnection>>
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>
 ) -> Result<Vec<SiweSessionsTable>, String> {
     use crate::schema::siwe_sessions::dsl::*;
 
@@ -37,7 +40,7 @@ pub fn fetch_siwe_data(
 
 pub fn fetch_siwe_data_by_address(
     address_param: &str,
-    db_connection: &mut PooledConnection<ConnectionManager<SqliteConnection>>
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>
 ) -> Result<Vec<SiweSessionsTable>, String> {
     use crate::schema::siwe_sessions::dsl::*;
 
@@ -50,7 +53,7 @@ pub fn fetch_siwe_data_by_address(
 
 pub fn fetch_siwe_session_by_nonce(
     nonce_value: &str,
-    db_connection: &mut PooledConnection<ConnectionManager<SqliteConnection>>,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
 ) -> Result<SiweSessionsTable, String> {
     use crate::schema::siwe_sessions::dsl::*;
 
@@ -62,11 +65,67 @@ pub fn fetch_siwe_session_by_nonce(
 
 pub fn delete_siwe_session_by_nonce(
     nonce_value: &str,
-    db_connection: &mut PooledConnection<ConnectionManager<SqliteConnection>>,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
 ) -> Result<usize, String> {
     use crate::schema::siwe_sessions::dsl::*;
 
     diesel::delete(siwe_sessions.filter(nonce.eq(nonce_value)))
         .execute(db_connection)
         .map_err(|e| format!("Error deleting SIWE session for nonce: {}", e))
+}
+
+/// Deletes the sessions that ended by `now`, and those whose end could not be worked out.
+pub fn delete_expired_siwe_sessions(
+    now: &str,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<usize, String> {
+    use crate::schema::siwe_sessions::dsl::*;
+
+    diesel::delete(siwe_sessions.filter(expires_at.le(now).or(expires_at.is_null())))
+        .execute(db_connection)
+        .map_err(|e| format!("Error deleting expired SIWE sessions: {}", e))
+}
+
+pub fn insert_siwe_nonce(
+    data: SiweNonceTable,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<i64, String> {
+    let inserted_id: i32 = diesel::insert_into(crate::schema::siwe_nonces::table)
+        .values(&data)
+        .returning(crate::schema::siwe_nonces::dsl::id)
+        .get_result::<Option<i32>>(db_connection)
+        .map_err(|e| format!("Error saving new siwe nonce: {}", e))?
+        .unwrap_or(-1);
+
+    Ok(inserted_id as i64)
+}
+
+/// Deletes the nonce if it is still pending, returns false when it was unknown, used or expired.
+pub fn consume_siwe_nonce(
+    nonce_value: &str,
+    now: &str,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<bool, String> {
+    use crate::schema::siwe_nonces::dsl::*;
+
+    let deleted = diesel::delete(
+        siwe_nonces
+            .filter(nonce.eq(nonce_value))
+            .filter(expires_at.gt(now)),
+    )
+    .execute(db_connection)
+    .map_err(|e| format!("Error consuming SIWE nonce: {}", e))?;
+
+    Ok(deleted == 1)
+}
+
+pub fn delete_expired_siwe_nonces(
+    now: &str,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<usize, String> {
+    use crate::schema::siwe_nonces::dsl::*;
+
+    diesel::delete(siwe_nonces.filter(expires_at.le(now)))
+        .execute(db_connection)
+        .map_err(|e| format!("Error deleting expired SIWE nonces: {}", e))
 }
\ No newline at end of file
@@ -0,0 +1,460 @@
+//! Runs the migrations and the DB layer against a fresh SQLite file and, when
+//! `POSTGRES_TEST_URL` names a database, against PostgreSQL as well.
+
+use crate::auth::nonce::format_nonce_time;
+use crate::auth::permissions::WRITE_ACCESS;
+use crate::auth::SiweSession;
+use crate::db::file_access::{fetch_file_access_by_file, insert_file_access};
+use crate::db::file_search::{search_file_ids, search_words};
+use crate::db::pages_db::{
+    delete_all_user_files, delete_page_data, fetch_file_summaries, fetch_page_data,
+    insert_page_data, update_page_data, PageUpdateError,
+};
+use crate::db::siwe::{
+    delete_expired_siwe_sessions, fetch_siwe_session_by_nonce, insert_siwe_data,
+};
+use crate::db::revisions::{
+    fetch_file_hashes, fetch_owner_file_hashes, fetch_revisions, file_hash_referenced,
+    owner_has_file,
+};
+use crate::db::DbConnection;
+use crate::models::input::FileListInput;
+use crate::models::{FileAccessTable, NewPagesTable};
+use crate::protocol::v1_3::{
+    append_signature_revision, append_witness_revision, genesis_chain, sha256_hex, AquaChainV13,
+    WitnessV13,
+};
+use crate::protocol::AquaDocument;
+use crate::util::run_db_migrations;
+use crate::verification::SignatureScheme;
+use diesel::r2d2::{self, ConnectionManager, PooledConnection};
+use chrono::{Duration, SecondsFormat, Utc};
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+use tempfile::TempDir;
+extern crate serde_json_path_to_error as serde_json;
+
+/// v1.2 chain with a content, a signature and a witness revision.
+const SAMPLE_V1_2: &str = include_str!("../../templates/sample.json");
+const SAMPLE_SIGNER: &str = "0x254b0d7b63342fcb8955db82e95c21d72efdb6f7";
+const V1_3_SIGNER: &str = "0x8DbA7F5bD4cF4c3A0D0a2D8b5F0bA1C2e3D4f5A6";
+
+type Conn = PooledConnection<ConnectionManager<DbConnection>>;
+
+/// A migrated database, the directory keeps a SQLite file alive.
+struct Backend {
+    name: &'static str,
+    pool: r2d2::Pool<ConnectionManager<DbConnection>>,
+    _dir: Option<TempDir>,
+}
+
+impl Backend {
+    fn new(name: &'static str, url: String, dir: Option<TempDir>) -> Self {
+        let pool = r2d2::Pool::builder()
+            .max_size(2)
+            .build(ConnectionManager::<DbConnection>::new(url))
+            .expect("Failed to create database pool");
+        run_db_migrations(&mut pool.get().unwrap()).expect("Failed to run migrations");
+        Backend {
+            name,
+            pool,
+            _dir: dir,
+        }
+    }
+
+    fn conn(&self) -> Conn {
+        self.pool.get().unwrap()
+    }
+}
+
+/// SQLite always, PostgreSQL only when `POSTGRES_TEST_URL` is set.
+fn backends() -> Vec<Backend> {
+    let dir = TempDir::new().unwrap();
+    let sqlite_url = dir.path().join("pages.db").to_string_lossy().to_string();
+    let mut backends = vec![Backend::new("sqlite", sqlite_url, Some(dir))];
+    match std::env::var("POSTGRES_TEST_URL") {
+        Ok(url) if !url.is_empty() => backends.push(Backend::new("postgres", url, None)),
+        _ => eprintln!("POSTGRES_TEST_URL not set, skipping PostgreSQL"),
+    }
+    backends
+}
+
+/// An owner no earlier run left pages for, a PostgreSQL database outlives the tests.
+fn test_owner() -> String {
+    let suffix: String = thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(12)
+        .map(char::from)
+        .collect();
+    format!("0xtest{}", suffix.to_lowercase())
+}
+
+fn new_page(owner: &str, name: &str, page_data: String) -> NewPagesTable {
+    NewPagesTable {
+        name: name.to_string(),
+        extension: "txt".to_string(),
+        page_data,
+        owner: owner.to_string(),
+        mode: "private".to_string(),
+        created_at: String::new(),
+        is_shared: false,
+    }
+}
+
+fn v1_3_chain(signed: bool) -> AquaChainV13 {
+    let mut chain = genesis_chain("notes.txt", &sha256_hex(b"notes"));
+    if signed {
+        append_signature_revision(
+            &mut chain,
+            "0x1234",
+            "0x04abcd",
+            V1_3_SIGNER,
+            &SignatureScheme::PersonalSign,
+        )
+        .unwrap();
+        append_witness_revision(
+            &mut chain,
+            WitnessV13 {
+                merkle_root: "ab".repeat(32),
+                merkle_proof: Vec::new(),
+                network: "sepolia".to_string(),
+                transaction_hash: "cd".repeat(32),
+                smart_contract_address: "0x45f59310ADD88E6d23ca58A0Fa7A55BEE6d2a611".to_string(),
+                sender_account_address: V1_3_SIGNER.to_string(),
+            },
+        )
+        .unwrap();
+    }
+    chain
+}
+
+fn json(data: &str) -> serde_json::Value {
+    serde_json::from_str(data).unwrap()
+}
+
+/// The document as its types serialize it, so stored and original chains compare field by field.
+fn normalized(data: &str) -> serde_json::Value {
+    match AquaDocument::parse(data.as_bytes()).unwrap() {
+        AquaDocument::V1_2(doc) => serde_json::to_value(doc).unwrap(),
+        AquaDocument::V1_3(chain) => serde_json::to_value(chain).unwrap(),
+    }
+}
+
+fn insert(conn: &mut Conn, owner: &str, name: &str, page_data: String) -> i32 {
+    insert_page_data(new_page(owner, name, page_data), conn).unwrap() as i32
+}
+
+fn grant_write(conn: &mut Conn, file_id: i32) {
+    let grant = FileAccessTable {
+        id: None,
+        file_id,
+        address: test_owner(),
+        access: WRITE_ACCESS.to_string(),
+        created_time: String::new(),
+    };
+    insert_file_access(grant, conn).unwrap();
+}
+
+fn listed_ids(conn: &mut Conn, owner: &str, input: FileListInput) -> Vec<i64> {
+    fetch_file_summaries(owner, &input, None, 50, conn)
+        .unwrap()
+        .into_iter()
+        .map(|summary| summary.id)
+        .collect()
+}
+
+#[test]
+fn v1_2_chain_round_trips_through_revision_rows() {
+    for backend in backends() {
+        let mut conn = backend.conn();
+        let owner = test_owner();
+        let file_id = insert(&mut conn, &owner, "sample", SAMPLE_V1_2.to_string());
+
+        let rows = fetch_revisions(file_id, &mut conn).unwrap();
+        let expected = json(SAMPLE_V1_2)["pages"][0]["revisions"]
+            .as_object()
+            .unwrap()
+            .len();
+        assert_eq!(rows.len(), expected, "{}", backend.name);
+        assert!(
+            rows.iter()
+                .any(|row| row.signer.as_deref() == Some(SAMPLE_SIGNER)),
+            "{}",
+            backend.name
+        );
+
+        let page = fetch_page_data(file_id, &mut conn).unwrap();
+        assert_eq!(
+            normalized(&page.page_data),
+            normalized(SAMPLE_V1_2),
+            "{}",
+            backend.name
+        );
+
+        delete_all_user_files(owner, &mut conn).unwrap();
+    }
+}
+
+#[test]
+fn v1_3_chain_round_trips_through_revision_rows() {
+    for backend in backends() {
+        let mut conn = backend.conn();
+        let owner = test_owner();
+        let chain = v1_3_chain(true);
+        let file_id = insert(&mut conn, &owner, "notes", serde_json::to_string(&chain).unwrap());
+
+        let rows = fetch_revisions(file_id, &mut conn).unwrap();
+        let kinds: Vec<&str> = rows.iter().map(|row| row.kind.as_str()).collect();
+        assert_eq!(kinds, ["content", "signature", "witness"], "{}", backend.name);
+        assert_eq!(
+            rows[1].signer.as_deref(),
+            Some(V1_3_SIGNER.to_lowercase().as_str()),
+            "{}",
+            backend.name
+        );
+        assert_eq!(rows[2].witness_network.as_deref(), Some("sepolia"), "{}", backend.name);
+
+        let page = fetch_page_data(file_id, &mut conn).unwrap();
+        assert_eq!(
+            normalized(&page.page_data),
+            serde_json::to_value(&chain).unwrap(),
+            "{}",
+            backend.name
+        );
+
+        delete_all_user_files(owner, &mut conn).unwrap();
+    }
+}
+
+#[test]
+fn listing_filters_match_both_protocol_versions() {
+    for backend in backends() {
+        let mut conn = backend.conn();
+        let owner = test_owner();
+        let v1_2 = insert(&mut conn, &owner, "sample", SAMPLE_V1_2.to_string()) as i64;
+        let signed = insert(
+            &mut conn,
+            &owner,
+            "signed",
+            serde_json::to_string(&v1_3_chain(true)).unwrap(),
+        ) as i64;
+        let unsigned = insert(
+            &mut conn,
+            &owner,
+            "unsigned",
+            serde_json::to_string(&v1_3_chain(false)).unwrap(),
+        ) as i64;
+
+        let mut ids = listed_ids(
+            &mut conn,
+            &owner,
+            FileListInput {
+                signed: Some(true),
+                ..Default::default()
+            },
+        );
+        ids.sort();
+        assert_eq!(ids, [v1_2, signed], "{}", backend.name);
+
+        let ids = listed_ids(
+            &mut conn,
+            &owner,
+            FileListInput {
+                witnessed: Some(false),
+                ..Default::default()
+            },
+        );
+        assert_eq!(ids, [unsigned], "{}", backend.name);
+
+        let ids = listed_ids(
+            &mut conn,
+            &owner,
+            FileListInput {
+                signer: Some(V1_3_SIGNER.to_string()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(ids, [signed], "{}", backend.name);
+
+        let mut ids = listed_ids(
+            &mut conn,
+            &owner,
+            FileListInput {
+                witness_network: Some("sepolia".to_string()),
+                ..Default::default()
+            },
+        );
+        ids.sort();
+        assert_eq!(ids, [v1_2, signed], "{}", backend.name);
+
+        delete_all_user_files(owner, &mut conn).unwrap();
+    }
+}
+
+#[test]
+fn update_rewrites_rows_and_rejects_stale_versions() {
+    for backend in backends() {
+        let mut conn = backend.conn();
+        let owner = test_owner();
+        let file_id = insert(
+            &mut conn,
+            &owner,
+            "notes",
+            serde_json::to_string(&v1_3_chain(false)).unwrap(),
+        );
+
+        let stale = fetch_page_data(file_id, &mut conn).unwrap();
+        let mut signed: AquaChainV13 = serde_json::from_str(&stale.page_data).unwrap();
+        append_signature_revision(
+            &mut signed,
+            "0x1234",
+            "0x04abcd",
+            V1_3_SIGNER,
+            &SignatureScheme::PersonalSign,
+        )
+        .unwrap();
+        let head = signed.latest_verification_hash();
+
+        let mut update = stale.clone();
+        update.page_data = serde_json::to_string(&signed).unwrap();
+        update_page_data(update.clone(), &mut conn).unwrap();
+        assert_eq!(fetch_revisions(file_id, &mut conn).unwrap().len(), 2, "{}", backend.name);
+
+        match update_page_data(update, &mut conn) {
+            Err(PageUpdateError::Conflict { head: current }) => {
+                assert_eq!(current, head, "{}", backend.name)
+            }
+            other => panic!("{}: expected a conflict, got {:?}", backend.name, other),
+        }
+
+        delete_all_user_files(owner, &mut conn).unwrap();
+    }
+}
+
+#[test]
+fn deletes_remove_revisions_grants_and_search_entries() {
+    for backend in backends() {
+        let mut conn = backend.conn();
+        let owner = test_owner();
+        let chain = v1_3_chain(true);
+        let file_hash = chain.file_hash().unwrap();
+        let v1_3 = insert(&mut conn, &owner, "notes", serde_json::to_string(&chain).unwrap());
+        let v1_2 = insert(&mut conn, &owner, "sample", SAMPLE_V1_2.to_string());
+        grant_write(&mut conn, v1_3);
+        grant_write(&mut conn, v1_2);
+
+        let words = search_words(&file_hash);
+        assert_eq!(
+            search_file_ids(&owner, &words, 10, &mut conn).unwrap(),
+            [v1_3],
+            "{}",
+            backend.name
+        );
+
+        assert_eq!(delete_page_data(v1_3, &mut conn).unwrap(), 1, "{}", backend.name);
+        assert!(fetch_page_data(v1_3, &mut conn).is_err(), "{}", backend.name);
+        assert!(fetch_revisions(v1_3, &mut conn).unwrap().is_empty(), "{}", backend.name);
+        assert!(fetch_file_access_by_file(v1_3, &mut conn).unwrap().is_empty(), "{}", backend.name);
+        assert!(
+            search_file_ids(&owner, &words, 10, &mut conn).unwrap().is_empty(),
+            "{}",
+            backend.name
+        );
+
+        assert_eq!(delete_all_user_files(owner.clone(), &mut conn).unwrap(), 1, "{}", backend.name);
+        assert!(fetch_revisions(v1_2, &mut conn).unwrap().is_empty(), "{}", backend.name);
+        assert!(fetch_file_access_by_file(v1_2, &mut conn).unwrap().is_empty(), "{}", backend.name);
+        assert!(
+            listed_ids(&mut conn, &owner, FileListInput::default()).is_empty(),
+            "{}",
+            backend.name
+        );
+    }
+}
+
+#[test]
+fn only_genesis_files_of_the_owner_count_as_theirs() {
+    for backend in backends() {
+        let mut conn = backend.conn();
+        let owner = test_owner();
+        let other = test_owner();
+        let chain = v1_3_chain(true);
+        let file_hash = chain.file_hash().unwrap();
+        insert(&mut conn, &owner, "notes", serde_json::to_string(&chain).unwrap());
+
+        assert!(owner_has_file(&owner, &file_hash, &mut conn).unwrap(), "{}", backend.name);
+        assert!(!owner_has_file(&other, &file_hash, &mut conn).unwrap(), "{}", backend.name);
+
+        delete_all_user_files(owner.clone(), &mut conn).unwrap();
+        assert!(!owner_has_file(&owner, &file_hash, &mut conn).unwrap(), "{}", backend.name);
+    }
+}
+
+#[test]
+fn file_hashes_stay_referenced_until_the_last_chain_is_deleted() {
+    for backend in backends() {
+        let mut conn = backend.conn();
+        let owner = test_owner();
+        let other = test_owner();
+        // a file of its own, other tests share the PostgreSQL database
+        let file_hash = sha256_hex(owner.as_bytes());
+        let chain = serde_json::to_string(&genesis_chain("notes.txt", &file_hash)).unwrap();
+        let mine = insert(&mut conn, &owner, "notes", chain.clone());
+        insert(&mut conn, &other, "notes", chain);
+
+        assert_eq!(
+            fetch_file_hashes(&[mine], &mut conn).unwrap(),
+            [file_hash.clone()],
+            "{}",
+            backend.name
+        );
+        assert_eq!(
+            fetch_owner_file_hashes(&owner, &mut conn).unwrap(),
+            [file_hash.clone()],
+            "{}",
+            backend.name
+        );
+
+        delete_page_data(mine, &mut conn).unwrap();
+        assert!(file_hash_referenced(&file_hash, &mut conn).unwrap(), "{}", backend.name);
+        delete_all_user_files(other, &mut conn).unwrap();
+        assert!(!file_hash_referenced(&file_hash, &mut conn).unwrap(), "{}", backend.name);
+    }
+}
+
+#[test]
+fn purge_deletes_expired_and_unreadable_sessions() {
+    for backend in backends() {
+        let mut conn = backend.conn();
+        let now = Utc::now();
+        let time = |offset: Duration| (now + offset).to_rfc3339_opts(SecondsFormat::Secs, true);
+        let sessions = [
+            (time(Duration::days(-2)), None, false),
+            (time(Duration::hours(-2)), Some(time(Duration::hours(-1))), false),
+            (time(Duration::hours(-2)), Some(time(Duration::hours(1))), true),
+            (time(Duration::hours(-1)), None, true),
+            ("not a time".to_string(), None, false),
+        ];
+
+        let mut nonces = Vec::new();
+        for (issued_at, expiration_time, _) in &sessions {
+            let nonce = test_owner();
+            let session = SiweSession {
+                address: test_owner(),
+                nonce: nonce.clone(),
+                issued_at: issued_at.clone(),
+                expiration_time: expiration_time.clone(),
+            };
+            insert_siwe_data(session, &mut conn).unwrap();
+            nonces.push(nonce);
+        }
+
+        delete_expired_siwe_sessions(&format_nonce_time(now), &mut conn).unwrap();
+        for (nonce, (_, _, kept)) in nonces.iter().zip(&sessions) {
+            let found = fetch_siwe_session_by_nonce(nonce, &mut conn);
+            assert_eq!(found.is_ok(), *kept, "{} {:?}", backend.name, found);
+        }
+        delete_expired_siwe_sessions(&format_nonce_time(now + Duration::days(2)), &mut conn)
+            .unwrap();
+    }
+}
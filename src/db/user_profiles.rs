@@ -1,9 +1,10 @@
 use std::env;
 
 use crate::models::{PagesTable, UserProfilesTable, DB_POOL};
+use crate::protocol::ProtocolVersion;
 use chrono::{NaiveDateTime, Utc};
 use diesel::r2d2::{self, ConnectionManager, PooledConnection};
-use diesel::SqliteConnection;
+use crate::db::DbConnection;
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
 use serde::{Deserialize, Serialize};
@@ -23,7 +24,7 @@ pub fn create_domain() -> String {
 
 pub fn insert_user_profile_data(
     address_par: String,
-    db_connection: &mut PooledConnection<ConnectionManager<SqliteConnection>>,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
 ) -> Result<UserProfilesTable, String> {
     let user_profile = fetch_user_profile(address_par.clone(), db_connection);
 
@@ -50,6 +51,7 @@ pub fn insert_user_profile_data(
         domain_name: create_domain(),
         chain: env::var("CHAIN").unwrap_or_default(),
         file_mode: env::var("FILE_MODE").unwrap_or_default(),
+        protocol_version: ProtocolVersion::for_profile(None).to_string(),
     };
 
     let inserted_id: i32 = diesel::insert_into(crate::schema::user_profiles::table)
@@ -65,7 +67,7 @@ pub fn insert_user_profile_data(
 
 pub fn fetch_user_profile(
     address_par: String,
-    db_connection: &mut PooledConnection<ConnectionManager<SqliteConnection>>,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
 ) -> Result<UserProfilesTable, String> {
     use crate::schema::user_profiles::dsl::*;
 
@@ -80,7 +82,7 @@ pub fn fetch_user_profile(
 }
 
 pub fn fetch_all_profiles(
-    db_connection: &mut PooledConnection<ConnectionManager<SqliteConnection>>,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
 ) -> Result<Vec<UserProfilesTable>, String> {
     use crate::schema::user_profiles::dsl::*;
 
@@ -93,7 +95,7 @@ pub fn fetch_all_profiles(
 
 pub fn update_user_profile(
     data: UserProfilesTable,
-    db_connection: &mut PooledConnection<ConnectionManager<SqliteConnection>>,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
 ) -> Result<(), String> {
     use crate::schema::user_profiles::dsl::*;
 
@@ -104,6 +106,7 @@ pub fn update_user_profile(
             file_mode.eq(&data.file_mode),
             domain_name.eq(&data.domain_name),
             contract_address.eq(&data.contract_address),
+            protocol_version.eq(&data.protocol_version),
         ))
         .execute(db_connection)
         .map_err(|e| format!("Error updating page data: {}", e))?;
@@ -113,7 +116,7 @@ pub fn update_user_profile(
 
 pub fn delete_user_profile(
     address_par: String,
-    db_connection: &mut PooledConnection<ConnectionManager<SqliteConnection>>,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
 ) -> Result<i8, String> {
     use crate::schema::user_profiles::dsl::*;
 
@@ -125,7 +128,7 @@ pub fn delete_user_profile(
 }
 
 pub fn delete_all_profiles(
-    db_connection: &mut PooledConnection<ConnectionManager<SqliteConnection>>,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
 ) -> Result<(), String> {
     use crate::schema::user_profiles::dsl::*;
 
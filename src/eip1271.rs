@@ -0,0 +1,212 @@
+use crate::networks::network_registry;
+use ethers::abi::{encode, Token};
+use ethers::providers::{Http, Middleware, Provider};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, Bytes, TransactionRequest};
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// `bytes4(keccak256("isValidSignature(bytes32,bytes)"))`, both the selector and the value a
+/// contract wallet returns for a valid signature.
+pub const EIP1271_MAGIC_VALUE: [u8; 4] = [0x16, 0x26, 0xba, 0x7e];
+
+/// Longest signature a wallet contract is asked about, enough for a Safe with dozens of owners.
+pub const MAX_CONTRACT_SIGNATURE_BYTES: usize = 4096;
+
+const DEFAULT_NO_CODE_CACHE_SECONDS: u64 = 300;
+/// Bounds the cache, it is cleared once this many addresses without code are remembered.
+const MAX_NO_CODE_ENTRIES: usize = 10_000;
+
+/// Addresses found without code, per endpoint, with when they were looked up.
+static NO_CODE: OnceLock<Mutex<HashMap<(String, Address), Instant>>> = OnceLock::new();
+
+/// How long an address without code is not asked again, `EIP1271_NO_CODE_CACHE_SECONDS`.
+fn no_code_ttl() -> Duration {
+    let seconds = env::var("EIP1271_NO_CODE_CACHE_SECONDS")
+        .ok()
+        .and_then(|value| value.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_NO_CODE_CACHE_SECONDS);
+    Duration::from_secs(seconds)
+}
+
+fn no_code_cache() -> &'static Mutex<HashMap<(String, Address), Instant>> {
+    NO_CODE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+fn known_without_code(rpc_url: &str, wallet: Address) -> bool {
+    let cache = no_code_cache().lock().unwrap_or_else(|e| e.into_inner());
+    cache
+        .get(&(rpc_url.to_string(), wallet))
+        .map_or(false, |looked_up| looked_up.elapsed() < no_code_ttl())
+}
+
+fn remember_without_code(rpc_url: &str, wallet: Address) {
+    let ttl = no_code_ttl();
+    if ttl.is_zero() {
+        return;
+    }
+    let mut cache = no_code_cache().lock().unwrap_or_else(|e| e.into_inner());
+    if cache.len() >= MAX_NO_CODE_ENTRIES {
+        cache.retain(|_, looked_up| looked_up.elapsed() < ttl);
+        if cache.len() >= MAX_NO_CODE_ENTRIES {
+            cache.clear();
+        }
+    }
+    cache.insert((rpc_url.to_string(), wallet), Instant::now());
+}
+
+/// Whether `signature` is worth asking a wallet contract about: EIP-1271 leaves the format to
+/// the wallet, but an empty or oversized signature is never sent to the endpoint.
+pub fn is_well_formed_signature(signature: &[u8]) -> bool {
+    !signature.is_empty() && signature.len() <= MAX_CONTRACT_SIGNATURE_BYTES
+}
+
+/// The JSON-RPC endpoint contract wallets are queried through: `EIP1271_RPC_URL`, else the
+/// endpoint of the `CHAIN` network. `None` disables smart-contract wallet signatures.
+pub fn signature_rpc_url() -> Option<String> {
+    env::var("EIP1271_RPC_URL")
+        .ok()
+        .filter(|url| !url.trim().is_empty())
+        .or_else(|| {
+            network_registry()
+                .resolve("", None)
+                .ok()
+                .and_then(|network| network.rpc_url.clone())
+        })
+}
+
+fn is_valid_signature_calldata(digest: [u8; 32], signature: &[u8]) -> Vec<u8> {
+    let arguments = encode(&[
+        Token::FixedBytes(digest.to_vec()),
+        Token::Bytes(signature.to_vec()),
+    ]);
+    [EIP1271_MAGIC_VALUE.as_slice(), arguments.as_slice()].concat()
+}
+
+/// Asks `wallet` whether it accepts `signature` over `digest`. Addresses without code are
+/// not contract wallets and never accept anything, they are remembered for
+/// `EIP1271_NO_CODE_CACHE_SECONDS` so repeated bad signatures do not reach the endpoint.
+pub async fn is_valid_signature(
+    rpc_url: &str,
+    wallet: Address,
+    digest: [u8; 32],
+    signature: &[u8],
+) -> Result<bool, String> {
+    if !is_well_formed_signature(signature) || known_without_code(rpc_url, wallet) {
+        return Ok(false);
+    }
+
+    let provider = Provider::<Http>::try_from(rpc_url).map_err(|e| e.to_string())?;
+
+    let code = provider
+        .get_code(wallet, None)
+        .await
+        .map_err(|e| format!("Error fetching code of {:?}: {}", wallet, e))?;
+    if code.as_ref().is_empty() {
+        remember_without_code(rpc_url, wallet);
+        return Ok(false);
+    }
+
+    let call: TypedTransaction = TransactionRequest::new()
+        .to(wallet)
+        .data(Bytes::from(is_valid_signature_calldata(digest, signature)))
+        .into();
+    // a reverting wallet rejects the signature, it does not break verification
+    match provider.call(&call, None).await {
+        Ok(result) => Ok(result.as_ref().starts_with(&EIP1271_MAGIC_VALUE)),
+        Err(e) => {
+            tracing::debug!("isValidSignature on {:?} failed: {}", wallet, e);
+            Ok(false)
+        }
+    }
+}
+
+/// `is_valid_signature` against the configured endpoint, `Ok(false)` when none is configured.
+pub async fn contract_signature_valid(
+    wallet: Address,
+    digest: [u8; 32],
+    signature: &[u8],
+) -> Result<bool, String> {
+    match signature_rpc_url() {
+        Some(rpc_url) => is_valid_signature(&rpc_url, wallet, digest, signature).await,
+        None => Ok(false),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use ethers::core::utils::{Anvil, AnvilInstance};
+    use ethers::middleware::SignerMiddleware;
+    use ethers::signers::{LocalWallet, Signer};
+
+    const CHAIN_ID: u64 = 31337;
+    /// Deploys a wallet whose every call returns the EIP-1271 magic value.
+    pub(crate) const ACCEPTING_INIT_CODE: &str =
+        "6010600c60003960106000f3631626ba7e60e01b60005260206000f3";
+    /// Deploys a wallet whose every call reverts.
+    pub(crate) const REJECTING_INIT_CODE: &str = "6005600c60003960056000f360006000fd";
+
+    /// Deploys `init_code` and returns the address of the contract.
+    pub(crate) async fn deploy(anvil: &AnvilInstance, init_code: &str) -> Address {
+        let provider = Provider::<Http>::try_from(anvil.endpoint()).unwrap();
+        let wallet = LocalWallet::from(anvil.keys()[0].clone()).with_chain_id(CHAIN_ID);
+        let client = SignerMiddleware::new(provider, wallet);
+        let request = TransactionRequest::new()
+            .data(Bytes::from(hex::decode(init_code).unwrap()))
+            .gas(200_000);
+        let receipt = client
+            .send_transaction(request, None)
+            .await
+            .unwrap()
+            .await
+            .unwrap()
+            .unwrap();
+        receipt.contract_address.unwrap()
+    }
+
+    #[tokio::test]
+    async fn malformed_signatures_never_reach_the_endpoint() {
+        // nothing listens on port 1, asking it would fail
+        let rpc_url = "http://127.0.0.1:1";
+        let wallet = Address::from_low_u64_be(0xa1);
+        assert_eq!(is_valid_signature(rpc_url, wallet, [0; 32], &[]).await, Ok(false));
+        let oversized = vec![0; MAX_CONTRACT_SIGNATURE_BYTES + 1];
+        assert_eq!(is_valid_signature(rpc_url, wallet, [0; 32], &oversized).await, Ok(false));
+    }
+
+    // needs `anvil` on the PATH, run with `cargo test -- --ignored`
+    #[tokio::test]
+    #[ignore]
+    async fn asks_wallet_contracts_about_signatures() {
+        let anvil = Anvil::new().chain_id(CHAIN_ID).spawn();
+        let accepting = deploy(&anvil, ACCEPTING_INIT_CODE).await;
+        let rejecting = deploy(&anvil, REJECTING_INIT_CODE).await;
+        let signature = [0x11; 65];
+
+        let valid = is_valid_signature(&anvil.endpoint(), accepting, [0; 32], &signature).await;
+        assert_eq!(valid, Ok(true));
+        let valid = is_valid_signature(&anvil.endpoint(), rejecting, [0; 32], &signature).await;
+        assert_eq!(valid, Ok(false));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn remembers_addresses_without_code() {
+        let anvil = Anvil::new().chain_id(CHAIN_ID).spawn();
+        let rpc_url = anvil.endpoint();
+        let account = anvil.addresses()[1];
+        let signature = [0x11; 65];
+
+        assert_eq!(is_valid_signature(&rpc_url, account, [0; 32], &signature).await, Ok(false));
+        // with the node gone the answer can only come from the cache
+        drop(anvil);
+        assert_eq!(is_valid_signature(&rpc_url, account, [0; 32], &signature).await, Ok(false));
+        let unknown = Address::from_low_u64_be(0xa2);
+        assert!(is_valid_signature(&rpc_url, unknown, [0; 32], &signature)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,94 @@
+use aqua_verifier_rs_types::models::signature::Signature;
+use ethers::types::{Address, H256, U256};
+use ethers::utils::keccak256;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::str::FromStr;
+
+/// Revision signatures over the plain message of `signature_message`, as produced by MetaMask.
+pub const SIGNING_MODE_PERSONAL_SIGN: &str = "personal_sign";
+/// Revision signatures over the `AquaRevision` typed struct.
+pub const SIGNING_MODE_EIP712: &str = "eip712";
+
+pub const EIP712_DOMAIN_NAME: &str = "Aqua";
+pub const EIP712_DOMAIN_VERSION: &str = "1";
+
+const EIP712_DOMAIN_TYPE: &str = "EIP712Domain(string name,string version)";
+const AQUA_REVISION_TYPE: &str =
+    "AquaRevision(string fileHash,string verificationHash,string domainId,uint256 timestamp)";
+
+/// The struct signed in EIP-712 mode. `verification_hash` is the revision being signed,
+/// i.e. the `previous_verification_hash` of the signature revision.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RevisionTypedData {
+    pub file_hash: String,
+    pub verification_hash: String,
+    pub domain_id: String,
+    /// Unix seconds at which the signature was requested.
+    pub timestamp: u64,
+}
+
+fn keccak_str(value: &str) -> [u8; 32] {
+    keccak256(value.as_bytes())
+}
+
+fn domain_separator() -> [u8; 32] {
+    let mut encoded = Vec::with_capacity(3 * 32);
+    encoded.extend_from_slice(&keccak_str(EIP712_DOMAIN_TYPE));
+    encoded.extend_from_slice(&keccak_str(EIP712_DOMAIN_NAME));
+    encoded.extend_from_slice(&keccak_str(EIP712_DOMAIN_VERSION));
+    keccak256(encoded)
+}
+
+fn struct_hash(data: &RevisionTypedData) -> [u8; 32] {
+    let mut timestamp = [0u8; 32];
+    U256::from(data.timestamp).to_big_endian(&mut timestamp);
+
+    let mut encoded = Vec::with_capacity(5 * 32);
+    encoded.extend_from_slice(&keccak_str(AQUA_REVISION_TYPE));
+    encoded.extend_from_slice(&keccak_str(&data.file_hash));
+    encoded.extend_from_slice(&keccak_str(&data.verification_hash));
+    encoded.extend_from_slice(&keccak_str(&data.domain_id));
+    encoded.extend_from_slice(&timestamp);
+    keccak256(encoded)
+}
+
+/// The digest a wallet signs for `eth_signTypedData_v4` with `typed_data_request(data)`.
+pub fn typed_data_digest(data: &RevisionTypedData) -> [u8; 32] {
+    let mut encoded = Vec::with_capacity(2 + 2 * 32);
+    encoded.extend_from_slice(&[0x19, 0x01]);
+    encoded.extend_from_slice(&domain_separator());
+    encoded.extend_from_slice(&struct_hash(data));
+    keccak256(encoded)
+}
+
+/// The typed data payload handed to the wallet.
+pub fn typed_data_request(data: &RevisionTypedData) -> Value {
+    json!({
+        "types": {
+            "EIP712Domain": [
+                { "name": "name", "type": "string" },
+                { "name": "version", "type": "string" },
+            ],
+            "AquaRevision": [
+                { "name": "fileHash", "type": "string" },
+                { "name": "verificationHash", "type": "string" },
+                { "name": "domainId", "type": "string" },
+                { "name": "timestamp", "type": "uint256" },
+            ],
+        },
+        "primaryType": "AquaRevision",
+        "domain": {
+            "name": EIP712_DOMAIN_NAME,
+            "version": EIP712_DOMAIN_VERSION,
+        },
+        "message": data,
+    })
+}
+
+/// Recovers who signed `data`, `None` if the signature is malformed.
+pub fn recover_typed_data_signer(signature: &Signature, data: &RevisionTypedData) -> Option<Address> {
+    let sig = ethers::types::Signature::from_str(&signature.to_string()).ok()?;
+    sig.recover(H256::from(typed_data_digest(data))).ok()
+}
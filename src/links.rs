@@ -0,0 +1,224 @@
+use crate::auth::permissions::{authorize_file_access, FileAccess};
+use crate::auth::session::AuthenticatedUser;
+use crate::blob_store::inline_file_data;
+use crate::db::chain_links::{fetch_chain_links_by_file, fetch_chain_links_by_revisions};
+use crate::db::pages_db::fetch_page_data;
+use crate::protocol::v1_3::{self, AquaChainV13};
+use crate::protocol::{AquaDocument, ProtocolVersion};
+use crate::signing::{load_signature_schemes, load_signature_schemes_for};
+use crate::verification::{
+    confirm_contract_signatures, verify_hash_chain_with_context, CheckResult, SignatureContext,
+    VerificationCheck, VerificationReport,
+};
+use axum::http::StatusCode;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use crate::db::DbConnection;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+/// Upper bound on chains in a resolved link graph.
+const MAX_LINKED_CHAINS: usize = 100;
+
+/// A chain in a link graph.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LinkedChain {
+    pub file_id: i32,
+    pub name: String,
+    pub protocol_version: ProtocolVersion,
+    pub latest_verification_hash: Option<String>,
+    /// Set when the graph was resolved with verification.
+    pub report: Option<VerificationReport>,
+}
+
+/// A link revision of `file_id` pointing at `linked_verification_hash` of `linked_file_id`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChainLink {
+    pub file_id: i32,
+    pub revision_hash: String,
+    pub linked_file_id: i32,
+    pub linked_verification_hash: String,
+}
+
+#[derive(Debug, Default)]
+pub struct LinkGraph {
+    pub chains: Vec<LinkedChain>,
+    pub links: Vec<ChainLink>,
+    /// Linked files the caller may not read or that no longer exist.
+    pub unresolved: Vec<String>,
+}
+
+impl LinkGraph {
+    pub fn valid(&self) -> bool {
+        self.unresolved.is_empty()
+            && self
+                .chains
+                .iter()
+                .all(|chain| chain.report.as_ref().map_or(true, |report| report.valid))
+    }
+}
+
+/// Verifies a stored chain of either version, without following its links.
+pub async fn verify_document(
+    document: &AquaDocument,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<VerificationReport, String> {
+    match document {
+        AquaDocument::V1_2(doc) => {
+            // stored v1.2 chains keep the file in the blob store, the file hash is checked
+            // against the bytes there
+            let mut doc = doc.clone();
+            inline_file_data(&mut doc)?;
+            let chain = doc
+                .pages
+                .first()
+                .ok_or_else(|| "first chain not found".to_string())?;
+            let schemes = load_signature_schemes(chain, db_connection)?;
+            let contract_signatures = confirm_contract_signatures(chain, &schemes).await?;
+            let context = SignatureContext {
+                schemes,
+                contract_signatures,
+            };
+            Ok(verify_hash_chain_with_context(chain, &context))
+        }
+        AquaDocument::V1_3(chain) => {
+            let schemes =
+                load_signature_schemes_for(&chain.legacy_signature_hashes(), db_connection)?;
+            Ok(v1_3::verify_chain(chain, &schemes))
+        }
+    }
+}
+
+/// Checks that every link revision of `chain` points at a stored chain that holds the linked
+/// revision and verifies, recording the outcome on the link revision's report.
+pub async fn check_links(
+    chain: &AquaChainV13,
+    report: &mut VerificationReport,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<(), String> {
+    let links = chain.links();
+    if links.is_empty() {
+        return Ok(());
+    }
+
+    let revision_hashes: Vec<String> = links.iter().map(|(hash, _)| hash.clone()).collect();
+    let rows: BTreeMap<String, i32> =
+        fetch_chain_links_by_revisions(&revision_hashes, db_connection)?
+            .into_iter()
+            .map(|row| (row.revision_hash, row.linked_file_id))
+            .collect();
+
+    for (revision_hash, linked_hash) in links {
+        let outcome = match rows.get(&revision_hash) {
+            None => Err("linked chain is not stored in this container".to_string()),
+            Some(linked_file_id) => {
+                check_linked_chain(*linked_file_id, &linked_hash, db_connection).await
+            }
+        };
+
+        if let Some(revision) = report
+            .revisions
+            .iter_mut()
+            .find(|revision| revision.verification_hash == revision_hash)
+        {
+            revision.checks.push(CheckResult {
+                check: VerificationCheck::LinkedChain,
+                passed: outcome.is_ok(),
+                message: outcome.err(),
+            });
+            revision.valid = revision.checks.iter().all(|check| check.passed);
+        }
+    }
+
+    report.valid = !report.revisions.is_empty() && report.revisions.iter().all(|r| r.valid);
+    Ok(())
+}
+
+async fn check_linked_chain(
+    linked_file_id: i32,
+    linked_hash: &str,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<(), String> {
+    let page = fetch_page_data(linked_file_id, db_connection)
+        .map_err(|_| format!("linked file {} no longer exists", linked_file_id))?;
+    let document = AquaDocument::parse(page.page_data.as_bytes())?;
+    if !document.contains_revision(linked_hash) {
+        return Err(format!(
+            "chain of file {} does not contain {}",
+            linked_file_id, linked_hash
+        ));
+    }
+    if !verify_document(&document, db_connection).await?.valid {
+        return Err(format!("chain of file {} does not verify", linked_file_id));
+    }
+    Ok(())
+}
+
+/// Walks the links starting at `file_id`, following only files the user may read. With
+/// `verify` every chain is verified including its link revisions.
+pub async fn resolve_link_graph(
+    user: &AuthenticatedUser,
+    file_id: i32,
+    verify: bool,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<LinkGraph, (StatusCode, String)> {
+    let internal = |e: String| (StatusCode::INTERNAL_SERVER_ERROR, format!("Error : {}", e));
+
+    let mut graph = LinkGraph::default();
+    let mut visited = BTreeSet::from([file_id]);
+    let mut queue = VecDeque::from([file_id]);
+
+    while let Some(current) = queue.pop_front() {
+        let page = match authorize_file_access(user, current, FileAccess::Read, db_connection) {
+            Ok(page) => page,
+            // the starting file decides the response, linked files are reported as unresolved
+            Err(e) if current == file_id => return Err((e.status_code(), format!("Error : {}", e))),
+            Err(e) => {
+                graph.unresolved.push(format!("file {}: {}", current, e));
+                continue;
+            }
+        };
+
+        let document = AquaDocument::parse(page.page_data.as_bytes()).map_err(internal)?;
+        let report = if verify {
+            let mut report = verify_document(&document, db_connection)
+                .await
+                .map_err(internal)?;
+            if let AquaDocument::V1_3(chain) = &document {
+                check_links(chain, &mut report, db_connection)
+                    .await
+                    .map_err(internal)?;
+            }
+            Some(report)
+        } else {
+            None
+        };
+
+        graph.chains.push(LinkedChain {
+            file_id: page.id,
+            name: page.name,
+            protocol_version: document.version(),
+            latest_verification_hash: document.latest_verification_hash(),
+            report,
+        });
+
+        for row in fetch_chain_links_by_file(current, db_connection).map_err(internal)? {
+            if visited.insert(row.linked_file_id) {
+                if visited.len() > MAX_LINKED_CHAINS {
+                    return Err((
+                        StatusCode::UNPROCESSABLE_ENTITY,
+                        format!("Error : a link graph holds at most {} chains", MAX_LINKED_CHAINS),
+                    ));
+                }
+                queue.push_back(row.linked_file_id);
+            }
+            graph.links.push(ChainLink {
+                file_id: row.file_id,
+                revision_hash: row.revision_hash,
+                linked_file_id: row.linked_file_id,
+                linked_verification_hash: row.linked_verification_hash,
+            });
+        }
+    }
+
+    Ok(graph)
+}
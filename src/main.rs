@@ -1,13 +1,25 @@
 #![allow(warnings)]
 
 pub mod auth;
+mod blob_store;
+mod bundle;
 mod controllers;
 mod db;
+mod eip1271;
+mod eip712;
+mod links;
+mod merge;
 mod models;
+mod networks;
+mod protocol;
 mod util;
 mod schema;
+mod server_signer;
+mod signing;
 
-// pub mod verification;
+pub mod verification;
+mod witness;
+mod witness_confirmation;
 // pub mod revision_integrity;
 
 use aqua_verifier_rs_types::models::page_data::HashChain;
@@ -21,7 +33,7 @@ use axum::{
     routing::{get, post},
     BoxError, Form, Json, Router,
 };
-use diesel::SqliteConnection;
+use crate::db::DbConnection;
 use diesel_migrations::embed_migrations;
 use ethaddr::address;
 
@@ -52,16 +64,27 @@ use diesel::r2d2::{self, ConnectionManager};
 use serde::{Deserialize, Serialize};
 extern crate serde_json_path_to_error as serde_json;
 use std::sync::{mpsc, Mutex, MutexGuard};
-use crate::controllers::api_controller::explorer_file_verify_hash_upload;
+use crate::controllers::api_controller::{explorer_file_verify_hash_upload, max_request_body_size};
 use crate::util::{check_or_generate_domain, establish_connection};
 use controllers::{api_controller::{
     explorer_aqua_file_upload, explorer_delete_all_files, explorer_delete_file, explorer_file_upload, explorer_import_aqua_chain, explorer_merge_chain, explorer_sign_revision, explorer_witness_file, fetch_explorer_files
-}, auth_controller::session_logout_by_nonce, share_controller::{get_share_data, save_share_data}};
+}, auth_controller::session_logout_by_nonce, share_controller::{explorer_grant_file_access, explorer_revoke_file_access, get_share_data, save_share_data}};
 use controllers::auth_controller::{
-    siwe_sign_in, verify_siwe_message, fetch_nonce_session
+    siwe_sign_in, siwe_issue_nonce, verify_siwe_message, fetch_nonce_session
 
 };
 use controllers::versions_controller::version_details;
+use controllers::file_content_controller::{explorer_export_bundle, explorer_file_content};
+use controllers::file_list_controller::{explorer_list_files, explorer_search_files};
+use controllers::signing_controller::{
+    explorer_create_signature_requests, explorer_decline_signature_request,
+    explorer_file_signature_requests, explorer_incoming_signature_requests,
+    explorer_file_signature_modes, explorer_server_sign_revision, explorer_sign_signature_request,
+    explorer_sign_typed_data,
+};
+use controllers::link_controller::{explorer_file_links, explorer_link_file, explorer_verify_file_links};
+use controllers::protocol_controller::explorer_upgrade_chain;
+use controllers::witness_controller::{explorer_witness_batch, explorer_witness_batch_root};
 use controllers::user_profile_controller::{explorer_fetch_user_profile, explorer_update_user_profile};
 
 const UPLOADS_DIRECTORY: &str = "uploads";
@@ -96,7 +119,7 @@ async fn main() {
 
     
     // Establish database connection pool
-    let pool:  r2d2::Pool<ConnectionManager<SqliteConnection>> = crate::util::establish_connection();
+    let pool:  r2d2::Pool<ConnectionManager<DbConnection>> = crate::util::establish_connection();
 
     // Run migrations
     // Get a connection from the pool to pass to run_db_migrations
@@ -108,6 +131,12 @@ async fn main() {
     // save files to a separate directory to not override files in the current directory
     tokio::fs::create_dir(UPLOADS_DIRECTORY).await;
 
+    // expired nonces and sessions are purged in the background
+    crate::auth::nonce::spawn_siwe_cleanup(pool.clone());
+
+    // decrypting the keystore is slow, do it before serving requests
+    crate::server_signer::server_signer();
+
     let server_database = Db { pool  };
 
     let app = Router::new()
@@ -127,8 +156,28 @@ async fn main() {
             post(explorer_file_verify_hash_upload),
         )
         .route("/explorer_sign_revision", post(explorer_sign_revision))
+        .route("/explorer_server_sign_revision", post(explorer_server_sign_revision))
+        .route("/explorer_sign_typed_data/{id}", get(explorer_sign_typed_data))
+        .route(
+            "/signature_requests",
+            get(explorer_incoming_signature_requests).post(explorer_create_signature_requests),
+        )
+        .route("/signature_requests/file/{id}", get(explorer_file_signature_requests))
+        .route("/signature_requests/{id}/sign", post(explorer_sign_signature_request))
+        .route("/signature_requests/{id}/decline", post(explorer_decline_signature_request))
         .route("/explorer_witness_file", post(explorer_witness_file))
+        .route("/explorer_witness_batch_root", post(explorer_witness_batch_root))
+        .route("/explorer_witness_batch", post(explorer_witness_batch))
         .route("/explorer_merge_chain", post(explorer_merge_chain))
+        .route("/explorer_upgrade_chain", post(explorer_upgrade_chain))
+        .route("/explorer_link_file", post(explorer_link_file))
+        .route("/files", get(explorer_list_files))
+        .route("/files/search", get(explorer_search_files))
+        .route("/files/{id}/links", get(explorer_file_links))
+        .route("/files/{id}/links/verify", get(explorer_verify_file_links))
+        .route("/files/{id}/content", get(explorer_file_content))
+        .route("/files/{id}/bundle", get(explorer_export_bundle))
+        .route("/files/{id}/signature_modes", get(explorer_file_signature_modes))
         .route("/explorer_delete_file", post(explorer_delete_file))
         .route("/explorer_delete_all_files", get(explorer_delete_all_files))
         .route(
@@ -143,17 +192,20 @@ async fn main() {
         //     "/explorer_fetch_user_profiles",
         //     get(explorer_update_user_profile),
         // )
+        .route("/siwe_nonce", get(siwe_issue_nonce))
         .route("/siwe", post(siwe_sign_in))
         .route("/fetch_nonce_session", post(fetch_nonce_session))
         .route("/siwe_logout", post(session_logout_by_nonce))
         .route("/share_data/{share_identifier}", get(get_share_data))
         .route("/share_data", post(save_share_data))
+        .route("/explorer_grant_file_access", post(explorer_grant_file_access))
+        .route("/explorer_revoke_file_access", post(explorer_revoke_file_access))
         .route("/version", get(version_details))
         //.route("/list", get(show_files_list).post(show_files))
         .with_state(server_database)
         .layer(CorsLayer::permissive())
         .layer(TraceLayer::new_for_http())
-        .layer(DefaultBodyLimit::max(50 * 1024 * 1024));
+        .layer(DefaultBodyLimit::max(max_request_body_size()));
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3600")
         .await
@@ -0,0 +1,296 @@
+use crate::protocol::v1_3::{verify_chain, AquaChainV13, RevisionV13};
+use crate::verification::{
+    verify_hash_chain_with_context, SignatureContext, SignatureSchemes, VerificationReport,
+};
+use aqua_verifier_rs_types::models::hash::Hash;
+use aqua_verifier_rs_types::models::page_data::HashChain;
+use aqua_verifier_rs_types::models::revision::Revision;
+use axum::http::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Local and incoming revisions that both continue the chain after `fork_point`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MergeConflict {
+    /// Last revision both chains share.
+    pub fork_point: String,
+    /// Local revisions after the fork point, kept as they are.
+    pub local_revisions: Vec<String>,
+    /// Incoming revisions after the fork point, rejected.
+    pub incoming_revisions: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum MergeError {
+    /// `last_identical_revision_hash` is not part of the local chain.
+    ForkPointNotFound(String),
+    /// An incoming revision does not point at the revision before it.
+    BrokenLinkage {
+        verification_hash: String,
+        expected_previous: String,
+    },
+    /// An incoming revision does not recompute to its verification hash.
+    InvalidRevision {
+        verification_hash: String,
+        failures: Vec<String>,
+    },
+    Conflict(MergeConflict),
+    /// The stored chain cannot be walked from its genesis revision.
+    LocalChain(String),
+}
+
+impl MergeError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            MergeError::ForkPointNotFound(_) => StatusCode::NOT_FOUND,
+            MergeError::BrokenLinkage { .. } | MergeError::InvalidRevision { .. } => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            MergeError::Conflict(_) => StatusCode::CONFLICT,
+            MergeError::LocalChain(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeError::ForkPointNotFound(hash) => {
+                write!(f, "Hash {} not found in existing revisions", hash)
+            }
+            MergeError::BrokenLinkage {
+                verification_hash,
+                expected_previous,
+            } => write!(
+                f,
+                "revision {} does not link to {}",
+                verification_hash, expected_previous
+            ),
+            MergeError::InvalidRevision {
+                verification_hash,
+                failures,
+            } => write!(
+                f,
+                "revision {} is not valid: {}",
+                verification_hash,
+                failures.join(", ")
+            ),
+            MergeError::Conflict(conflict) => write!(
+                f,
+                "chains diverge after {}: {} local and {} incoming revisions",
+                conflict.fork_point,
+                conflict.local_revisions.len(),
+                conflict.incoming_revisions.len()
+            ),
+            MergeError::LocalChain(e) => write!(f, "local chain is not valid: {}", e),
+        }
+    }
+}
+
+/// The chain after a merge, `appended` revisions were added to the local chain.
+#[derive(Debug)]
+pub struct MergePlan {
+    pub chain: HashChain,
+    /// Index of the first appended revision.
+    pub first_appended: usize,
+    pub appended: usize,
+}
+
+fn same_hash(hash: &Hash, other: &str) -> bool {
+    // clients send either form
+    hash.to_string() == other || format!("{:?}", hash) == other
+}
+
+/// Attaches `incoming` after `last_identical_revision_hash`. Incoming revisions the local chain
+/// already holds are skipped, the rest must link to each other. A local chain that moved on
+/// after the fork point is a conflict, its revisions are never discarded.
+pub fn plan_merge(
+    chain: &HashChain,
+    last_identical_revision_hash: &str,
+    incoming: Vec<Revision>,
+) -> Result<MergePlan, MergeError> {
+    let fork_index = chain
+        .revisions
+        .iter()
+        .position(|(hash, _)| same_hash(hash, last_identical_revision_hash))
+        .ok_or_else(|| MergeError::ForkPointNotFound(last_identical_revision_hash.to_string()))?;
+
+    // incoming revisions identical to local ones move the fork point forward
+    let mut common = fork_index;
+    let mut incoming = incoming.into_iter().peekable();
+    while let Some(revision) = incoming.peek() {
+        match chain.revisions.get(common + 1) {
+            Some((hash, _)) if *hash == revision.metadata.verification_hash => {
+                common += 1;
+                incoming.next();
+            }
+            _ => break,
+        }
+    }
+    let incoming: Vec<Revision> = incoming.collect();
+
+    let local_tail = &chain.revisions[common + 1..];
+    if incoming.is_empty() {
+        return Ok(MergePlan {
+            chain: chain.clone(),
+            first_appended: chain.revisions.len(),
+            appended: 0,
+        });
+    }
+    if !local_tail.is_empty() {
+        return Err(MergeError::Conflict(MergeConflict {
+            fork_point: chain.revisions[common].0.to_string(),
+            local_revisions: local_tail.iter().map(|(hash, _)| hash.to_string()).collect(),
+            incoming_revisions: incoming
+                .iter()
+                .map(|revision| revision.metadata.verification_hash.to_string())
+                .collect(),
+        }));
+    }
+
+    let mut merged = chain.clone();
+    for revision in incoming {
+        let previous = merged.revisions[merged.revisions.len() - 1].0;
+        if revision.metadata.previous_verification_hash != Some(previous) {
+            return Err(MergeError::BrokenLinkage {
+                verification_hash: revision.metadata.verification_hash.to_string(),
+                expected_previous: previous.to_string(),
+            });
+        }
+        merged
+            .revisions
+            .push((revision.metadata.verification_hash, revision));
+    }
+
+    Ok(MergePlan {
+        first_appended: chain.revisions.len(),
+        appended: merged.revisions.len() - chain.revisions.len(),
+        chain: merged,
+    })
+}
+
+/// Re-derives every hash of the appended revisions in the merged chain.
+pub fn validate_merge(plan: &MergePlan, context: &SignatureContext) -> Result<(), MergeError> {
+    let report = verify_hash_chain_with_context(&plan.chain, context);
+    first_invalid(&report, |index, _| index >= plan.first_appended)
+}
+
+/// A v1.3 chain after a merge, `appended` holds the verification hashes of the added revisions.
+#[derive(Debug)]
+pub struct MergePlanV13 {
+    pub chain: AquaChainV13,
+    pub appended: Vec<String>,
+}
+
+fn same_hash_v13(hash: &str, other: &str) -> bool {
+    hash.trim_start_matches("0x")
+        .eq_ignore_ascii_case(other.trim_start_matches("0x"))
+}
+
+/// `plan_merge` for v1.3 chains. Incoming revisions are identified by the merkle root of the
+/// leaves they carry, `validate_merge_v13` checks the leaves against their fields.
+pub fn plan_merge_v13(
+    chain: &AquaChainV13,
+    last_identical_revision_hash: &str,
+    incoming: Vec<RevisionV13>,
+) -> Result<MergePlanV13, MergeError> {
+    let local: Vec<String> = chain
+        .ordered()
+        .map_err(MergeError::LocalChain)?
+        .into_iter()
+        .map(|(hash, _)| hash.clone())
+        .collect();
+    let fork_index = local
+        .iter()
+        .position(|hash| same_hash_v13(hash, last_identical_revision_hash))
+        .ok_or_else(|| MergeError::ForkPointNotFound(last_identical_revision_hash.to_string()))?;
+
+    // incoming revisions identical to local ones move the fork point forward
+    let mut common = fork_index;
+    let mut incoming = incoming
+        .into_iter()
+        .map(|revision| (revision.verification_hash(), revision))
+        .peekable();
+    while let Some((hash, _)) = incoming.peek() {
+        match local.get(common + 1) {
+            Some(local_hash) if local_hash == hash => {
+                common += 1;
+                incoming.next();
+            }
+            _ => break,
+        }
+    }
+    let incoming: Vec<(String, RevisionV13)> = incoming.collect();
+
+    let local_tail = &local[common + 1..];
+    if incoming.is_empty() {
+        return Ok(MergePlanV13 {
+            chain: chain.clone(),
+            appended: Vec::new(),
+        });
+    }
+    if !local_tail.is_empty() {
+        return Err(MergeError::Conflict(MergeConflict {
+            fork_point: local[common].clone(),
+            local_revisions: local_tail.to_vec(),
+            incoming_revisions: incoming.into_iter().map(|(hash, _)| hash).collect(),
+        }));
+    }
+
+    let mut merged = chain.clone();
+    let mut previous = local[common].clone();
+    let mut appended = Vec::new();
+    for (hash, revision) in incoming {
+        if revision.previous_verification_hash() != Some(previous.as_str()) {
+            return Err(MergeError::BrokenLinkage {
+                verification_hash: hash,
+                expected_previous: previous,
+            });
+        }
+        merged.revisions.insert(hash.clone(), revision);
+        appended.push(hash.clone());
+        previous = hash;
+    }
+
+    Ok(MergePlanV13 {
+        chain: merged,
+        appended,
+    })
+}
+
+/// Re-derives every leaf and hash of the appended revisions in the merged v1.3 chain.
+pub fn validate_merge_v13(plan: &MergePlanV13, schemes: &SignatureSchemes) -> Result<(), MergeError> {
+    let report = verify_chain(&plan.chain, schemes);
+    first_invalid(&report, |_, hash| plan.appended.iter().any(|appended| appended == hash))
+}
+
+fn first_invalid(
+    report: &VerificationReport,
+    appended: impl Fn(usize, &str) -> bool,
+) -> Result<(), MergeError> {
+    match report
+        .revisions
+        .iter()
+        .enumerate()
+        .filter(|(index, revision)| appended(*index, revision.verification_hash.as_str()))
+        .map(|(_, revision)| revision)
+        .find(|revision| !revision.valid)
+    {
+        Some(revision) => Err(MergeError::InvalidRevision {
+            verification_hash: revision.verification_hash.clone(),
+            failures: revision
+                .checks
+                .iter()
+                .filter(|check| !check.passed)
+                .map(|check| {
+                    format!(
+                        "{:?} {}",
+                        check.check,
+                        check.message.clone().unwrap_or_default()
+                    )
+                })
+                .collect(),
+        }),
+        None => Ok(()),
+    }
+}
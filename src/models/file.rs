@@ -1,4 +1,8 @@
+use crate::models::input::{FileSort, SortOrder};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
 use serde::{Deserialize, Serialize};
+extern crate serde_json_path_to_error as serde_json;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FileInfo {
     pub id : i64,
@@ -9,6 +13,56 @@ pub struct FileInfo {
     pub owner: String
 }
 
+/// A file in a listing, without its chain.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileSummary {
+    pub id: i64,
+    pub name: String,
+    pub extension: String,
+    pub mode: String,
+    pub owner: String,
+    pub created_at: String,
+    pub is_shared: bool,
+}
+
+/// Where a listing page ended, handed to clients as an opaque string.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileListCursor {
+    pub sort: FileSort,
+    pub order: SortOrder,
+    /// Sort key of the last listed file, empty when sorting by id.
+    pub key: String,
+    pub id: i32,
+}
+
+impl FileListCursor {
+    pub fn after(file: &FileSummary, sort: FileSort, order: SortOrder) -> Self {
+        let key = match sort {
+            FileSort::CreatedAt => file.created_at.clone(),
+            FileSort::Name => file.name.clone(),
+            FileSort::Id => String::new(),
+        };
+        FileListCursor {
+            sort,
+            order,
+            key,
+            id: file.id as i32,
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        // serializing a struct of strings and numbers does not fail
+        URL_SAFE_NO_PAD.encode(serde_json::to_string(self).unwrap_or_default())
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, String> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(cursor)
+            .map_err(|_| "invalid cursor".to_string())?;
+        serde_json::from_slice(&bytes).map_err(|_| "invalid cursor".to_string())
+    }
+}
+
 #[derive(Debug,  Serialize, Deserialize, Clone)]
 pub struct FileDataInformation {
    pub file_type: String,
@@ -1,5 +1,6 @@
-use aqua_verifier_rs_types::models::revision::Revision;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+extern crate serde_json_path_to_error as serde_json;
 
 #[derive(Deserialize, Serialize, Debug)]
 #[allow(dead_code)]
@@ -8,6 +9,10 @@ pub struct RevisionInput {
     pub signature: String,
     pub publickey: String,
     pub wallet_address: String,
+    /// `personal_sign` (default) or `eip712`.
+    pub signing_mode: Option<String>,
+    /// Timestamp of the signed typed data, for `eip712`.
+    pub timestamp: Option<u64>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -15,6 +20,8 @@ pub struct RevisionInput {
 pub struct WitnessInput {
     pub file_id: i32,
     pub tx_hash: String,
+    /// The account that sent the witness transaction, kept on v1.3 witness revisions. v1.2
+    /// witnesses have no field for it, so it is only logged for them.
     pub wallet_address: String,
     pub network: String,
 }
@@ -42,5 +49,100 @@ pub struct UpdateConfigurationInput {
 pub struct MergeInput {
     pub file_id: i32,
     pub last_identical_revision_hash: String,
-    pub revisions_to_import: Vec<Revision>,
-}
\ No newline at end of file
+    /// Revisions in the protocol version of the chain they are merged into.
+    pub revisions_to_import: Vec<Value>,
+}
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WitnessBatchRootInput {
+    pub file_ids: Vec<i32>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WitnessBatchInput {
+    pub file_ids: Vec<i32>,
+    pub tx_hash: String,
+    /// The account that sent the witness transaction, kept on v1.3 witness revisions. v1.2
+    /// witnesses have no field for it, so it is only logged for them.
+    pub wallet_address: String,
+    pub network: String,
+    /// Root returned by `explorer_witness_batch_root`, rejected if any chain moved on since.
+    pub merkle_root: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ServerSignInput {
+    pub file_id: i32,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct UpgradeChainInput {
+    pub file_id: i32,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct LinkFileInput {
+    pub file_id: i32,
+    pub linked_file_id: i32,
+    /// Revision of the linked chain to point at, its latest revision if empty.
+    pub linked_verification_hash: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct FileFilterInput {
+    /// Only files with a signature revision by this wallet.
+    pub signer: Option<String>,
+    /// Only files with a witness revision on this network.
+    pub witness_network: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileSort {
+    #[default]
+    CreatedAt,
+    Name,
+    Id,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct FileSearchInput {
+    /// Words of a file name or extension, or (prefixes of) hashes and wallet addresses.
+    #[serde(default)]
+    pub q: String,
+    pub limit: Option<i64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct FileListInput {
+    /// `next_cursor` of the previous page.
+    pub cursor: Option<String>,
+    /// Files per page.
+    pub limit: Option<i64>,
+    pub extension: Option<String>,
+    pub mode: Option<String>,
+    pub is_shared: Option<bool>,
+    /// Files created at or after, `YYYY-MM-DD` or `YYYY-MM-DD HH:MM:SS` in UTC.
+    pub created_from: Option<String>,
+    /// Files created before, same format as `created_from`.
+    pub created_before: Option<String>,
+    /// Files with (or without) a signature revision.
+    pub signed: Option<bool>,
+    /// Files with (or without) a witness revision.
+    pub witnessed: Option<bool>,
+    /// Only files with a signature revision by this wallet.
+    pub signer: Option<String>,
+    /// Only files with a witness revision on this network.
+    pub witness_network: Option<String>,
+    #[serde(default)]
+    pub sort: FileSort,
+    #[serde(default)]
+    pub order: SortOrder,
+}
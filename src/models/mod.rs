@@ -1,15 +1,16 @@
 use diesel::prelude::*;
 use diesel::r2d2::{self, ConnectionManager};
-use diesel::SqliteConnection;
+use crate::db::DbConnection;
 use serde::{Deserialize, Serialize};
 pub mod file;
 pub mod input;
 pub mod page_data;
 pub mod share_data;
+pub mod signature_requests;
 pub mod user_profiles;
 use chrono::{DateTime, Utc};
 
-pub type DB_POOL = r2d2::Pool<ConnectionManager<SqliteConnection>>;
+pub type DB_POOL = r2d2::Pool<ConnectionManager<DbConnection>>;
 
 use chrono::NaiveDateTime;
 use diesel::expression::AsExpression;
@@ -39,6 +40,9 @@ pub struct PagesTable {
     pub mode: String,
     pub created_at: String,
     pub is_shared: bool,
+    /// Bumped by every update, an update only commits against the version it was read at.
+    #[serde(default)]
+    pub version: i32,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Insertable)]
@@ -69,18 +73,30 @@ impl From<PagesTable> for NewPagesTable {
 
 #[derive(Queryable, Selectable, Deserialize, Serialize, Debug, Clone, Insertable)]
 #[diesel(table_name = crate::schema::siwe_sessions)]
-#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
 pub struct SiweSessionsTable {
     pub id: Option<i32>,
     pub address: String,
     pub nonce: String,
     pub issued_at: String,
     pub expiration_time: Option<String>,
+    /// When the session ends in `NONCE_TIME_FORMAT`, see `siwe_session_expires_at`.
+    pub expires_at: Option<String>,
+}
+
+#[derive(Queryable, Selectable, Deserialize, Serialize, Debug, Clone, Insertable)]
+#[diesel(table_name = crate::schema::siwe_nonces)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
+pub struct SiweNonceTable {
+    pub id: Option<i32>,
+    pub nonce: String,
+    pub created_at: String,
+    pub expires_at: String,
 }
 
 #[derive(Queryable, Selectable, Deserialize, Serialize, Debug, Clone, Insertable)]
 #[diesel(table_name = crate::schema::share_data)]
-#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
 pub struct ShareDataTable {
     pub id: Option<i32>,
     pub file_id: i32,
@@ -88,9 +104,88 @@ pub struct ShareDataTable {
     pub created_time: String,
 }
 
+#[derive(Queryable, Selectable, Deserialize, Serialize, Debug, Clone, Insertable)]
+#[diesel(table_name = crate::schema::file_access)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
+pub struct FileAccessTable {
+    pub id: Option<i32>,
+    pub file_id: i32,
+    pub address: String,
+    pub access: String,
+    pub created_time: String,
+}
+
+#[derive(Queryable, Selectable, Deserialize, Serialize, Debug, Clone, Insertable)]
+#[diesel(table_name = crate::schema::signature_modes)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
+pub struct SignatureModeTable {
+    pub id: Option<i32>,
+    /// Verification hash of the signature revision.
+    pub verification_hash: String,
+    pub mode: String,
+    /// JSON of the signed `RevisionTypedData`.
+    pub typed_data: String,
+    pub created_time: String,
+}
+
+#[derive(Queryable, Selectable, Deserialize, Serialize, Debug, Clone, Insertable)]
+#[diesel(table_name = crate::schema::revisions)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
+pub struct RevisionsTable {
+    pub id: Option<i32>,
+    pub file_id: i32,
+    /// Index of the revision in its chain, the genesis revision is 0.
+    pub position: i32,
+    pub verification_hash: String,
+    pub previous_verification_hash: Option<String>,
+    /// `content`, `signature` or `witness`.
+    pub kind: String,
+    pub domain_id: String,
+    pub time_stamp: String,
+    pub metadata_hash: String,
+    pub content_hash: String,
+    pub file_hash: Option<String>,
+    /// Lowercase wallet address of signature revisions.
+    pub signer: Option<String>,
+    pub witness_network: Option<String>,
+    pub witness_tx_hash: Option<String>,
+    /// JSON of the whole `Revision`, what the chain is assembled from.
+    pub revision_data: String,
+}
+
+#[derive(Queryable, Selectable, Deserialize, Serialize, Debug, Clone, Insertable)]
+#[diesel(table_name = crate::schema::chain_links)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
+pub struct ChainLinkTable {
+    pub id: Option<i32>,
+    /// File whose chain holds the link revision.
+    pub file_id: i32,
+    /// Verification hash of the link revision.
+    pub revision_hash: String,
+    pub linked_file_id: i32,
+    pub linked_verification_hash: String,
+    pub created_time: String,
+}
+
+#[derive(Queryable, Selectable, Deserialize, Serialize, Debug, Clone, Insertable)]
+#[diesel(table_name = crate::schema::signature_requests)]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
+pub struct SignatureRequestTable {
+    pub id: Option<i32>,
+    pub file_id: i32,
+    pub requester: String,
+    pub signer: String,
+    pub status: String,
+    pub created_time: String,
+    pub updated_time: String,
+    pub expires_at: Option<String>,
+    /// Verification hash of the signature revision once signed.
+    pub verification_hash: Option<String>,
+}
+
 #[derive(Queryable, Selectable, Deserialize, Serialize, Debug, Clone, Insertable)]
 #[diesel(table_name = crate::schema::user_profiles)]
-#[diesel(check_for_backend(diesel::sqlite::Sqlite))]
+#[diesel(check_for_backend(diesel::sqlite::Sqlite, diesel::pg::Pg))]
 pub struct UserProfilesTable {
     pub id: Option<i32>,
     pub address: String,
@@ -99,4 +194,7 @@ pub struct UserProfilesTable {
     pub contract_address: String,
     pub file_mode: String,
     pub domain_name: String,
+    /// Aqua protocol version new chains are created in, `1.2` or `1.3`.
+    #[serde(default)]
+    pub protocol_version: String,
 }
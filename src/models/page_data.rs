@@ -1,7 +1,12 @@
-use crate::models::file::FileInfo;
+use crate::links::{ChainLink, LinkedChain};
+use crate::merge::MergeConflict;
+use crate::models::file::{FileInfo, FileSummary};
+use crate::signing::ExportedSignatureModes;
+use crate::verification::VerificationReport;
 use serde::{Deserialize, Serialize};
 
 use aqua_verifier_rs_types::models::page_data::HashChain;
+extern crate serde_json_path_to_error as serde_json;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PageDataContainer<HashChain: std::marker::Sync + std::marker::Send> {
@@ -13,5 +18,62 @@ pub struct ApiResponse {
     pub logs :  Vec<String>,
     pub file :  Option<FileInfo>,
     pub files : Vec<FileInfo>,
+    /// Latest verification hash of the chain when an update is rejected with 409.
+    pub head : Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VerificationApiResponse {
+    pub logs :  Vec<String>,
+    pub report : Option<VerificationReport>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WitnessBatchRootResponse {
+    pub logs :  Vec<String>,
+    pub merkle_root : Option<String>,
+    /// Value to publish in the witness transaction, the merkle root itself for v1.3 chains.
+    pub witness_event_verification_hash : Option<String>,
+    pub leaves : Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MergeApiResponse {
+    pub logs :  Vec<String>,
+    pub file : Option<FileInfo>,
+    /// Set when the local chain moved on after the fork point.
+    pub conflict : Option<MergeConflict>,
+    /// Latest verification hash of the chain when an update is rejected with 409.
+    pub head : Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FileListApiResponse {
+    pub logs :  Vec<String>,
+    pub files : Vec<FileSummary>,
+    /// Cursor of the next page, `None` on the last page.
+    pub next_cursor : Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LinkGraphApiResponse {
+    pub logs :  Vec<String>,
+    pub valid : Option<bool>,
+    pub chains : Vec<LinkedChain>,
+    pub links : Vec<ChainLink>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SignatureModesApiResponse {
+    pub logs :  Vec<String>,
+    /// Typed data of the file's EIP-712 signatures by verification hash, exported next to the
+    /// chain JSON and accepted back as `signature_modes` on verify and import.
+    pub signature_modes : ExportedSignatureModes,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TypedDataApiResponse {
+    pub logs :  Vec<String>,
+    /// Payload for `eth_signTypedData_v4`, submit its `message.timestamp` with the signature.
+    pub typed_data : Option<serde_json::Value>,
+}
@@ -17,4 +17,10 @@ pub struct ShareDataResponse{
 pub struct CreateShareData {
     pub file_id: i32,
     pub identifier : String
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct FileAccessInput {
+    pub file_id: i32,
+    pub address: String,
 }
\ No newline at end of file
@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+
+use super::SignatureRequestTable;
+
+pub const SIGNATURE_REQUEST_PENDING: &str = "pending";
+pub const SIGNATURE_REQUEST_SIGNED: &str = "signed";
+pub const SIGNATURE_REQUEST_DECLINED: &str = "declined";
+pub const SIGNATURE_REQUEST_EXPIRED: &str = "expired";
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct CreateSignatureRequestInput {
+    pub file_id: i32,
+    pub signers: Vec<String>,
+    /// Between one second and one year. Requests without expiry stay pending until signed or
+    /// declined.
+    pub expires_in_seconds: Option<i64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct SignRequestInput {
+    pub signature: String,
+    pub publickey: String,
+    pub wallet_address: String,
+    /// `personal_sign` (default) or `eip712`.
+    pub signing_mode: Option<String>,
+    /// Timestamp of the signed typed data, for `eip712`.
+    pub timestamp: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SignatureRequestInfo {
+    pub request: SignatureRequestTable,
+    pub file_name: String,
+    /// Hash the signer has to sign, set while the request is pending.
+    pub previous_verification_hash: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SignatureRequestApiResponse {
+    pub logs: Vec<String>,
+    pub requests: Vec<SignatureRequestInfo>,
+}
@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::sync::OnceLock;
+extern crate serde_json_path_to_error as serde_json;
+
+/// A network witnesses can be anchored on.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WitnessNetwork {
+    pub name: String,
+    pub chain_id: u64,
+    /// JSON-RPC endpoint used to confirm witness transactions.
+    pub rpc_url: Option<String>,
+    /// Witness contract on this network, the caller's profile contract is used if unset.
+    pub contract_address: Option<String>,
+}
+
+/// Known witness networks by name. Mainnet, sepolia and holesky are built in,
+/// `WITNESS_NETWORKS_FILE` can point at a JSON list of `WitnessNetwork` that adds or replaces entries.
+#[derive(Debug, Clone)]
+pub struct NetworkRegistry {
+    networks: BTreeMap<String, WitnessNetwork>,
+}
+
+fn builtin_networks() -> BTreeMap<String, WitnessNetwork> {
+    [("mainnet", 1), ("sepolia", 11155111), ("holesky", 17000)]
+        .into_iter()
+        .map(|(name, chain_id)| {
+            let network = WitnessNetwork {
+                name: name.to_string(),
+                chain_id,
+                rpc_url: rpc_url_from_env(name),
+                contract_address: None,
+            };
+            (network.name.clone(), network)
+        })
+        .collect()
+}
+
+/// `WITNESS_RPC_URL_<NETWORK>`, e.g. `WITNESS_RPC_URL_SEPOLIA` or `WITNESS_RPC_URL_ANVIL`.
+fn rpc_url_from_env(network: &str) -> Option<String> {
+    let key = format!(
+        "WITNESS_RPC_URL_{}",
+        network.trim().to_uppercase().replace('-', "_")
+    );
+    env::var(key).ok().filter(|url| !url.trim().is_empty())
+}
+
+impl NetworkRegistry {
+    pub fn load() -> Result<Self, String> {
+        let mut networks = builtin_networks();
+
+        if let Ok(path) = env::var("WITNESS_NETWORKS_FILE") {
+            let data = fs::read_to_string(&path)
+                .map_err(|e| format!("Error reading witness networks file {}: {}", path, e))?;
+            let configured: Vec<WitnessNetwork> = serde_json::from_str(&data)
+                .map_err(|e| format!("Error parsing witness networks file {}: {}", path, e))?;
+            for mut network in configured {
+                network.name = network.name.trim().to_lowercase();
+                if network.name.is_empty() {
+                    return Err(format!("Witness networks file {} has an unnamed network", path));
+                }
+                networks.insert(network.name.clone(), network);
+            }
+        }
+
+        for network in networks.values_mut() {
+            if network.rpc_url.is_none() {
+                network.rpc_url = rpc_url_from_env(&network.name);
+            }
+        }
+
+        Ok(NetworkRegistry { networks })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&WitnessNetwork> {
+        self.networks.get(&name.trim().to_lowercase())
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.networks.keys().cloned().collect()
+    }
+
+    /// The requested network, or the caller's profile chain, or `CHAIN` when none was requested.
+    pub fn resolve(
+        &self,
+        requested: &str,
+        profile_chain: Option<&str>,
+    ) -> Result<&WitnessNetwork, String> {
+        let name = Some(requested)
+            .filter(|name| !name.trim().is_empty())
+            .or(profile_chain.filter(|name| !name.trim().is_empty()))
+            .map(|name| name.to_string())
+            .or_else(|| env::var("CHAIN").ok())
+            .filter(|name| !name.trim().is_empty())
+            .ok_or_else(|| "Network is empty".to_string())?;
+
+        self.get(&name).ok_or_else(|| {
+            format!(
+                "Unknown witness network {}, expected one of {}",
+                name.trim(),
+                self.names().join(", ")
+            )
+        })
+    }
+}
+
+static NETWORK_REGISTRY: OnceLock<NetworkRegistry> = OnceLock::new();
+
+/// The registry loaded on first use. A broken `WITNESS_NETWORKS_FILE` is logged and only the
+/// built in networks are served, so the server still starts.
+pub fn network_registry() -> &'static NetworkRegistry {
+    NETWORK_REGISTRY.get_or_init(|| {
+        NetworkRegistry::load().unwrap_or_else(|e| {
+            tracing::error!("Failed to load witness networks: {}", e);
+            NetworkRegistry {
+                networks: builtin_networks(),
+            }
+        })
+    })
+}
@@ -0,0 +1,176 @@
+pub mod v1_3;
+
+use crate::blob_store::{genesis_file, serialize_for_storage};
+use crate::models::page_data::PageDataContainer;
+use crate::models::UserProfilesTable;
+use aqua_verifier_rs_types::models::page_data::HashChain;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+use v1_3::AquaChainV13;
+extern crate serde_json_path_to_error as serde_json;
+
+/// Aqua protocol versions the container reads and writes.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProtocolVersion {
+    /// `aqua_verifier_rs_types` chains: SHA3-512 hashes, the file embedded in the genesis revision.
+    #[default]
+    #[serde(rename = "1.2")]
+    V1_2,
+    /// Flat revisions hashed as merkle trees of their fields, the file travels separately.
+    #[serde(rename = "1.3")]
+    V1_3,
+}
+
+impl ProtocolVersion {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProtocolVersion::V1_2 => "1.2",
+            ProtocolVersion::V1_3 => "1.3",
+        }
+    }
+
+    /// v1.2 documents wrap their chains in `pages`, v1.3 documents key revisions by hash.
+    pub fn detect(value: &Value) -> Option<Self> {
+        if value.get("pages").map_or(false, Value::is_array) {
+            Some(ProtocolVersion::V1_2)
+        } else if value.get("revisions").map_or(false, Value::is_object) {
+            Some(ProtocolVersion::V1_3)
+        } else {
+            None
+        }
+    }
+
+    /// The version new chains of a user are created in: the profile's choice,
+    /// else `PROTOCOL_VERSION`, else v1.2.
+    pub fn for_profile(profile: Option<&UserProfilesTable>) -> Self {
+        profile
+            .map(|profile| profile.protocol_version.clone())
+            .filter(|version| !version.trim().is_empty())
+            .or_else(|| env::var("PROTOCOL_VERSION").ok())
+            .and_then(|version| version.parse().ok())
+            .unwrap_or_default()
+    }
+}
+
+impl FromStr for ProtocolVersion {
+    type Err = String;
+
+    /// Accepts `1.3`, `v1.3` and full versions like `1.3.0`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let version = value.trim().trim_start_matches('v');
+        let mut parts = version.split('.');
+        match (parts.next(), parts.next()) {
+            (Some("1"), Some("2")) => Ok(ProtocolVersion::V1_2),
+            (Some("1"), Some("3")) => Ok(ProtocolVersion::V1_3),
+            _ => Err(format!("Unsupported protocol version {}", version)),
+        }
+    }
+}
+
+impl fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// An Aqua JSON document of either protocol version.
+#[derive(Debug, Clone)]
+pub enum AquaDocument {
+    V1_2(PageDataContainer<HashChain>),
+    V1_3(AquaChainV13),
+}
+
+impl AquaDocument {
+    /// Parses an uploaded or stored document, detecting its version.
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        let value: Value =
+            serde_json::from_slice(data).map_err(|e| format!("Failed to parse JSON: {}", e))?;
+        match ProtocolVersion::detect(&value) {
+            Some(ProtocolVersion::V1_2) => serde_json::from_value(value)
+                .map(AquaDocument::V1_2)
+                .map_err(|e| format!("Failed to parse v1.2 chain: {}", e)),
+            Some(ProtocolVersion::V1_3) => serde_json::from_value(value)
+                .map(AquaDocument::V1_3)
+                .map_err(|e| format!("Failed to parse v1.3 chain: {}", e)),
+            None => Err("Document is neither a v1.2 nor a v1.3 Aqua chain".to_string()),
+        }
+    }
+
+    pub fn version(&self) -> ProtocolVersion {
+        match self {
+            AquaDocument::V1_2(_) => ProtocolVersion::V1_2,
+            AquaDocument::V1_3(_) => ProtocolVersion::V1_3,
+        }
+    }
+
+    /// JSON for the `pages.page_data` column, file bytes live in the blob store.
+    pub fn to_storage(&mut self) -> Result<String, String> {
+        match self {
+            AquaDocument::V1_2(doc) => serialize_for_storage(doc),
+            AquaDocument::V1_3(chain) => serde_json::to_string(chain)
+                .map_err(|e| format!("Failed to serialize page data: {}", e)),
+        }
+    }
+
+    pub fn latest_verification_hash(&self) -> Option<String> {
+        match self {
+            AquaDocument::V1_2(doc) => doc
+                .pages
+                .first()?
+                .revisions
+                .last()
+                .map(|(hash, _)| hash.to_string()),
+            AquaDocument::V1_3(chain) => chain.latest_verification_hash(),
+        }
+    }
+
+    pub fn contains_revision(&self, verification_hash: &str) -> bool {
+        match self {
+            AquaDocument::V1_2(doc) => doc.pages.first().map_or(false, |chain| {
+                chain
+                    .revisions
+                    .iter()
+                    .any(|(hash, _)| hash.to_string() == verification_hash)
+            }),
+            AquaDocument::V1_3(chain) => chain.revisions.contains_key(verification_hash),
+        }
+    }
+
+    /// Hash of the file as the chain names it, SHA3-512 for v1.2 and SHA-256 for v1.3.
+    pub fn file_hash(&self) -> Option<String> {
+        match self {
+            AquaDocument::V1_2(doc) => genesis_file(doc).map(|(hash, _)| hash.to_string()),
+            AquaDocument::V1_3(chain) => chain.file_hash(),
+        }
+    }
+
+    /// Name and blob key of the file the chain is about, plus data still kept inline.
+    pub fn stored_file(&self) -> Option<StoredFile> {
+        match self {
+            AquaDocument::V1_2(doc) => {
+                let (file_hash, file) = genesis_file(doc)?;
+                let inline_data: &[u8] = file.data.as_ref();
+                Some(StoredFile {
+                    blob_key: file_hash.to_string(),
+                    file_name: file.filename.clone(),
+                    inline_data: inline_data.to_vec(),
+                })
+            }
+            AquaDocument::V1_3(chain) => Some(StoredFile {
+                blob_key: chain.file_hash()?,
+                file_name: chain.file_name().unwrap_or_default(),
+                inline_data: Vec::new(),
+            }),
+        }
+    }
+}
+
+pub struct StoredFile {
+    pub blob_key: String,
+    pub file_name: String,
+    /// v1.2 chains written before the blob store keep the file in the genesis revision.
+    pub inline_data: Vec<u8>,
+}
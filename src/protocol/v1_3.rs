@@ -0,0 +1,827 @@
+use crate::blob_store::is_sha256_key;
+use crate::eip712::{
+    recover_typed_data_signer, RevisionTypedData, SIGNING_MODE_EIP712, SIGNING_MODE_PERSONAL_SIGN,
+};
+use crate::verification::{
+    public_key_address, signature_message_for, verify_signature, verify_witness, RevisionKind,
+    RevisionReport, SignatureScheme, SignatureSchemes, VerificationCheck, VerificationReport,
+};
+use aqua_verifier::util::{content_hash, metadata_hash, verification_hash};
+use aqua_verifier_rs_types::models::hash::Hash;
+use aqua_verifier_rs_types::models::page_data::HashChain;
+use aqua_verifier_rs_types::models::signature::{RevisionSignature, Signature};
+use aqua_verifier_rs_types::models::timestamp::Timestamp;
+use aqua_verifier_rs_types::models::witness::RevisionWitness;
+use chrono::Utc;
+use ethers::core::k256::sha2::{Digest, Sha256};
+use ethers::types::Address;
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+extern crate serde_json_path_to_error as serde_json;
+
+pub const REVISION_TYPE_FILE: &str = "file";
+pub const REVISION_TYPE_SIGNATURE: &str = "signature";
+pub const REVISION_TYPE_WITNESS: &str = "witness";
+pub const REVISION_TYPE_LINK: &str = "link";
+
+pub const SIGNATURE_TYPE_EIP191: &str = "ethereum:eip-191";
+pub const SIGNATURE_TYPE_EIP712: &str = "ethereum:eip-712";
+
+/// A v1.3 revision is a flat set of fields. `leaves` holds `sha256("<name>:<value>")` of every
+/// other field in name order and the verification hash is `0x` followed by their merkle root.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct RevisionV13 {
+    #[serde(flatten)]
+    pub fields: BTreeMap<String, Value>,
+    #[serde(default)]
+    pub leaves: Vec<String>,
+}
+
+impl RevisionV13 {
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields.get(name).and_then(Value::as_str)
+    }
+
+    pub fn set(&mut self, name: &str, value: impl Into<Value>) {
+        self.fields.insert(name.to_string(), value.into());
+    }
+
+    /// `None` for the genesis revision.
+    pub fn previous_verification_hash(&self) -> Option<&str> {
+        self.field("previous_verification_hash")
+            .filter(|hash| !hash.is_empty())
+    }
+
+    pub fn revision_type(&self) -> &str {
+        self.field("revision_type").unwrap_or_default()
+    }
+
+    /// The verification hash `leaves` commit to, which only matches the fields once verified.
+    pub fn verification_hash(&self) -> String {
+        leaves_verification_hash(&self.leaves)
+    }
+
+    /// Fills `leaves` from the fields and returns the verification hash.
+    pub fn seal(&mut self) -> String {
+        self.leaves = revision_leaves(&self.fields);
+        self.verification_hash()
+    }
+}
+
+/// A v1.3 document: revisions keyed by verification hash and the file name of the genesis.
+/// The file itself is not embedded, it travels next to the chain.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AquaChainV13 {
+    pub revisions: BTreeMap<String, RevisionV13>,
+    #[serde(default)]
+    pub file_index: BTreeMap<String, String>,
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+fn field_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+pub fn revision_leaves(fields: &BTreeMap<String, Value>) -> Vec<String> {
+    fields
+        .iter()
+        .map(|(name, value)| sha256_hex(format!("{}:{}", name, field_text(value)).as_bytes()))
+        .collect()
+}
+
+/// Pairs are hashed as `sha256(left + right)` over their hex strings, an odd node is carried up.
+pub fn merkle_root(leaves: &[String]) -> Option<String> {
+    let mut level = leaves.to_vec();
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| {
+                if pair.len() == 2 {
+                    sha256_hex(pair.concat().as_bytes())
+                } else {
+                    pair[0].clone()
+                }
+            })
+            .collect();
+    }
+    level.pop()
+}
+
+fn leaves_verification_hash(leaves: &[String]) -> String {
+    format!("0x{}", merkle_root(leaves).unwrap_or_default())
+}
+
+fn local_timestamp() -> String {
+    Utc::now().format("%Y%m%d%H%M%S").to_string()
+}
+
+fn file_nonce() -> String {
+    hex::encode(thread_rng().gen::<[u8; 32]>())
+}
+
+fn strip_0x(hash: &str) -> &str {
+    hash.trim_start_matches("0x")
+}
+
+impl AquaChainV13 {
+    /// Adds a sealed revision and returns its verification hash.
+    pub fn push(&mut self, mut revision: RevisionV13) -> String {
+        let hash = revision.seal();
+        self.revisions.insert(hash.clone(), revision);
+        hash
+    }
+
+    pub fn genesis(&self) -> Option<(&String, &RevisionV13)> {
+        let mut genesis = self
+            .revisions
+            .iter()
+            .filter(|(_, revision)| revision.previous_verification_hash().is_none());
+        match (genesis.next(), genesis.next()) {
+            (Some(genesis), None) => Some(genesis),
+            _ => None,
+        }
+    }
+
+    /// Revisions from genesis to the latest, following `previous_verification_hash`.
+    pub fn ordered(&self) -> Result<Vec<(&String, &RevisionV13)>, String> {
+        let genesis = self
+            .genesis()
+            .ok_or_else(|| "chain needs exactly one genesis revision".to_string())?;
+
+        let mut ordered = vec![genesis];
+        loop {
+            let (current, _) = ordered[ordered.len() - 1];
+            let mut children = self.revisions.iter().filter(|(_, revision)| {
+                revision.previous_verification_hash() == Some(current.as_str())
+            });
+            match (children.next(), children.next()) {
+                (None, _) => break,
+                (Some(child), None) => ordered.push(child),
+                (Some(_), Some(_)) => return Err(format!("chain forks after {}", current)),
+            }
+        }
+
+        if ordered.len() != self.revisions.len() {
+            return Err(format!(
+                "{} revisions are not linked to the genesis revision",
+                self.revisions.len() - ordered.len()
+            ));
+        }
+        Ok(ordered)
+    }
+
+    pub fn latest_verification_hash(&self) -> Option<String> {
+        self.ordered()
+            .ok()?
+            .last()
+            .map(|(hash, _)| (*hash).clone())
+    }
+
+    pub fn file_name(&self) -> Option<String> {
+        let (hash, _) = self.genesis()?;
+        self.file_index.get(hash).cloned()
+    }
+
+    /// SHA-256 of the file, the key of its blob.
+    pub fn file_hash(&self) -> Option<String> {
+        let (_, genesis) = self.genesis()?;
+        genesis.field("file_hash").map(str::to_string)
+    }
+
+    /// The genesis file hash, an error unless it is the lowercase hex of a SHA-256 and can key
+    /// a blob.
+    pub fn checked_file_hash(&self) -> Result<String, String> {
+        let file_hash = self
+            .file_hash()
+            .ok_or_else(|| "genesis revision has no file_hash".to_string())?;
+        if !is_sha256_key(&file_hash) {
+            return Err(format!("file_hash {:?} is not a SHA-256 hex digest", file_hash));
+        }
+        Ok(file_hash)
+    }
+
+    /// Verification hashes of the link revisions with the verification hash each points at.
+    pub fn links(&self) -> Vec<(String, String)> {
+        self.revisions
+            .iter()
+            .filter(|(_, revision)| revision.revision_type() == REVISION_TYPE_LINK)
+            .map(|(hash, revision)| {
+                let linked = revision.field("link_verification_hash").unwrap_or_default();
+                (hash.clone(), linked.to_string())
+            })
+            .collect()
+    }
+
+    /// Verification hashes, file hashes, signers and witness transactions a search finds the
+    /// chain by, including the v1.2 hashes of upgraded revisions.
+    pub fn search_terms(&self) -> Vec<String> {
+        let mut terms = Vec::new();
+        for (hash, revision) in &self.revisions {
+            terms.push(hash.clone());
+            for name in [
+                "legacy_verification_hash",
+                "file_hash",
+                "signature_wallet_address",
+                "witness_transaction_hash",
+            ] {
+                if let Some(value) = revision.field(name).filter(|value| !value.is_empty()) {
+                    terms.push(value.to_string());
+                }
+            }
+        }
+        terms
+    }
+
+    /// v1.2 verification hashes of the upgraded signature revisions, which key their schemes.
+    pub fn legacy_signature_hashes(&self) -> Vec<String> {
+        self.revisions
+            .values()
+            .filter(|revision| revision.revision_type() == REVISION_TYPE_SIGNATURE)
+            .filter_map(|revision| revision.field("legacy_verification_hash"))
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+/// A new chain for a file whose SHA-256 is `file_hash`.
+pub fn genesis_chain(file_name: &str, file_hash: &str) -> AquaChainV13 {
+    let mut genesis = RevisionV13::default();
+    genesis.set("previous_verification_hash", "");
+    genesis.set("local_timestamp", local_timestamp());
+    genesis.set("revision_type", REVISION_TYPE_FILE);
+    genesis.set("file_hash", file_hash);
+    genesis.set("file_nonce", file_nonce());
+
+    let mut chain = AquaChainV13::default();
+    let hash = chain.push(genesis);
+    chain.file_index.insert(hash, file_name.to_string());
+    chain
+}
+
+/// Appends a revision linking to `linked_verification_hash` of another chain and returns its
+/// hash. `linked_file_hash` is the file hash the linked chain names, in its own format.
+pub fn append_link_revision(
+    chain: &mut AquaChainV13,
+    linked_verification_hash: &str,
+    linked_file_hash: &str,
+) -> Result<String, String> {
+    let previous = chain.latest_verification_hash().ok_or_else(|| {
+        "chain has no revisions or is not linear".to_string()
+    })?;
+
+    let mut link = RevisionV13::default();
+    link.set("previous_verification_hash", previous);
+    link.set("local_timestamp", local_timestamp());
+    link.set("revision_type", REVISION_TYPE_LINK);
+    link.set("link_type", "aqua");
+    link.set("link_verification_hash", linked_verification_hash);
+    link.set("link_file_hash", linked_file_hash);
+    Ok(chain.push(link))
+}
+
+/// Appends a signature over the latest revision and returns its verification hash. The
+/// signature must already be checked against the hash, see `check_wallet_signature`.
+pub fn append_signature_revision(
+    chain: &mut AquaChainV13,
+    signature: &str,
+    public_key: &str,
+    wallet_address: &str,
+    scheme: &SignatureScheme,
+) -> Result<String, String> {
+    let previous = chain.latest_verification_hash().ok_or_else(|| {
+        "chain has no revisions or is not linear".to_string()
+    })?;
+
+    let mut revision = RevisionV13::default();
+    revision.set("previous_verification_hash", previous);
+    revision.set("local_timestamp", local_timestamp());
+    revision.set("revision_type", REVISION_TYPE_SIGNATURE);
+    revision.set("signature", format!("0x{}", strip_0x(signature)));
+    revision.set("signature_public_key", format!("0x{}", strip_0x(public_key)));
+    revision.set("signature_wallet_address", wallet_address);
+    match scheme {
+        SignatureScheme::PersonalSign => revision.set("signature_type", SIGNATURE_TYPE_EIP191),
+        SignatureScheme::Eip712(data) => {
+            revision.set("signature_type", SIGNATURE_TYPE_EIP712);
+            revision.set("signature_typed_data", json_value(data)?);
+        }
+    }
+    Ok(chain.push(revision))
+}
+
+/// A confirmed witness transaction, `merkle_proof` walks from the chain's latest verification
+/// hash up to `merkle_root`.
+#[derive(Debug, Clone)]
+pub struct WitnessV13 {
+    pub merkle_root: String,
+    pub merkle_proof: Vec<MerkleNodeV13>,
+    pub network: String,
+    pub transaction_hash: String,
+    pub smart_contract_address: String,
+    /// The account that sent the transaction.
+    pub sender_account_address: String,
+}
+
+/// Appends a witness revision over the latest revision and returns its verification hash.
+pub fn append_witness_revision(
+    chain: &mut AquaChainV13,
+    witness: WitnessV13,
+) -> Result<String, String> {
+    let previous = chain.latest_verification_hash().ok_or_else(|| {
+        "chain has no revisions or is not linear".to_string()
+    })?;
+
+    let mut revision = RevisionV13::default();
+    revision.set("previous_verification_hash", previous);
+    revision.set("local_timestamp", local_timestamp());
+    revision.set("revision_type", REVISION_TYPE_WITNESS);
+    revision.set("witness_merkle_root", strip_0x(&witness.merkle_root));
+    revision.set("witness_merkle_proof", json_value(&witness.merkle_proof)?);
+    revision.set("witness_network", witness.network);
+    revision.set(
+        "witness_transaction_hash",
+        format!("0x{}", strip_0x(&witness.transaction_hash)),
+    );
+    revision.set("witness_smart_contract_address", witness.smart_contract_address);
+    revision.set("witness_sender_account_address", witness.sender_account_address);
+    Ok(chain.push(revision))
+}
+
+/// A merkle tree over the latest verification hashes of several v1.3 chains, built the way
+/// `verify_merkle_proof` walks it. `proofs[i]` belongs to `leaves[i]`, a single chain has an
+/// empty proof and is its own root.
+#[derive(Debug, Clone)]
+pub struct MerkleTreeV13 {
+    pub root: String,
+    pub leaves: Vec<String>,
+    pub proofs: Vec<Vec<MerkleNodeV13>>,
+}
+
+pub fn build_merkle_tree(leaves: &[String]) -> Option<MerkleTreeV13> {
+    let leaves: Vec<String> = leaves.iter().map(|leaf| strip_0x(leaf).to_string()).collect();
+    if leaves.is_empty() {
+        return None;
+    }
+
+    let mut proofs: Vec<Vec<MerkleNodeV13>> = vec![Vec::new(); leaves.len()];
+    let mut positions: Vec<usize> = (0..leaves.len()).collect();
+    let mut level = leaves.clone();
+
+    while level.len() > 1 {
+        let nodes: Vec<Option<MerkleNodeV13>> = level
+            .chunks(2)
+            .map(|pair| {
+                (pair.len() == 2).then(|| MerkleNodeV13 {
+                    left_leaf: pair[0].clone(),
+                    right_leaf: pair[1].clone(),
+                    successor: sha256_hex(pair.concat().as_bytes()),
+                })
+            })
+            .collect();
+
+        for (proof, position) in proofs.iter_mut().zip(positions.iter_mut()) {
+            *position /= 2;
+            // an odd node is carried up without a step
+            if let Some(node) = &nodes[*position] {
+                proof.push(node.clone());
+            }
+        }
+
+        level = level
+            .chunks(2)
+            .zip(&nodes)
+            .map(|(pair, node)| {
+                node.as_ref()
+                    .map_or_else(|| pair[0].clone(), |node| node.successor.clone())
+            })
+            .collect();
+    }
+
+    Some(MerkleTreeV13 {
+        root: level[0].clone(),
+        leaves,
+        proofs,
+    })
+}
+
+fn json_value<T: Serialize>(value: &T) -> Result<Value, String> {
+    serde_json::to_value(value).map_err(|e| format!("Failed to serialize revision: {}", e))
+}
+
+/// Re-hashes a v1.2 chain as v1.3. Every revision keeps its v1.2 verification hash in
+/// `legacy_verification_hash`, and signatures and witnesses keep the v1.2 object they were
+/// made over, so they stay verifiable after the upgrade. `file_hash` is the SHA-256 of the file.
+pub fn upgrade_chain(
+    chain: &HashChain,
+    file_name: &str,
+    file_hash: &str,
+    schemes: &SignatureSchemes,
+) -> Result<AquaChainV13, String> {
+    let mut upgraded = AquaChainV13::default();
+    let mut previous = String::new();
+
+    for (index, (hash, revision)) in chain.revisions.iter().enumerate() {
+        let mut current = RevisionV13::default();
+        current.set("previous_verification_hash", previous.clone());
+        current.set(
+            "local_timestamp",
+            field_text(&json_value(&revision.metadata.time_stamp)?),
+        );
+        current.set("legacy_verification_hash", hash.to_string());
+        if let Some(legacy_previous) = &revision.metadata.previous_verification_hash {
+            current.set("legacy_previous_verification_hash", legacy_previous.to_string());
+        }
+
+        if let Some(signature) = &revision.signature {
+            let scheme = schemes
+                .get(&hash.to_string())
+                .unwrap_or(&SignatureScheme::PersonalSign);
+            current.set("revision_type", REVISION_TYPE_SIGNATURE);
+            current.set("signature", format!("0x{}", strip_0x(&signature.signature.to_string())));
+            current.set(
+                "signature_public_key",
+                format!("0x{}", strip_0x(&signature.public_key.to_string())),
+            );
+            current.set("signature_wallet_address", signature.wallet_address.to_string());
+            match scheme {
+                SignatureScheme::PersonalSign => {
+                    current.set("signature_type", SIGNATURE_TYPE_EIP191);
+                }
+                SignatureScheme::Eip712(data) => {
+                    current.set("signature_type", SIGNATURE_TYPE_EIP712);
+                    current.set("signature_typed_data", json_value(data)?);
+                }
+            }
+            current.set("legacy_signature", json_value(signature)?);
+        } else if let Some(witness) = &revision.witness {
+            current.set("revision_type", REVISION_TYPE_WITNESS);
+            current.set("witness_merkle_root", witness.merkle_root.to_string());
+            current.set("witness_network", witness.witness_network.clone());
+            current.set(
+                "witness_transaction_hash",
+                witness.witness_event_transaction_hash.to_string(),
+            );
+            current.set("legacy_witness", json_value(witness)?);
+        } else {
+            current.set("revision_type", REVISION_TYPE_FILE);
+            current.set("file_hash", file_hash);
+            current.set("file_nonce", file_nonce());
+        }
+
+        if index == 0 {
+            // what legacy signatures and witnesses were made over
+            current.set("legacy_protocol_version", "1.2");
+            current.set("legacy_domain_id", chain.domain_id.clone());
+            current.set(
+                "legacy_file_hash",
+                revision.content.content.file_hash.to_string(),
+            );
+        }
+
+        previous = upgraded.push(current);
+        if index == 0 {
+            upgraded.file_index.insert(previous.clone(), file_name.to_string());
+        }
+    }
+
+    if upgraded.revisions.is_empty() {
+        return Err("chain has no revisions".to_string());
+    }
+    Ok(upgraded)
+}
+
+pub fn revision_kind(revision: &RevisionV13) -> RevisionKind {
+    match revision.revision_type() {
+        REVISION_TYPE_SIGNATURE => RevisionKind::Signature,
+        REVISION_TYPE_WITNESS => RevisionKind::Witness,
+        REVISION_TYPE_LINK => RevisionKind::Link,
+        _ => RevisionKind::Content,
+    }
+}
+
+fn field_as<T: for<'de> Deserialize<'de>>(
+    revision: &RevisionV13,
+    name: &str,
+) -> Option<Result<T, String>> {
+    revision.fields.get(name).map(|value| {
+        serde_json::from_value(value.clone()).map_err(|e| format!("Invalid {}: {}", name, e))
+    })
+}
+
+/// Re-derives every leaf and hash of a v1.3 chain. `schemes` are keyed by the v1.2
+/// verification hash of upgraded signatures.
+pub fn verify_chain(chain: &AquaChainV13, schemes: &SignatureSchemes) -> VerificationReport {
+    let genesis = chain.genesis();
+    let revisions: Vec<(&String, &RevisionV13)> = match chain.ordered() {
+        Ok(ordered) => ordered,
+        Err(e) => {
+            tracing::debug!("v1.3 chain is not linear: {}", e);
+            chain.revisions.iter().collect()
+        }
+    };
+
+    let mut reports = Vec::new();
+    for (hash, revision) in revisions {
+        let mut report = RevisionReport::new(hash.clone(), revision_kind(revision));
+
+        let linked = match revision.previous_verification_hash() {
+            None => genesis.map_or(false, |(genesis_hash, _)| genesis_hash == hash),
+            Some(previous) => {
+                chain.revisions.contains_key(previous)
+                    && chain
+                        .revisions
+                        .values()
+                        .filter(|other| other.previous_verification_hash() == Some(previous))
+                        .count()
+                        == 1
+            }
+        };
+        report.record(VerificationCheck::ChainLinkage, linked, || {
+            format!(
+                "previous verification hash {:?} is missing or shared",
+                revision.previous_verification_hash()
+            )
+        });
+
+        let previous = revision
+            .previous_verification_hash()
+            .and_then(|previous| chain.revisions.get(previous));
+
+        let leaves = revision_leaves(&revision.fields);
+        report.record(VerificationCheck::Leaves, leaves == revision.leaves, || {
+            "leaves do not match the revision fields".to_string()
+        });
+
+        match revision.revision_type() {
+            REVISION_TYPE_SIGNATURE => verify_signature_revision(
+                &mut report,
+                revision,
+                previous,
+                genesis.map(|(_, genesis)| genesis),
+                schemes,
+            ),
+            REVISION_TYPE_WITNESS => verify_witness_revision(
+                &mut report,
+                revision,
+                previous,
+                genesis.map(|(_, genesis)| genesis),
+            ),
+            // whether the target exists is up to the caller, it is not part of the chain
+            REVISION_TYPE_LINK => report.record(
+                VerificationCheck::LinkedChain,
+                revision
+                    .field("link_verification_hash")
+                    .map_or(false, |hash| !hash.is_empty()),
+                || "link revision names no verification hash".to_string(),
+            ),
+            _ => {}
+        }
+
+        let recomputed = leaves_verification_hash(&leaves);
+        report.record(VerificationCheck::VerificationHash, recomputed == *hash, || {
+            format!("verification hash {} is not valid", hash)
+        });
+
+        report.valid = report.checks.iter().all(|check| check.passed);
+        reports.push(report);
+    }
+
+    VerificationReport {
+        valid: !reports.is_empty() && reports.iter().all(|r| r.valid),
+        revisions: reports,
+    }
+}
+
+fn verify_signature_revision(
+    report: &mut RevisionReport,
+    revision: &RevisionV13,
+    previous_revision: Option<&RevisionV13>,
+    genesis: Option<&RevisionV13>,
+    schemes: &SignatureSchemes,
+) {
+    // upgraded signatures were made over the v1.2 chain
+    if let Some(legacy) = field_as::<RevisionSignature>(revision, "legacy_signature") {
+        let legacy_previous = field_as::<Hash>(revision, "legacy_previous_verification_hash");
+        let (signature, previous) = match (legacy, legacy_previous) {
+            (Ok(signature), Some(Ok(previous))) => (signature, previous),
+            _ => {
+                report.record(VerificationCheck::SignatureSigner, false, || {
+                    "legacy signature cannot be read".to_string()
+                });
+                return;
+            }
+        };
+        let scheme = revision
+            .field("legacy_verification_hash")
+            .and_then(|hash| schemes.get(hash))
+            .unwrap_or(&SignatureScheme::PersonalSign);
+        let genesis_field =
+            |name: &str| genesis.and_then(|genesis| genesis.field(name)).unwrap_or_default();
+        let signature_hash = verify_signature(
+            report,
+            genesis_field("legacy_file_hash"),
+            genesis_field("legacy_domain_id"),
+            &signature,
+            Some(previous),
+            scheme,
+            false,
+        );
+        verify_legacy_hash(
+            report,
+            revision,
+            previous,
+            previous_revision,
+            genesis,
+            Some(signature_hash),
+            None,
+        );
+        return;
+    }
+
+    let previous = revision.previous_verification_hash().unwrap_or_default();
+    let wallet_address = revision.field("signature_wallet_address").unwrap_or_default();
+
+    let signer: Option<Address> = match revision.field("signature_type") {
+        Some(SIGNATURE_TYPE_EIP712) => {
+            report.signing_mode = Some(SIGNING_MODE_EIP712.to_string());
+            let data = field_as::<RevisionTypedData>(revision, "signature_typed_data")
+                .and_then(Result::ok);
+            report.record(
+                VerificationCheck::TypedData,
+                data.as_ref().map_or(false, |data| {
+                    strip_0x(&data.verification_hash).eq_ignore_ascii_case(strip_0x(previous))
+                }),
+                || "typed data does not describe this revision".to_string(),
+            );
+            let signature = revision
+                .field("signature")
+                .and_then(|signature| Signature::from_str(signature).ok());
+            match (signature, data) {
+                (Some(signature), Some(data)) => recover_typed_data_signer(&signature, &data),
+                _ => None,
+            }
+        }
+        _ => {
+            report.signing_mode = Some(SIGNING_MODE_PERSONAL_SIGN.to_string());
+            let signature = revision.field("signature").and_then(|signature| {
+                ethers::types::Signature::from_str(strip_0x(signature)).ok()
+            });
+            signature.and_then(|signature| signature.recover(signature_message_for(previous)).ok())
+        }
+    };
+
+    report.signer = signer.map(|signer| format!("{:?}", signer));
+    report.record(
+        VerificationCheck::SignatureSigner,
+        report
+            .signer
+            .as_deref()
+            .map_or(false, |signer| signer.eq_ignore_ascii_case(wallet_address)),
+        || format!("signature recovers to {:?} instead of {}", signer, wallet_address),
+    );
+
+    let key_address = revision
+        .field("signature_public_key")
+        .and_then(public_key_address)
+        .map(|address| format!("{:?}", address));
+    report.record(
+        VerificationCheck::PublicKey,
+        key_address.map_or(false, |address| address.eq_ignore_ascii_case(wallet_address)),
+        || format!("public key does not belong to {}", wallet_address),
+    );
+}
+
+fn verify_witness_revision(
+    report: &mut RevisionReport,
+    revision: &RevisionV13,
+    previous_revision: Option<&RevisionV13>,
+    genesis: Option<&RevisionV13>,
+) {
+    // upgraded witnesses were made over the v1.2 chain
+    if let Some(legacy) = field_as::<RevisionWitness>(revision, "legacy_witness") {
+        let legacy_previous = field_as::<Hash>(revision, "legacy_previous_verification_hash");
+        match (legacy, legacy_previous) {
+            (Ok(witness), Some(Ok(previous))) => {
+                let witness_hash = verify_witness(report, &witness, Some(previous));
+                verify_legacy_hash(
+                    report,
+                    revision,
+                    previous,
+                    previous_revision,
+                    genesis,
+                    None,
+                    Some(witness_hash),
+                );
+            }
+            _ => report.record(VerificationCheck::WitnessHash, false, || {
+                "legacy witness cannot be read".to_string()
+            }),
+        }
+        return;
+    }
+
+    report.witness_network = revision.field("witness_network").map(str::to_string);
+    report.witness_tx_hash = revision.field("witness_transaction_hash").map(str::to_string);
+
+    let previous = strip_0x(revision.previous_verification_hash().unwrap_or_default()).to_string();
+    let root = strip_0x(revision.field("witness_merkle_root").unwrap_or_default()).to_string();
+    let proof = field_as::<Vec<MerkleNodeV13>>(revision, "witness_merkle_proof")
+        .unwrap_or(Ok(Vec::new()));
+    let valid = match proof {
+        Ok(proof) => verify_merkle_proof(&proof, &previous, &root),
+        Err(e) => Err(e),
+    };
+    report.record(VerificationCheck::MerkleProof, valid.is_ok(), || {
+        valid.clone().err().unwrap_or_default()
+    });
+}
+
+/// A legacy signature or witness only holds for the v1.2 chain it was made over, so the
+/// upgraded revision has to follow the v1.2 revision of its predecessor and its
+/// `legacy_verification_hash` has to hash the v1.2 revision it was upgraded from. Upgraded
+/// revisions keep the file of the genesis and their own timestamp.
+fn verify_legacy_hash(
+    report: &mut RevisionReport,
+    revision: &RevisionV13,
+    legacy_previous: Hash,
+    previous_revision: Option<&RevisionV13>,
+    genesis: Option<&RevisionV13>,
+    signature_hash: Option<Hash>,
+    witness_hash: Option<Hash>,
+) {
+    let follows = previous_revision
+        .and_then(|previous| previous.field("legacy_verification_hash"))
+        .map_or(false, |hash| hash == legacy_previous.to_string());
+    report.record(VerificationCheck::LegacyVerificationHash, follows, || {
+        format!(
+            "legacy previous verification hash {} is not the v1.2 hash of the revision before it",
+            legacy_previous
+        )
+    });
+
+    let legacy_hash = revision.field("legacy_verification_hash").unwrap_or_default();
+    let file_hash = genesis.and_then(|genesis| genesis.field("legacy_file_hash"));
+    let domain_id = genesis.and_then(|genesis| genesis.field("legacy_domain_id"));
+    let timestamp = revision.field("local_timestamp").and_then(|text| {
+        serde_json::from_value::<Timestamp>(Value::String(text.to_string()))
+            .ok()
+            .or_else(|| serde_json::from_str::<Timestamp>(text).ok())
+    });
+    let recomputed = match (file_hash, domain_id, timestamp) {
+        (Some(file_hash), Some(domain_id), Some(timestamp)) => {
+            let mut content = BTreeMap::new();
+            content.insert("file_hash".to_owned(), file_hash.to_string());
+            Some(verification_hash(
+                &content_hash(&content),
+                &metadata_hash(domain_id, &timestamp, Some(&legacy_previous)),
+                signature_hash.as_ref(),
+                witness_hash.as_ref(),
+            ))
+        }
+        _ => None,
+    };
+    report.record(
+        VerificationCheck::LegacyVerificationHash,
+        recomputed.map_or(false, |hash| hash.to_string() == legacy_hash),
+        || format!("legacy verification hash {} is not valid", legacy_hash),
+    );
+}
+
+/// A step of a v1.3 witness merkle proof, hashes are hex without `0x`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MerkleNodeV13 {
+    pub left_leaf: String,
+    pub right_leaf: String,
+    pub successor: String,
+}
+
+/// Walks the proof from `leaf` up to `root`. A witness of a single chain has no proof and
+/// publishes the verification hash itself as the root.
+fn verify_merkle_proof(proof: &[MerkleNodeV13], leaf: &str, root: &str) -> Result<(), String> {
+    let mut current = leaf.to_string();
+    for node in proof {
+        if node.left_leaf != current && node.right_leaf != current {
+            return Err(format!("merkle node does not contain {}", current));
+        }
+        let successor = sha256_hex(format!("{}{}", node.left_leaf, node.right_leaf).as_bytes());
+        if successor != node.successor {
+            return Err(format!("merkle node successor {} is not valid", node.successor));
+        }
+        current = successor;
+    }
+
+    if current.eq_ignore_ascii_case(root) {
+        Ok(())
+    } else {
+        Err(format!("merkle root {} is not valid", root))
+    }
+}
@@ -1,5 +1,15 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    file_access (id) {
+        id -> Nullable<Integer>,
+        file_id -> Integer,
+        address -> Text,
+        access -> Text,
+        created_time -> Text,
+    }
+}
+
 diesel::table! {
     pages (id) {
         id -> Integer,
@@ -10,6 +20,27 @@ diesel::table! {
         mode -> Text,
         created_at -> Text,
         is_shared -> Bool,
+        version -> Integer,
+    }
+}
+
+diesel::table! {
+    revisions (id) {
+        id -> Nullable<Integer>,
+        file_id -> Integer,
+        position -> Integer,
+        verification_hash -> Text,
+        previous_verification_hash -> Nullable<Text>,
+        kind -> Text,
+        domain_id -> Text,
+        time_stamp -> Text,
+        metadata_hash -> Text,
+        content_hash -> Text,
+        file_hash -> Nullable<Text>,
+        signer -> Nullable<Text>,
+        witness_network -> Nullable<Text>,
+        witness_tx_hash -> Nullable<Text>,
+        revision_data -> Text,
     }
 }
 
@@ -22,6 +53,50 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    chain_links (id) {
+        id -> Nullable<Integer>,
+        file_id -> Integer,
+        revision_hash -> Text,
+        linked_file_id -> Integer,
+        linked_verification_hash -> Text,
+        created_time -> Text,
+    }
+}
+
+diesel::table! {
+    signature_modes (id) {
+        id -> Nullable<Integer>,
+        verification_hash -> Text,
+        mode -> Text,
+        typed_data -> Text,
+        created_time -> Text,
+    }
+}
+
+diesel::table! {
+    signature_requests (id) {
+        id -> Nullable<Integer>,
+        file_id -> Integer,
+        requester -> Text,
+        signer -> Text,
+        status -> Text,
+        created_time -> Text,
+        updated_time -> Text,
+        expires_at -> Nullable<Text>,
+        verification_hash -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    siwe_nonces (id) {
+        id -> Nullable<Integer>,
+        nonce -> Text,
+        created_at -> Text,
+        expires_at -> Text,
+    }
+}
+
 diesel::table! {
     siwe_sessions (id) {
         id -> Nullable<Integer>,
@@ -29,6 +104,7 @@ diesel::table! {
         nonce -> Text,
         issued_at -> Text,
         expiration_time -> Nullable<Text>,
+        expires_at -> Nullable<Text>,
     }
 }
 
@@ -41,12 +117,19 @@ diesel::table! {
         contract_address -> Text,
         file_mode -> Text,
         domain_name -> Text,
+        protocol_version -> Text,
     }
 }
 
 diesel::allow_tables_to_appear_in_same_query!(
+    chain_links,
+    file_access,
     pages,
+    revisions,
     share_data,
+    signature_modes,
+    signature_requests,
+    siwe_nonces,
     siwe_sessions,
     user_profiles,
 );
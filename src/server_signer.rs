@@ -0,0 +1,117 @@
+use crate::verification::signature_message_for;
+use aqua_verifier_rs_types::models::public_key::PublicKey;
+use aqua_verifier_rs_types::models::signature::Signature;
+use ethers::signers::{LocalWallet, Signer};
+use std::env;
+use std::fs;
+use std::sync::OnceLock;
+
+/// A key held by the container, used to sign revisions for automated pipelines.
+/// Configured with `SERVER_SIGNER_KEYSTORE` (an encrypted JSON keystore) and
+/// `SERVER_SIGNER_PASSWORD` or `SERVER_SIGNER_PASSWORD_FILE`.
+#[derive(Debug, Clone)]
+pub struct ServerSigner {
+    wallet: LocalWallet,
+    /// Signed in addresses allowed to sign with this key, from `SERVER_SIGNER_ALLOWED_ADDRESSES`.
+    allowed_addresses: Vec<String>,
+}
+
+/// What a signature revision needs, in the same form MetaMask delivers it.
+pub struct ServerSignature {
+    pub signature: Signature,
+    pub public_key: PublicKey,
+    pub wallet_address: ethaddr::Address,
+}
+
+fn keystore_password() -> Result<String, String> {
+    if let Ok(password) = env::var("SERVER_SIGNER_PASSWORD") {
+        return Ok(password);
+    }
+    let path = env::var("SERVER_SIGNER_PASSWORD_FILE")
+        .map_err(|_| "SERVER_SIGNER_PASSWORD or SERVER_SIGNER_PASSWORD_FILE must be set".to_string())?;
+    fs::read_to_string(&path)
+        .map(|password| password.trim_end_matches(['\r', '\n']).to_string())
+        .map_err(|e| format!("Error reading signer password file {}: {}", path, e))
+}
+
+impl ServerSigner {
+    /// `Ok(None)` when no keystore is configured, the server signer is optional.
+    pub fn load() -> Result<Option<Self>, String> {
+        let keystore = match env::var("SERVER_SIGNER_KEYSTORE") {
+            Ok(path) if !path.trim().is_empty() => path,
+            _ => return Ok(None),
+        };
+
+        let wallet = LocalWallet::decrypt_keystore(&keystore, keystore_password()?)
+            .map_err(|e| format!("Error decrypting signer keystore {}: {}", keystore, e))?;
+
+        let allowed_addresses = env::var("SERVER_SIGNER_ALLOWED_ADDRESSES")
+            .unwrap_or_default()
+            .split(',')
+            .map(|address| address.trim().to_string())
+            .filter(|address| !address.is_empty())
+            .collect();
+
+        Ok(Some(ServerSigner {
+            wallet,
+            allowed_addresses,
+        }))
+    }
+
+    pub fn address(&self) -> String {
+        format!("{:?}", self.wallet.address())
+    }
+
+    /// Only addresses listed in `SERVER_SIGNER_ALLOWED_ADDRESSES` may use the key, nobody if it is empty.
+    pub fn is_allowed(&self, address: &str) -> bool {
+        self.allowed_addresses
+            .iter()
+            .any(|allowed| allowed.eq_ignore_ascii_case(address))
+    }
+
+    /// Signs the same message MetaMask signs for `previous_verification_hash`, a hash of
+    /// either protocol version.
+    pub async fn sign(&self, previous_verification_hash: &str) -> Result<ServerSignature, String> {
+        let signature = self
+            .wallet
+            .sign_message(signature_message_for(previous_verification_hash))
+            .await
+            .map_err(|e| format!("Error signing revision: {}", e))?;
+
+        let public_key = self
+            .wallet
+            .signer()
+            .verifying_key()
+            .to_encoded_point(false);
+
+        Ok(ServerSignature {
+            signature: format!("0x{}", signature)
+                .parse::<Signature>()
+                .map_err(|e| format!("Error encoding signature: {:?}", e))?,
+            public_key: format!("0x{}", hex::encode(public_key.as_bytes()))
+                .parse::<PublicKey>()
+                .map_err(|e| format!("Error encoding public key: {:?}", e))?,
+            wallet_address: ethaddr::Address(self.wallet.address().0),
+        })
+    }
+}
+
+static SERVER_SIGNER: OnceLock<Option<ServerSigner>> = OnceLock::new();
+
+/// The configured server signer, loaded once. A keystore that fails to load disables it.
+pub fn server_signer() -> Option<&'static ServerSigner> {
+    SERVER_SIGNER
+        .get_or_init(|| match ServerSigner::load() {
+            Ok(signer) => {
+                if let Some(signer) = &signer {
+                    tracing::info!("Server signer loaded for {}", signer.address());
+                }
+                signer
+            }
+            Err(e) => {
+                tracing::error!("Server signer disabled: {}", e);
+                None
+            }
+        })
+        .as_ref()
+}
@@ -0,0 +1,290 @@
+use crate::db::signature_modes::{fetch_signature_modes, insert_signature_mode};
+use crate::eip712::{RevisionTypedData, SIGNING_MODE_EIP712, SIGNING_MODE_PERSONAL_SIGN};
+use crate::models::SignatureModeTable;
+use crate::protocol::{v1_3, AquaDocument};
+use crate::verification::{
+    confirm_contract_signatures, verify_hash_chain_with_context, SignatureContext,
+    SignatureScheme, SignatureSchemes,
+};
+use aqua_verifier::util::{metadata_hash, signature_hash, verification_hash};
+use aqua_verifier_rs_types::models::hash::Hash;
+use aqua_verifier_rs_types::models::page_data::HashChain;
+use aqua_verifier_rs_types::models::public_key::PublicKey;
+use aqua_verifier_rs_types::models::signature::{RevisionSignature, Signature};
+use aqua_verifier_rs_types::models::timestamp::Timestamp;
+use chrono::Utc;
+use diesel::r2d2::{ConnectionManager, PooledConnection};
+use crate::db::DbConnection;
+use std::collections::{BTreeMap, BTreeSet};
+extern crate serde_json_path_to_error as serde_json;
+
+/// EIP-712 typed data of signature revisions by verification hash. A chain does not record how
+/// it was signed, so exports carry this next to it as `signature_modes`.
+pub type ExportedSignatureModes = BTreeMap<String, RevisionTypedData>;
+
+/// Appends a signature revision over the chain's latest verification hash and returns its hash.
+pub fn append_signature_revision(
+    chain: &mut HashChain,
+    signature: Signature,
+    public_key: PublicKey,
+    wallet_address: ethaddr::Address,
+) -> Result<Hash, String> {
+    let (previous_hash, previous) = chain
+        .revisions
+        .last()
+        .cloned()
+        .ok_or_else(|| "chain has no revisions".to_string())?;
+
+    let signature_hash_current = signature_hash(&signature, &public_key);
+
+    let mut revision = previous;
+    revision.witness = None;
+    revision.content.file = None;
+    revision.signature = Some(RevisionSignature {
+        signature,
+        public_key,
+        signature_hash: signature_hash_current,
+        wallet_address,
+    });
+
+    let timestamp_current = Timestamp::from(chrono::Utc::now().naive_utc());
+    let metadata_hash_current =
+        metadata_hash(&chain.domain_id, &timestamp_current, Some(&previous_hash));
+    let verification_hash_current = verification_hash(
+        &revision.content.content_hash,
+        &metadata_hash_current,
+        Some(&signature_hash_current),
+        None,
+    );
+
+    revision.metadata.previous_verification_hash = Some(previous_hash);
+    revision.metadata.time_stamp = timestamp_current;
+    revision.metadata.metadata_hash = metadata_hash_current;
+    revision.metadata.verification_hash = verification_hash_current;
+
+    chain.revisions.push((verification_hash_current, revision));
+    Ok(verification_hash_current)
+}
+
+/// Appends a signature revision to a document of either protocol version and returns its
+/// verification hash. v1.3 revisions carry their typed data, v1.2 ones need
+/// `record_signature_scheme`.
+pub fn append_document_signature(
+    document: &mut AquaDocument,
+    signature: Signature,
+    public_key: PublicKey,
+    wallet_address: ethaddr::Address,
+    scheme: &SignatureScheme,
+) -> Result<String, String> {
+    match document {
+        AquaDocument::V1_2(doc) => {
+            let chain = doc
+                .pages
+                .first_mut()
+                .ok_or_else(|| "document has no chain".to_string())?;
+            append_signature_revision(chain, signature, public_key, wallet_address)
+                .map(|hash| hash.to_string())
+        }
+        AquaDocument::V1_3(chain) => v1_3::append_signature_revision(
+            chain,
+            &signature.to_string(),
+            &public_key.to_string(),
+            &wallet_address.to_string(),
+            scheme,
+        ),
+    }
+}
+
+/// The typed struct a wallet signs in EIP-712 mode for the document's latest revision.
+/// v1.3 chains have no domain and name the SHA-256 of the file.
+pub fn typed_data_for(document: &AquaDocument, timestamp: u64) -> Option<RevisionTypedData> {
+    match document {
+        AquaDocument::V1_2(doc) => {
+            let chain = doc.pages.first()?;
+            let (hash, revision) = chain.revisions.last()?;
+            Some(RevisionTypedData {
+                file_hash: revision.content.content.file_hash.to_string(),
+                verification_hash: hash.to_string(),
+                domain_id: chain.domain_id.clone(),
+                timestamp,
+            })
+        }
+        AquaDocument::V1_3(chain) => Some(RevisionTypedData {
+            file_hash: chain.file_hash()?,
+            verification_hash: chain.latest_verification_hash()?,
+            domain_id: String::new(),
+            timestamp,
+        }),
+    }
+}
+
+/// The scheme a submitted signature was made with, `personal_sign` unless `mode` says otherwise.
+/// EIP-712 signatures need the `timestamp` of the typed data the wallet signed.
+pub fn signature_scheme(
+    mode: Option<&str>,
+    timestamp: Option<u64>,
+    document: &AquaDocument,
+) -> Result<SignatureScheme, String> {
+    match mode.map(str::trim).filter(|mode| !mode.is_empty()) {
+        None | Some(SIGNING_MODE_PERSONAL_SIGN) => Ok(SignatureScheme::PersonalSign),
+        Some(SIGNING_MODE_EIP712) => {
+            let timestamp =
+                timestamp.ok_or_else(|| "timestamp is required for eip712 signatures".to_string())?;
+            typed_data_for(document, timestamp)
+                .map(SignatureScheme::Eip712)
+                .ok_or_else(|| "chain has no revisions".to_string())
+        }
+        Some(mode) => Err(format!("Unknown signing mode {}", mode)),
+    }
+}
+
+/// Remembers how a v1.2 signature revision was signed, `personal_sign` needs no entry.
+pub fn record_signature_scheme(
+    verification_hash: &str,
+    scheme: &SignatureScheme,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<(), String> {
+    let typed_data = match scheme {
+        SignatureScheme::PersonalSign => return Ok(()),
+        SignatureScheme::Eip712(data) => serde_json::to_string(data)
+            .map_err(|e| format!("Failed to serialize typed data: {}", e))?,
+    };
+
+    insert_signature_mode(
+        SignatureModeTable {
+            id: None,
+            verification_hash: verification_hash.to_string(),
+            mode: scheme.mode().to_string(),
+            typed_data,
+            created_time: Utc::now().naive_utc().format("%Y-%m-%d %H:%M:%S").to_string(),
+        },
+        db_connection,
+    )?;
+    Ok(())
+}
+
+/// The recorded schemes of every revision in `chain`.
+pub fn load_signature_schemes(
+    chain: &HashChain,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<SignatureSchemes, String> {
+    let hashes: Vec<String> = chain
+        .revisions
+        .iter()
+        .filter(|(_, revision)| revision.signature.is_some())
+        .map(|(hash, _)| hash.to_string())
+        .collect();
+    load_signature_schemes_for(&hashes, db_connection)
+}
+
+/// The recorded schemes of the signature revisions with the given verification hashes.
+pub fn load_signature_schemes_for(
+    hashes: &[String],
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<SignatureSchemes, String> {
+    if hashes.is_empty() {
+        return Ok(SignatureSchemes::new());
+    }
+
+    fetch_signature_modes(hashes, db_connection)?
+        .into_iter()
+        .map(|row| {
+            SignatureScheme::from_mode(&row.mode, &row.typed_data)
+                .map(|scheme| (row.verification_hash, scheme))
+        })
+        .collect()
+}
+
+/// The recorded schemes of every signature of a document of either protocol version, upgraded
+/// v1.3 signatures keep the scheme recorded under their v1.2 hash.
+pub fn load_document_signature_schemes(
+    document: &AquaDocument,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<SignatureSchemes, String> {
+    match document {
+        AquaDocument::V1_2(doc) => match doc.pages.first() {
+            Some(chain) => load_signature_schemes(chain, db_connection),
+            None => Ok(SignatureSchemes::new()),
+        },
+        AquaDocument::V1_3(chain) => {
+            load_signature_schemes_for(&chain.legacy_signature_hashes(), db_connection)
+        }
+    }
+}
+
+/// The EIP-712 entries of `schemes`, `personal_sign` needs none.
+pub fn export_signature_modes(schemes: &SignatureSchemes) -> ExportedSignatureModes {
+    schemes
+        .iter()
+        .filter_map(|(hash, scheme)| match scheme {
+            SignatureScheme::Eip712(data) => Some((hash.clone(), data.clone())),
+            SignatureScheme::PersonalSign => None,
+        })
+        .collect()
+}
+
+/// `schemes` completed with exported modes of signatures that have none recorded.
+pub fn with_exported_modes(
+    mut schemes: SignatureSchemes,
+    modes: &ExportedSignatureModes,
+) -> SignatureSchemes {
+    for (hash, data) in modes {
+        schemes
+            .entry(hash.clone())
+            .or_insert_with(|| SignatureScheme::Eip712(data.clone()));
+    }
+    schemes
+}
+
+/// Records exported modes that come with an imported document. Only signatures without a
+/// recorded mode that verify under the exported one are recorded, so an import cannot change
+/// how a stored signature is checked. Returns how many modes were recorded.
+pub async fn import_signature_modes(
+    document: &AquaDocument,
+    modes: &ExportedSignatureModes,
+    db_connection: &mut PooledConnection<ConnectionManager<DbConnection>>,
+) -> Result<usize, String> {
+    if modes.is_empty() {
+        return Ok(0);
+    }
+    let recorded = load_document_signature_schemes(document, db_connection)?;
+    let schemes = with_exported_modes(recorded.clone(), modes);
+
+    let verified: BTreeSet<String> = match document {
+        AquaDocument::V1_2(doc) => {
+            let chain = doc
+                .pages
+                .first()
+                .ok_or_else(|| "first chain not found".to_string())?;
+            let contract_signatures = confirm_contract_signatures(chain, &schemes).await?;
+            let context = SignatureContext {
+                schemes,
+                contract_signatures,
+            };
+            verify_hash_chain_with_context(chain, &context)
+                .revisions
+                .into_iter()
+                .filter(|report| report.valid)
+                .map(|report| report.verification_hash)
+                .collect()
+        }
+        AquaDocument::V1_3(chain) => v1_3::verify_chain(chain, &schemes)
+            .revisions
+            .iter()
+            .filter(|report| report.valid)
+            .filter_map(|report| chain.revisions.get(&report.verification_hash))
+            .filter_map(|revision| revision.field("legacy_verification_hash"))
+            .map(str::to_string)
+            .collect(),
+    };
+
+    let mut imported = 0;
+    for (hash, data) in modes {
+        if recorded.contains_key(hash) || !verified.contains(hash) {
+            continue;
+        }
+        record_signature_scheme(hash, &SignatureScheme::Eip712(data.clone()), db_connection)?;
+        imported += 1;
+    }
+    Ok(imported)
+}
@@ -21,28 +21,45 @@ use std::collections::HashMap;
 use crate::models::file::FileDataInformation;
 use diesel::{r2d2, Connection};
 use diesel::prelude::*;
-use diesel::sqlite::SqliteConnection;
+use crate::db::DbConnection;
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
 
 
 const MIGRATIONS: EmbeddedMigrations = embed_migrations!();
-type DB = diesel::sqlite::Sqlite;
-
-pub fn run_db_migrations(conn: &mut impl MigrationHarness<DB>) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
-    conn.run_pending_migrations(MIGRATIONS)?;
+const POSTGRES_MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations_postgres");
+
+/// Runs the migrations written for the backend `conn` is connected to.
+pub fn run_db_migrations(conn: &mut DbConnection) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    match conn {
+        DbConnection::Postgres(conn) => conn.run_pending_migrations(POSTGRES_MIGRATIONS)?,
+        DbConnection::Sqlite(conn) => conn.run_pending_migrations(MIGRATIONS)?,
+    };
     Ok(())
 }
 
-pub fn establish_connection() ->  r2d2::Pool<ConnectionManager<SqliteConnection>> {
-    let database_url = std::env::var("DATABASE_URL")
-        .expect("DATABASE_URL must be set");
-    
-        println!("Database url {}",database_url );
-    let manager = ConnectionManager::<SqliteConnection>::new(database_url);
-    
-    r2d2::Pool::builder()
+pub fn establish_connection() ->  r2d2::Pool<ConnectionManager<DbConnection>> {
+    let database_url = std::env::var("DATABASE_URL").unwrap_or_else(|_| {
+        tracing::warn!("DATABASE_URL not set, using pages.db");
+        "pages.db".to_string()
+    });
+    let manager = ConnectionManager::<DbConnection>::new(database_url);
+
+    let pool = r2d2::Pool::builder()
         .build(manager)
-        .expect("Failed to create database pool")
+        .expect("Failed to create database pool");
+
+    // named after the connection, the URL carries the password
+    match pool.get() {
+        Ok(conn) => {
+            let backend = match &*conn {
+                DbConnection::Postgres(_) => "postgres",
+                DbConnection::Sqlite(_) => "sqlite",
+            };
+            tracing::info!("Database backend {}", backend);
+        }
+        Err(e) => tracing::error!("Failed to get database connection: {}", e),
+    }
+    pool
 }
 
 
@@ -0,0 +1,708 @@
+use crate::eip1271::contract_signature_valid;
+use crate::eip712::{
+    recover_typed_data_signer, typed_data_digest, RevisionTypedData, SIGNING_MODE_EIP712,
+    SIGNING_MODE_PERSONAL_SIGN,
+};
+use aqua_verifier::util::{
+    content_hash, metadata_hash, signature_hash, verification_hash, witness_hash,
+};
+use aqua_verifier_rs_types::models::hash::Hash;
+use aqua_verifier_rs_types::models::page_data::HashChain;
+use aqua_verifier_rs_types::models::public_key::PublicKey;
+use aqua_verifier_rs_types::models::revision::Revision;
+use aqua_verifier_rs_types::models::signature::{RevisionSignature, Signature};
+use aqua_verifier_rs_types::models::witness::RevisionWitness;
+use axum::http::StatusCode;
+use ethers_core::k256::ecdsa::VerifyingKey;
+use ethers_core::types::Address;
+use ethers_core::utils::{hash_message, public_key_to_address};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_512};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::str::FromStr;
+extern crate serde_json_path_to_error as serde_json;
+
+/// The individual checks run against every revision of a chain.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum VerificationCheck {
+    /// `previous_verification_hash` points at the revision before it.
+    ChainLinkage,
+    FileHash,
+    ContentHash,
+    MetadataHash,
+    SignatureHash,
+    /// The signature recovers to `wallet_address`.
+    SignatureSigner,
+    /// `public_key` belongs to `wallet_address`.
+    PublicKey,
+    /// `wallet_address` is a contract wallet that accepted the signature through EIP-1271,
+    /// checked instead of `SignatureSigner` and `PublicKey`.
+    ContractSignature,
+    /// EIP-712 signatures were made over this revision's file and predecessor.
+    TypedData,
+    /// v1.3 `leaves` hash the fields of the revision.
+    Leaves,
+    /// The linked chain is stored here, holds the linked revision and verifies.
+    LinkedChain,
+    WitnessHash,
+    WitnessEventVerificationHash,
+    MerkleProof,
+    VerificationHash,
+    /// An upgraded v1.3 revision follows the v1.2 revision of its predecessor and re-derives
+    /// its `legacy_verification_hash`.
+    LegacyVerificationHash,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CheckResult {
+    pub check: VerificationCheck,
+    pub passed: bool,
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RevisionKind {
+    Content,
+    Signature,
+    Witness,
+    /// v1.3 revision pointing at a revision of another chain.
+    Link,
+}
+
+impl RevisionKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RevisionKind::Content => "content",
+            RevisionKind::Signature => "signature",
+            RevisionKind::Witness => "witness",
+            RevisionKind::Link => "link",
+        }
+    }
+
+    pub fn of(revision: &Revision) -> Self {
+        if revision.witness.is_some() {
+            RevisionKind::Witness
+        } else if revision.signature.is_some() {
+            RevisionKind::Signature
+        } else {
+            RevisionKind::Content
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RevisionReport {
+    pub verification_hash: String,
+    pub kind: RevisionKind,
+    pub valid: bool,
+    pub checks: Vec<CheckResult>,
+    /// Address the signature recovers to, for signature revisions.
+    pub signer: Option<String>,
+    pub signing_mode: Option<String>,
+    pub witness_network: Option<String>,
+    pub witness_tx_hash: Option<String>,
+}
+
+impl RevisionReport {
+    pub(crate) fn new(verification_hash: String, kind: RevisionKind) -> Self {
+        RevisionReport {
+            verification_hash,
+            kind,
+            valid: false,
+            checks: Vec::new(),
+            signer: None,
+            signing_mode: None,
+            witness_network: None,
+            witness_tx_hash: None,
+        }
+    }
+
+    pub(crate) fn record(&mut self, check: VerificationCheck, passed: bool, failure: impl FnOnce() -> String) {
+        self.checks.push(CheckResult {
+            check,
+            passed,
+            message: if passed { None } else { Some(failure()) },
+        });
+    }
+}
+
+/// Result of verifying a whole chain, one entry per revision in chain order.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VerificationReport {
+    pub valid: bool,
+    pub revisions: Vec<RevisionReport>,
+}
+
+impl VerificationReport {
+    /// One line per failed check, used for the `logs` of api responses.
+    pub fn failures(&self) -> Vec<String> {
+        self.revisions
+            .iter()
+            .flat_map(|revision| {
+                revision.checks.iter().filter(|c| !c.passed).map(move |c| {
+                    format!(
+                        "revision {} failed {:?}: {}",
+                        revision.verification_hash,
+                        c.check,
+                        c.message.clone().unwrap_or_default()
+                    )
+                })
+            })
+            .collect()
+    }
+}
+
+/// How a signature revision was signed, recorded in `signature_modes`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SignatureScheme {
+    PersonalSign,
+    Eip712(RevisionTypedData),
+}
+
+impl SignatureScheme {
+    pub fn from_mode(mode: &str, typed_data: &str) -> Result<Self, String> {
+        match mode {
+            SIGNING_MODE_PERSONAL_SIGN => Ok(SignatureScheme::PersonalSign),
+            SIGNING_MODE_EIP712 => serde_json::from_str(typed_data)
+                .map(SignatureScheme::Eip712)
+                .map_err(|e| format!("Invalid typed data: {}", e)),
+            _ => Err(format!("Unknown signing mode {}", mode)),
+        }
+    }
+
+    pub fn mode(&self) -> &'static str {
+        match self {
+            SignatureScheme::PersonalSign => SIGNING_MODE_PERSONAL_SIGN,
+            SignatureScheme::Eip712(_) => SIGNING_MODE_EIP712,
+        }
+    }
+
+    /// Recovers who signed `previous_verification_hash` under this scheme.
+    pub fn recover(&self, signature: &Signature, previous_verification_hash: &Hash) -> Option<Address> {
+        self.recover_for(signature, &previous_verification_hash.to_string())
+    }
+
+    /// `recover` for a hash of any protocol version, with or without `0x`.
+    pub fn recover_for(&self, signature: &Signature, previous_verification_hash: &str) -> Option<Address> {
+        match self {
+            SignatureScheme::PersonalSign => {
+                let sig = ethers::types::Signature::from_str(&signature.to_string()).ok()?;
+                sig.recover(signature_message_for(previous_verification_hash))
+                    .ok()
+            }
+            SignatureScheme::Eip712(data) => recover_typed_data_signer(signature, data),
+        }
+    }
+
+    /// The digest signed under this scheme, what a contract wallet is asked to validate.
+    pub fn digest(&self, previous_verification_hash: &Hash) -> [u8; 32] {
+        self.digest_for(&previous_verification_hash.to_string())
+    }
+
+    /// `digest` for a hash of any protocol version, with or without `0x`.
+    pub fn digest_for(&self, previous_verification_hash: &str) -> [u8; 32] {
+        match self {
+            SignatureScheme::PersonalSign => {
+                hash_message(signature_message_for(previous_verification_hash)).0
+            }
+            SignatureScheme::Eip712(data) => typed_data_digest(data),
+        }
+    }
+}
+
+
+/// Signature schemes by the verification hash of their signature revision,
+/// revisions without an entry were signed with `personal_sign`.
+pub type SignatureSchemes = BTreeMap<String, SignatureScheme>;
+
+/// What verifying signatures needs beyond the chain itself.
+#[derive(Debug, Clone, Default)]
+pub struct SignatureContext {
+    pub schemes: SignatureSchemes,
+    /// Verification hashes of signature revisions a contract wallet accepted through EIP-1271.
+    pub contract_signatures: BTreeSet<String>,
+}
+
+/// The message MetaMask signs for a signature revision, see `aqua_chain_actions.tsx`.
+pub fn signature_message(previous_verification_hash: &Hash) -> String {
+    signature_message_for(&previous_verification_hash.to_string())
+}
+
+/// `signature_message` for a hash of any protocol version, with or without `0x`.
+pub fn signature_message_for(previous_verification_hash: &str) -> String {
+    format!(
+        "I sign the following page verification_hash: [0x{}]",
+        previous_verification_hash.trim_start_matches("0x")
+    )
+}
+
+pub fn sha3_hash(data: String) -> Hash {
+    let mut hasher = Sha3_512::default();
+    hasher.update(data);
+    Hash::from(hasher.finalize())
+}
+
+/// Recovers the signer of a revision signature, `None` if the signature is malformed.
+pub fn recover_signer(
+    signature: &RevisionSignature,
+    previous_verification_hash: &Hash,
+) -> Option<Address> {
+    recover_message_signer(&signature.signature, previous_verification_hash)
+}
+
+/// Recovers who signed `signature_message(previous_verification_hash)`.
+pub fn recover_message_signer(
+    signature: &Signature,
+    previous_verification_hash: &Hash,
+) -> Option<Address> {
+    let sig = ethers::types::Signature::from_str(&signature.to_string()).ok()?;
+    sig.recover(signature_message(previous_verification_hash)).ok()
+}
+
+/// Derives the wallet address of a hex encoded (compressed or uncompressed) public key.
+pub fn public_key_address(public_key: &str) -> Option<Address> {
+    let bytes = hex::decode(public_key.trim_start_matches("0x")).ok()?;
+    let key = VerifyingKey::from_sec1_bytes(&bytes).ok()?;
+    Some(public_key_to_address(&key))
+}
+
+pub(crate) fn same_address(address: &Address, wallet_address: &ethaddr::Address) -> bool {
+    format!("{:?}", address).eq_ignore_ascii_case(&wallet_address.to_string())
+}
+
+/// Why a submitted signature cannot become a signature revision.
+#[derive(Debug)]
+pub enum SignatureVerificationError {
+    MalformedSignature,
+    SignerMismatch {
+        wallet_address: String,
+        recovered: String,
+    },
+    InvalidPublicKey,
+    PublicKeyMismatch {
+        wallet_address: String,
+        key_address: String,
+    },
+    /// The wallet contract could not be asked about the signature.
+    WalletContract(String),
+}
+
+impl SignatureVerificationError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            SignatureVerificationError::MalformedSignature
+            | SignatureVerificationError::InvalidPublicKey => StatusCode::BAD_REQUEST,
+            SignatureVerificationError::SignerMismatch { .. }
+            | SignatureVerificationError::PublicKeyMismatch { .. } => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            SignatureVerificationError::WalletContract(_) => StatusCode::BAD_GATEWAY,
+        }
+    }
+}
+
+impl fmt::Display for SignatureVerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SignatureVerificationError::MalformedSignature => {
+                write!(f, "signature cannot be recovered")
+            }
+            SignatureVerificationError::SignerMismatch {
+                wallet_address,
+                recovered,
+            } => write!(
+                f,
+                "signature was made by {} instead of {}",
+                recovered, wallet_address
+            ),
+            SignatureVerificationError::InvalidPublicKey => {
+                write!(f, "public key is not a valid secp256k1 key")
+            }
+            SignatureVerificationError::PublicKeyMismatch {
+                wallet_address,
+                key_address,
+            } => write!(
+                f,
+                "public key belongs to {} instead of {}",
+                key_address, wallet_address
+            ),
+            SignatureVerificationError::WalletContract(e) => {
+                write!(f, "wallet contract could not be checked: {}", e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SignatureVerificationError {}
+
+/// Checks a signature before it is appended: it must recover to `wallet_address` over the
+/// message for `previous_verification_hash`, and `public_key` must belong to the same address.
+/// The hash may be of either protocol version, with or without `0x`.
+pub fn check_signature(
+    signature: &Signature,
+    public_key: &PublicKey,
+    wallet_address: &ethaddr::Address,
+    previous_verification_hash: &str,
+    scheme: &SignatureScheme,
+) -> Result<(), SignatureVerificationError> {
+    let recovered = scheme
+        .recover_for(signature, previous_verification_hash)
+        .ok_or(SignatureVerificationError::MalformedSignature)?;
+    if !same_address(&recovered, wallet_address) {
+        return Err(SignatureVerificationError::SignerMismatch {
+            wallet_address: wallet_address.to_string(),
+            recovered: format!("{:?}", recovered),
+        });
+    }
+
+    let key_address = public_key_address(&public_key.to_string())
+        .ok_or(SignatureVerificationError::InvalidPublicKey)?;
+    if !same_address(&key_address, wallet_address) {
+        return Err(SignatureVerificationError::PublicKeyMismatch {
+            wallet_address: wallet_address.to_string(),
+            key_address: format!("{:?}", key_address),
+        });
+    }
+
+    Ok(())
+}
+
+/// `check_signature` for wallets that may be smart contracts. A signature that does not
+/// recover to `wallet_address` is accepted if the wallet contract validates it through
+/// EIP-1271, `public_key` then belongs to one of its owners and is not checked.
+pub async fn check_wallet_signature(
+    signature: &Signature,
+    public_key: &PublicKey,
+    wallet_address: &ethaddr::Address,
+    previous_verification_hash: &str,
+    scheme: &SignatureScheme,
+) -> Result<(), SignatureVerificationError> {
+    let rejected = match check_signature(
+        signature,
+        public_key,
+        wallet_address,
+        previous_verification_hash,
+        scheme,
+    ) {
+        Ok(()) => return Ok(()),
+        Err(
+            e @ (SignatureVerificationError::MalformedSignature
+            | SignatureVerificationError::SignerMismatch { .. }),
+        ) => e,
+        Err(e) => return Err(e),
+    };
+
+    match contract_signature_valid(
+        Address::from(wallet_address.0),
+        scheme.digest_for(previous_verification_hash),
+        &signature_bytes(signature),
+    )
+    .await
+    {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(rejected),
+        Err(e) => Err(SignatureVerificationError::WalletContract(e)),
+    }
+}
+
+fn signature_bytes(signature: &Signature) -> Vec<u8> {
+    hex::decode(signature.to_string().trim_start_matches("0x")).unwrap_or_default()
+}
+
+/// Asks the wallet contracts of signatures that do not recover to their wallet address
+/// whether they accept them, see `check_wallet_signature`.
+pub async fn confirm_contract_signatures(
+    chain: &HashChain,
+    schemes: &SignatureSchemes,
+) -> Result<BTreeSet<String>, String> {
+    let mut confirmed = BTreeSet::new();
+    for (hash, revision) in &chain.revisions {
+        let (signature, previous) = match (
+            revision.signature.as_ref(),
+            revision.metadata.previous_verification_hash.as_ref(),
+        ) {
+            (Some(signature), Some(previous)) => (signature, previous),
+            _ => continue,
+        };
+        let scheme = schemes
+            .get(&hash.to_string())
+            .unwrap_or(&SignatureScheme::PersonalSign);
+
+        let recovered = scheme.recover(&signature.signature, previous);
+        if recovered.map_or(false, |signer| same_address(&signer, &signature.wallet_address)) {
+            continue;
+        }
+
+        if contract_signature_valid(
+            Address::from(signature.wallet_address.0),
+            scheme.digest(previous),
+            &signature_bytes(&signature.signature),
+        )
+        .await?
+        {
+            confirmed.insert(hash.to_string());
+        }
+    }
+    Ok(confirmed)
+}
+
+/// Re-derives every hash of a chain and reports each check per revision.
+pub fn verify_hash_chain(chain: &HashChain) -> VerificationReport {
+    verify_hash_chain_with_context(chain, &SignatureContext::default())
+}
+
+/// Like `verify_hash_chain`, recomputing signature digests with the recorded schemes and
+/// trusting the signatures contract wallets accepted.
+pub fn verify_hash_chain_with_context(
+    chain: &HashChain,
+    context: &SignatureContext,
+) -> VerificationReport {
+    let mut revisions = Vec::new();
+    let mut previous: Option<Hash> = None;
+
+    for (index, (hash, revision)) in chain.revisions.iter().enumerate() {
+        let expected_previous = if index == 0 { None } else { previous };
+        revisions.push(verify_revision(chain, hash, revision, expected_previous, context));
+        previous = Some(*hash);
+    }
+
+    if revisions.is_empty() {
+        return VerificationReport {
+            valid: false,
+            revisions,
+        };
+    }
+
+    VerificationReport {
+        valid: revisions.iter().all(|r| r.valid),
+        revisions,
+    }
+}
+
+fn verify_revision(
+    chain: &HashChain,
+    hash: &Hash,
+    revision: &Revision,
+    expected_previous: Option<Hash>,
+    context: &SignatureContext,
+) -> RevisionReport {
+    let mut report = RevisionReport::new(hash.to_string(), RevisionKind::of(revision));
+
+    let metadata = &revision.metadata;
+    let linked = match (expected_previous, metadata.previous_verification_hash) {
+        (None, None) => chain.genesis_hash == hash.to_string(),
+        (Some(expected), Some(actual)) => expected == actual,
+        _ => false,
+    };
+    report.record(VerificationCheck::ChainLinkage, linked, || {
+        format!(
+            "previous verification hash {:?} does not match {:?}",
+            metadata.previous_verification_hash, expected_previous
+        )
+    });
+
+    let file_hash = revision.content.content.file_hash;
+    if let Some(file) = &revision.content.file {
+        let mut hasher = Sha3_512::default();
+        hasher.update(file.data.clone());
+        let recomputed = Hash::from(hasher.finalize());
+        report.record(VerificationCheck::FileHash, recomputed == file_hash, || {
+            format!("file hash {} does not match file data", file_hash)
+        });
+    }
+
+    let mut content = BTreeMap::new();
+    content.insert("file_hash".to_owned(), file_hash.to_string());
+    let content_hash_current = content_hash(&content);
+    report.record(
+        VerificationCheck::ContentHash,
+        content_hash_current == revision.content.content_hash,
+        || format!("content hash {} is not valid", revision.content.content_hash),
+    );
+
+    let metadata_hash_current = metadata_hash(
+        &metadata.domain_id,
+        &metadata.time_stamp,
+        metadata.previous_verification_hash.as_ref(),
+    );
+    report.record(
+        VerificationCheck::MetadataHash,
+        metadata_hash_current == metadata.metadata_hash,
+        || format!("metadata hash {} is not valid", metadata.metadata_hash),
+    );
+
+    let signature_hash_current = revision.signature.as_ref().map(|signature| {
+        verify_signature(
+            &mut report,
+            &revision.content.content.file_hash.to_string(),
+            &chain.domain_id,
+            signature,
+            metadata.previous_verification_hash,
+            context
+                .schemes
+                .get(&hash.to_string())
+                .unwrap_or(&SignatureScheme::PersonalSign),
+            context.contract_signatures.contains(&hash.to_string()),
+        )
+    });
+
+    let witness_hash_current = revision.witness.as_ref().map(|witness| {
+        verify_witness(&mut report, witness, metadata.previous_verification_hash)
+    });
+
+    let verification_hash_current = verification_hash(
+        &revision.content.content_hash,
+        &metadata.metadata_hash,
+        signature_hash_current.as_ref(),
+        witness_hash_current.as_ref(),
+    );
+    report.record(
+        VerificationCheck::VerificationHash,
+        verification_hash_current == metadata.verification_hash
+            && metadata.verification_hash == *hash,
+        || format!("verification hash {} is not valid", hash),
+    );
+
+    report.valid = report.checks.iter().all(|check| check.passed);
+    report
+}
+
+/// Checks a v1.2 signature over `previous_verification_hash`, `file_hash` and `domain_id`
+/// describe the chain for EIP-712 typed data.
+pub(crate) fn verify_signature(
+    report: &mut RevisionReport,
+    file_hash: &str,
+    domain_id: &str,
+    signature: &RevisionSignature,
+    previous_verification_hash: Option<Hash>,
+    scheme: &SignatureScheme,
+    contract_signed: bool,
+) -> Hash {
+    report.signing_mode = Some(scheme.mode().to_string());
+    if let SignatureScheme::Eip712(data) = scheme {
+        let matches = previous_verification_hash
+            .map(|previous| data.verification_hash == previous.to_string())
+            .unwrap_or(false)
+            && data.file_hash == file_hash
+            && data.domain_id == domain_id;
+        report.record(VerificationCheck::TypedData, matches, || {
+            "typed data does not describe this revision".to_string()
+        });
+    }
+
+    let signature_hash_current = signature_hash(&signature.signature, &signature.public_key);
+    report.record(
+        VerificationCheck::SignatureHash,
+        signature_hash_current == signature.signature_hash,
+        || format!("signature hash {} is not valid", signature.signature_hash),
+    );
+
+    let signer = previous_verification_hash
+        .as_ref()
+        .and_then(|previous| scheme.recover(&signature.signature, previous));
+    report.signer = signer.map(|signer| format!("{:?}", signer));
+
+    // the signature was made by an owner of the contract wallet, not by the wallet itself
+    if contract_signed {
+        report.signer = Some(signature.wallet_address.to_string());
+        report.record(VerificationCheck::ContractSignature, true, String::new);
+        return signature.signature_hash;
+    }
+
+    report.record(
+        VerificationCheck::SignatureSigner,
+        signer
+            .map(|signer| same_address(&signer, &signature.wallet_address))
+            .unwrap_or(false),
+        || {
+            format!(
+                "signature recovers to {:?} instead of {}",
+                signer, signature.wallet_address
+            )
+        },
+    );
+
+    let key_address = public_key_address(&signature.public_key.to_string());
+    report.record(
+        VerificationCheck::PublicKey,
+        key_address
+            .map(|address| same_address(&address, &signature.wallet_address))
+            .unwrap_or(false),
+        || format!("public key does not belong to {}", signature.wallet_address),
+    );
+
+    signature.signature_hash
+}
+
+pub(crate) fn verify_witness(
+    report: &mut RevisionReport,
+    witness: &RevisionWitness,
+    previous_verification_hash: Option<Hash>,
+) -> Hash {
+    report.witness_network = Some(witness.witness_network.clone());
+    report.witness_tx_hash = Some(witness.witness_event_transaction_hash.to_string());
+
+    let witness_hash_current = witness_hash(
+        &witness.domain_snapshot_genesis_hash,
+        &witness.merkle_root,
+        &witness.witness_network,
+        &witness.witness_event_transaction_hash,
+    );
+    report.record(
+        VerificationCheck::WitnessHash,
+        witness_hash_current == witness.witness_hash,
+        || format!("witness hash {} is not valid", witness.witness_hash),
+    );
+
+    let event_hash = sha3_hash(format!(
+        "{}{}",
+        witness.domain_snapshot_genesis_hash, witness.merkle_root
+    ));
+    report.record(
+        VerificationCheck::WitnessEventVerificationHash,
+        event_hash == witness.witness_event_verification_hash,
+        || {
+            format!(
+                "witness event verification hash {} is not valid",
+                witness.witness_event_verification_hash
+            )
+        },
+    );
+
+    let proof = previous_verification_hash
+        .map(|leaf| verify_merkle_proof(witness, &leaf))
+        .unwrap_or(Err("witness revision has no previous revision".to_string()));
+    report.record(VerificationCheck::MerkleProof, proof.is_ok(), || {
+        proof.clone().err().unwrap_or_default()
+    });
+
+    witness.witness_hash
+}
+
+/// Walks the proof from `leaf` up to `merkle_root`.
+pub fn verify_merkle_proof(witness: &RevisionWitness, leaf: &Hash) -> Result<(), String> {
+    let proof = &witness.structured_merkle_proof;
+    let mut current = *leaf;
+
+    for node in proof {
+        if node.left_leaf != current && node.right_leaf != current {
+            return Err(format!("merkle node does not contain {}", current));
+        }
+        let successor = sha3_hash(format!("{}{}", node.left_leaf, node.right_leaf));
+        if successor != node.successor {
+            return Err(format!("merkle node successor {} is not valid", node.successor));
+        }
+        current = node.successor;
+    }
+
+    // single file witnesses store the witnessed hash itself as the root
+    let single_leaf = proof.len() == 1 && witness.merkle_root == *leaf;
+    if current == witness.merkle_root || single_leaf {
+        Ok(())
+    } else {
+        Err(format!("merkle root {} is not valid", witness.merkle_root))
+    }
+}
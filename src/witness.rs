@@ -0,0 +1,130 @@
+use crate::util::make_empty_hash;
+use crate::verification::sha3_hash;
+use aqua_verifier::util::{metadata_hash, verification_hash, witness_hash};
+use aqua_verifier_rs_types::models::hash::Hash;
+use aqua_verifier_rs_types::models::page_data::HashChain;
+use aqua_verifier_rs_types::models::timestamp::Timestamp;
+use aqua_verifier_rs_types::models::tx_hash::TxHash;
+use aqua_verifier_rs_types::models::witness::{MerkleNode, RevisionWitness};
+
+/// A Merkle tree over the latest verification hashes of several chains.
+/// `proofs[i]` walks from `leaves[i]` up to `root`, as checked by `verify_merkle_proof`.
+#[derive(Debug, Clone)]
+pub struct MerkleTree {
+    pub root: Hash,
+    pub leaves: Vec<Hash>,
+    pub proofs: Vec<Vec<MerkleNode>>,
+}
+
+fn merkle_node(left_leaf: Hash, right_leaf: Hash) -> MerkleNode {
+    MerkleNode {
+        left_leaf,
+        right_leaf,
+        successor: sha3_hash(format!("{}{}", left_leaf, right_leaf)),
+    }
+}
+
+/// Pairs the leaves level by level, an odd node out is paired with the empty hash like single
+/// file witnesses. Every leaf gets at least one node, so a batch of one still has a real root.
+pub fn build_merkle_tree(leaves: &[Hash]) -> Option<MerkleTree> {
+    if leaves.is_empty() {
+        return None;
+    }
+
+    let mut proofs: Vec<Vec<MerkleNode>> = vec![Vec::new(); leaves.len()];
+    let mut positions: Vec<usize> = (0..leaves.len()).collect();
+    let mut level: Vec<Hash> = leaves.to_vec();
+
+    loop {
+        let nodes: Vec<MerkleNode> = level
+            .chunks(2)
+            .map(|pair| merkle_node(pair[0], pair.get(1).copied().unwrap_or_else(make_empty_hash)))
+            .collect();
+
+        for (proof, position) in proofs.iter_mut().zip(positions.iter_mut()) {
+            *position /= 2;
+            proof.push(nodes[*position].clone());
+        }
+
+        level = nodes.iter().map(|node| node.successor).collect();
+        if level.len() == 1 {
+            break;
+        }
+    }
+
+    Some(MerkleTree {
+        root: level[0],
+        leaves: leaves.to_vec(),
+        proofs,
+    })
+}
+
+/// The value published on chain for a witness, see `verify_witness`.
+pub fn witness_event_verification_hash(
+    domain_snapshot_genesis_hash: &Hash,
+    merkle_root: &Hash,
+) -> Hash {
+    sha3_hash(format!("{}{}", domain_snapshot_genesis_hash, merkle_root))
+}
+
+pub fn latest_verification_hash(chain: &HashChain) -> Option<Hash> {
+    chain.revisions.last().map(|(hash, _)| *hash)
+}
+
+/// Appends a witness revision for `merkle_root` to the chain and returns its verification hash.
+/// The chain's latest revision must be the leaf `structured_merkle_proof` starts from.
+pub fn append_witness_revision(
+    chain: &mut HashChain,
+    merkle_root: Hash,
+    structured_merkle_proof: Vec<MerkleNode>,
+    network: &str,
+    tx_hash: TxHash,
+) -> Result<Hash, String> {
+    let (previous_hash, previous) = chain
+        .revisions
+        .last()
+        .cloned()
+        .ok_or_else(|| "chain has no revisions".to_string())?;
+
+    let domain_snapshot_genesis_hash = make_empty_hash();
+    let witness_hash_current = witness_hash(
+        &domain_snapshot_genesis_hash,
+        &merkle_root,
+        network,
+        &tx_hash,
+    );
+
+    let mut revision = previous;
+    revision.signature = None;
+    revision.content.file = None;
+    revision.witness = Some(RevisionWitness {
+        domain_snapshot_genesis_hash,
+        merkle_root,
+        witness_network: network.to_string(),
+        witness_event_transaction_hash: tx_hash,
+        witness_event_verification_hash: witness_event_verification_hash(
+            &domain_snapshot_genesis_hash,
+            &merkle_root,
+        ),
+        witness_hash: witness_hash_current,
+        structured_merkle_proof,
+    });
+
+    let timestamp_current = Timestamp::from(chrono::Utc::now().naive_utc());
+    let metadata_hash_current =
+        metadata_hash(&chain.domain_id, &timestamp_current, Some(&previous_hash));
+    let verification_hash_current = verification_hash(
+        &revision.content.content_hash,
+        &metadata_hash_current,
+        None,
+        Some(&witness_hash_current),
+    );
+
+    revision.metadata.previous_verification_hash = Some(previous_hash);
+    revision.metadata.time_stamp = timestamp_current;
+    revision.metadata.metadata_hash = metadata_hash_current;
+    revision.metadata.verification_hash = verification_hash_current;
+
+    chain.revisions.push((verification_hash_current, revision));
+    Ok(verification_hash_current)
+}
@@ -0,0 +1,340 @@
+use crate::models::UserProfilesTable;
+use crate::networks::WitnessNetwork;
+use aqua_verifier_rs_types::models::tx_hash::TxHash;
+use axum::http::StatusCode;
+use ethers::providers::{Http, Middleware, Provider};
+use ethers::types::{Address, H256, U256, U64};
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+
+/// Selector of the witness contract function called by the frontend, see `aqua_chain_actions.tsx`.
+pub const WITNESS_FUNCTION_SELECTOR: [u8; 4] = [0x9c, 0xef, 0x4e, 0xa1];
+
+#[derive(Debug)]
+pub enum WitnessConfirmationError {
+    NoRpcUrl(String),
+    InvalidInput(String),
+    Rpc(String),
+    WrongChain { network: String, expected: u64, actual: String },
+    TransactionNotFound(String),
+    /// The transaction exists but is not mined yet, the request can be retried later.
+    TransactionPending(String),
+    TransactionFailed(String),
+    WrongContract { expected: String, actual: Option<String> },
+    WrongCalldata(String),
+}
+
+impl WitnessConfirmationError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            WitnessConfirmationError::NoRpcUrl(_) | WitnessConfirmationError::InvalidInput(_) => {
+                StatusCode::BAD_REQUEST
+            }
+            WitnessConfirmationError::Rpc(_) | WitnessConfirmationError::WrongChain { .. } => {
+                StatusCode::BAD_GATEWAY
+            }
+            WitnessConfirmationError::TransactionPending(_) => StatusCode::SERVICE_UNAVAILABLE,
+            WitnessConfirmationError::TransactionNotFound(_)
+            | WitnessConfirmationError::TransactionFailed(_)
+            | WitnessConfirmationError::WrongContract { .. }
+            | WitnessConfirmationError::WrongCalldata(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+}
+
+impl fmt::Display for WitnessConfirmationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WitnessConfirmationError::NoRpcUrl(network) => {
+                write!(f, "No JSON-RPC endpoint configured for network {}", network)
+            }
+            WitnessConfirmationError::InvalidInput(e) => write!(f, "{}", e),
+            WitnessConfirmationError::Rpc(e) => write!(f, "JSON-RPC request failed: {}", e),
+            WitnessConfirmationError::WrongChain {
+                network,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "JSON-RPC endpoint of {} serves chain {} instead of {}",
+                network, actual, expected
+            ),
+            WitnessConfirmationError::TransactionNotFound(tx) => {
+                write!(f, "Transaction {} not found", tx)
+            }
+            WitnessConfirmationError::TransactionPending(tx) => {
+                write!(f, "Transaction {} is not mined yet, retry once it is", tx)
+            }
+            WitnessConfirmationError::TransactionFailed(tx) => {
+                write!(f, "Transaction {} was reverted", tx)
+            }
+            WitnessConfirmationError::WrongContract { expected, actual } => write!(
+                f,
+                "Transaction was sent to {} instead of the witness contract {}",
+                actual.as_deref().unwrap_or("no contract"),
+                expected
+            ),
+            WitnessConfirmationError::WrongCalldata(tx) => write!(
+                f,
+                "Transaction {} does not carry the witnessed hash",
+                tx
+            ),
+        }
+    }
+}
+
+impl std::error::Error for WitnessConfirmationError {}
+
+fn expected_calldata(published_hash: &str) -> Result<Vec<u8>, WitnessConfirmationError> {
+    let hash = hex::decode(published_hash.trim_start_matches("0x")).map_err(|e| {
+        WitnessConfirmationError::InvalidInput(format!("Invalid witness event hash: {}", e))
+    })?;
+    Ok([WITNESS_FUNCTION_SELECTOR.as_slice(), hash.as_slice()].concat())
+}
+
+/// Confirms that `tx_hash` exists on `network`, was sent to `contract_address`
+/// and calls the witness function with `published_hash`: the witness event verification hash
+/// of a v1.2 witness or the merkle root of a v1.3 one.
+/// The transaction must be mined and must not have reverted.
+pub async fn confirm_witness_transaction(
+    network: &WitnessNetwork,
+    rpc_url: &str,
+    tx_hash: &TxHash,
+    contract_address: &str,
+    published_hash: &str,
+) -> Result<(), WitnessConfirmationError> {
+    let tx_hex = format!("0x{}", tx_hash.to_string().trim_start_matches("0x"));
+    let tx_id = H256::from_str(&tx_hex).map_err(|e| {
+        WitnessConfirmationError::InvalidInput(format!("Invalid transaction hash: {}", e))
+    })?;
+    let contract = Address::from_str(contract_address.trim()).map_err(|e| {
+        WitnessConfirmationError::InvalidInput(format!(
+            "Invalid witness contract address {}: {}",
+            contract_address, e
+        ))
+    })?;
+    let calldata = expected_calldata(published_hash)?;
+
+    let provider = Provider::<Http>::try_from(rpc_url)
+        .map_err(|e| WitnessConfirmationError::Rpc(e.to_string()))?;
+
+    let chain_id = provider
+        .get_chainid()
+        .await
+        .map_err(|e| WitnessConfirmationError::Rpc(e.to_string()))?;
+    if chain_id != U256::from(network.chain_id) {
+        return Err(WitnessConfirmationError::WrongChain {
+            network: network.name.clone(),
+            expected: network.chain_id,
+            actual: chain_id.to_string(),
+        });
+    }
+
+    let transaction = provider
+        .get_transaction(tx_id)
+        .await
+        .map_err(|e| WitnessConfirmationError::Rpc(e.to_string()))?
+        .ok_or_else(|| WitnessConfirmationError::TransactionNotFound(tx_hex.clone()))?;
+
+    if transaction.to != Some(contract) {
+        return Err(WitnessConfirmationError::WrongContract {
+            expected: format!("{:?}", contract),
+            actual: transaction.to.map(|to| format!("{:?}", to)),
+        });
+    }
+
+    if !transaction.input.as_ref().starts_with(&calldata) {
+        return Err(WitnessConfirmationError::WrongCalldata(tx_hex));
+    }
+
+    let receipt = provider
+        .get_transaction_receipt(tx_id)
+        .await
+        .map_err(|e| WitnessConfirmationError::Rpc(e.to_string()))?
+        .ok_or_else(|| WitnessConfirmationError::TransactionPending(tx_hex.clone()))?;
+    if receipt.status != Some(U64::one()) {
+        return Err(WitnessConfirmationError::TransactionFailed(tx_hex));
+    }
+
+    Ok(())
+}
+
+/// The witness contract of the network, else the caller's profile contract, else `CONTRACT_ADDRESS`.
+pub fn witness_contract_address(
+    network: &WitnessNetwork,
+    profile: Option<&UserProfilesTable>,
+) -> String {
+    network
+        .contract_address
+        .clone()
+        .or_else(|| profile.map(|profile| profile.contract_address.clone()))
+        .filter(|contract| !contract.trim().is_empty())
+        .unwrap_or_else(|| env::var("CONTRACT_ADDRESS").unwrap_or_default())
+}
+
+/// Confirms the witness transaction against the JSON-RPC endpoint of `network`.
+pub async fn confirm_witness(
+    network: &WitnessNetwork,
+    tx_hash: &TxHash,
+    contract_address: &str,
+    published_hash: &str,
+) -> Result<(), WitnessConfirmationError> {
+    let rpc_url = network
+        .rpc_url
+        .as_deref()
+        .ok_or_else(|| WitnessConfirmationError::NoRpcUrl(network.name.clone()))?;
+    confirm_witness_transaction(
+        network,
+        rpc_url,
+        tx_hash,
+        contract_address,
+        published_hash,
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::core::utils::{Anvil, AnvilInstance};
+    use ethers::middleware::SignerMiddleware;
+    use ethers::signers::{LocalWallet, Signer};
+    use ethers::types::{Bytes, TransactionRequest};
+
+    const CHAIN_ID: u64 = 31337;
+    const PUBLISHED_HASH: &str = "0x5b2c3b1a9f0e4d7c6b5a49382716f5e4d3c2b1a09f8e7d6c5b4a39281706f5e4";
+    /// Deploys a contract whose every call reverts.
+    const REVERTING_INIT_CODE: &str = "6005600c60003960056000f360006000fd";
+
+    fn network() -> WitnessNetwork {
+        WitnessNetwork {
+            name: "anvil".to_string(),
+            chain_id: CHAIN_ID,
+            rpc_url: None,
+            contract_address: None,
+        }
+    }
+
+    fn client(anvil: &AnvilInstance) -> SignerMiddleware<Provider<Http>, LocalWallet> {
+        let provider = Provider::<Http>::try_from(anvil.endpoint()).unwrap();
+        let wallet = LocalWallet::from(anvil.keys()[0].clone()).with_chain_id(CHAIN_ID);
+        SignerMiddleware::new(provider, wallet)
+    }
+
+    /// Sends a witness call to `to` without waiting for it to be mined.
+    async fn send_witness(
+        client: &SignerMiddleware<Provider<Http>, LocalWallet>,
+        to: Address,
+    ) -> TxHash {
+        let request = TransactionRequest::new()
+            .to(to)
+            .data(Bytes::from(expected_calldata(PUBLISHED_HASH).unwrap()))
+            .gas(100_000);
+        let pending = client.send_transaction(request, None).await.unwrap();
+        format!("{:?}", pending.tx_hash()).parse::<TxHash>().unwrap()
+    }
+
+    async fn mine(anvil: &AnvilInstance) {
+        let provider = Provider::<Http>::try_from(anvil.endpoint()).unwrap();
+        provider.request::<_, String>("evm_mine", ()).await.unwrap();
+    }
+
+    // needs `anvil` on the PATH, run with `cargo test -- --ignored`
+    #[tokio::test]
+    #[ignore]
+    async fn confirms_mined_witness_transaction() {
+        let anvil = Anvil::new().chain_id(CHAIN_ID).spawn();
+        let contract = Address::from_low_u64_be(0xa1);
+        let tx_hash = send_witness(&client(&anvil), contract).await;
+
+        let confirmed = confirm_witness_transaction(
+            &network(),
+            &anvil.endpoint(),
+            &tx_hash,
+            &format!("{:?}", contract),
+            PUBLISHED_HASH,
+        )
+        .await;
+        assert!(confirmed.is_ok(), "{:?}", confirmed);
+
+        let other_contract = confirm_witness_transaction(
+            &network(),
+            &anvil.endpoint(),
+            &tx_hash,
+            &format!("{:?}", Address::from_low_u64_be(0xa2)),
+            PUBLISHED_HASH,
+        )
+        .await;
+        assert!(matches!(
+            other_contract,
+            Err(WitnessConfirmationError::WrongContract { .. })
+        ));
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn pending_witness_transaction_is_retryable() {
+        let anvil = Anvil::new().chain_id(CHAIN_ID).arg("--no-mining").spawn();
+        let contract = Address::from_low_u64_be(0xa1);
+        let tx_hash = send_witness(&client(&anvil), contract).await;
+
+        let pending = confirm_witness_transaction(
+            &network(),
+            &anvil.endpoint(),
+            &tx_hash,
+            &format!("{:?}", contract),
+            PUBLISHED_HASH,
+        )
+        .await;
+        match pending {
+            Err(e @ WitnessConfirmationError::TransactionPending(_)) => {
+                assert_eq!(e.status_code(), StatusCode::SERVICE_UNAVAILABLE)
+            }
+            other => panic!("expected a pending transaction, got {:?}", other),
+        }
+
+        mine(&anvil).await;
+        let mined = confirm_witness_transaction(
+            &network(),
+            &anvil.endpoint(),
+            &tx_hash,
+            &format!("{:?}", contract),
+            PUBLISHED_HASH,
+        )
+        .await;
+        assert!(mined.is_ok(), "{:?}", mined);
+    }
+
+    #[tokio::test]
+    #[ignore]
+    async fn reverted_witness_transaction_is_rejected() {
+        let anvil = Anvil::new().chain_id(CHAIN_ID).spawn();
+        let client = client(&anvil);
+        let deploy = TransactionRequest::new()
+            .data(Bytes::from(hex::decode(REVERTING_INIT_CODE).unwrap()))
+            .gas(100_000);
+        let contract = client
+            .send_transaction(deploy, None)
+            .await
+            .unwrap()
+            .await
+            .unwrap()
+            .and_then(|receipt| receipt.contract_address)
+            .unwrap();
+        let tx_hash = send_witness(&client, contract).await;
+
+        let reverted = confirm_witness_transaction(
+            &network(),
+            &anvil.endpoint(),
+            &tx_hash,
+            &format!("{:?}", contract),
+            PUBLISHED_HASH,
+        )
+        .await;
+        assert!(matches!(
+            reverted,
+            Err(WitnessConfirmationError::TransactionFailed(_))
+        ));
+    }
+}
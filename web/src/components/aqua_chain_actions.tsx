@@ -1,8 +1,8 @@
 import { LuDelete, LuDownload, LuGlasses, LuShare2, LuSignature } from "react-icons/lu"
 import { Button } from "./ui/button"
 import { ethers } from "ethers"
-import { getCurrentNetwork, switchNetwork } from "../utils/functions"
-import { ETH_CHAIN_ADDRESSES_MAP, ETH_CHAINID_MAP } from "../utils/constants"
+import { getCookie, getCurrentNetwork, switchNetwork, withFileData } from "../utils/functions"
+import { ETH_CHAIN_ADDRESSES_MAP, ETH_CHAINID_MAP, SESSION_COOKIE_NAME } from "../utils/constants"
 import { useStore } from "zustand"
 import appStore from "../store"
 import axios, { AxiosError } from "axios"
@@ -18,6 +18,29 @@ import { Box, Center, Text, VStack } from "@chakra-ui/react"
 import { ClipboardButton, ClipboardIconButton, ClipboardInput, ClipboardLabel, ClipboardRoot } from "./ui/clipboard"
 import { InputGroup } from "./ui/input-group"
 
+// The backend only accepts mined witness transactions and answers 503 while they are pending
+const WITNESS_RETRY_DELAY_MS = 5000
+const WITNESS_MAX_ATTEMPTS = 60
+
+async function postWitnessTx(url: string, formData: URLSearchParams) {
+    for (let attempt = 1; ; attempt++) {
+        try {
+            return await axios.post(url, formData, {
+                headers: {
+                    'Content-Type': 'application/x-www-form-urlencoded',
+                    'nonce': getCookie(SESSION_COOKIE_NAME) ?? ''
+                }
+            })
+        } catch (error) {
+            const pending = axios.isAxiosError(error) && error.response?.status === 503
+            if (!pending || attempt >= WITNESS_MAX_ATTEMPTS) {
+                throw error
+            }
+            await new Promise(resolve => setTimeout(resolve, WITNESS_RETRY_DELAY_MS))
+        }
+    }
+}
+
 async function storeWitnessTx(file_id: number, filename: string, txhash: string, ownerAddress: string, network: string, files: ApiFileInfo[], setFiles: any, backend_url: string) {
 
     const formData = new URLSearchParams();
@@ -30,11 +53,7 @@ async function storeWitnessTx(file_id: number, filename: string, txhash: string,
 
     const url = `${backend_url}/explorer_witness_file`
 
-    const response = await axios.post(url, formData, {
-        headers: {
-            'Content-Type': 'application/x-www-form-urlencoded'
-        }
-    })
+    const response = await postWitnessTx(url, formData)
 
     const res = await response.data;
     // let logs: Array<string> = res.logs
@@ -229,7 +248,8 @@ export const SignAquaChain = ({ file_id, filename, lastRevisionVerificationHash
                         const url = `${backend_url}/explorer_sign_revision`;
                         const response = await axios.post(url, formData, {
                             headers: {
-                                'Content-Type': 'application/x-www-form-urlencoded'
+                                'Content-Type': 'application/x-www-form-urlencoded',
+                                'nonce': getCookie(SESSION_COOKIE_NAME) ?? ''
                             }
                         });
 
@@ -307,7 +327,8 @@ export const DeleteAquaChain = ({ file_id, filename, backend_url }: ISigningAndW
         const url = `${backend_url}/explorer_delete_file`
         const response = await axios.post(url, formData, {
             headers: {
-                'Content-Type': 'application/x-www-form-urlencoded'
+                'Content-Type': 'application/x-www-form-urlencoded',
+                'nonce': getCookie(SESSION_COOKIE_NAME) ?? ''
             }
         });
 
@@ -339,12 +360,12 @@ export const DeleteAquaChain = ({ file_id, filename, backend_url }: ISigningAndW
 }
 
 export const DownloadAquaChain = ({ file }: { file: ApiFileInfo }) => {
+    const { backend_url } = useStore(appStore)
 
-
-    const downloadAquaJson = () => {
+    const downloadAquaJson = async () => {
         try {
-            // Parse the page_data string to a PageData object
-            const pageData: PageData = JSON.parse(file.page_data);
+            // Parse the page_data string to a PageData object, with the file bytes the listing leaves out
+            const pageData: PageData = JSON.parse((await withFileData(file, backend_url)).page_data);
 
             for (const page of pageData.pages) {
                 for (const revisionKey in page.revisions) {
@@ -386,6 +407,26 @@ export const DownloadAquaChain = ({ file }: { file: ApiFileInfo }) => {
             // Clean up
             document.body.removeChild(a);
             URL.revokeObjectURL(url);
+
+            // EIP-712 signatures only verify with their typed data, export it next to the chain
+            const modesResponse = await axios.get(`${backend_url}/files/${file.id}/signature_modes`, {
+                headers: {
+                    'nonce': getCookie(SESSION_COOKIE_NAME) ?? ''
+                }
+            });
+            const signatureModes = modesResponse.data.signature_modes ?? {};
+            if (Object.keys(signatureModes).length > 0) {
+                const modesBlob = new Blob([JSON.stringify(signatureModes, null, 2)], { type: 'application/json' });
+                const modesUrl = URL.createObjectURL(modesBlob);
+                const modesLink = document.createElement('a');
+                modesLink.href = modesUrl;
+                modesLink.download = `${file.name}-signature-modes.json`;
+                document.body.appendChild(modesLink);
+                modesLink.click();
+                document.body.removeChild(modesLink);
+                URL.revokeObjectURL(modesUrl);
+            }
+
             toaster.create({
                 description: `Aqua Chain Downloaded successfully`,
                 type: "success"
@@ -432,7 +473,8 @@ export const ShareButton = ({ filename, file_id }: IShareButton) => {
 
         const response = await axios.post(url, formData, {
             headers: {
-                'Content-Type': 'application/x-www-form-urlencoded'
+                'Content-Type': 'application/x-www-form-urlencoded',
+                'nonce': getCookie(SESSION_COOKIE_NAME) ?? ''
             }
         });
 
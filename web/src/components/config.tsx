@@ -4,6 +4,7 @@ import { fetchFiles, generateAvatar, getCookie } from "../utils/functions";
 import { useStore } from "zustand";
 import appStore from "../store";
 import { toaster } from "./ui/toaster";
+import { SESSION_COOKIE_NAME } from "../utils/constants";
 import { ethers } from "ethers";
 
 
@@ -32,9 +33,9 @@ const LoadConfiguration = () => {
                         setMetamaskAddress(address)
                         const avatar = generateAvatar(address)
                         setAvatar(avatar)
-                        const files = await fetchFiles(address, url2);
+                        const files = await fetchFiles(url2);
                         setFiles(files)
-                        fetchUserProfile(_address)
+                        fetchUserProfile()
                     }
                 } else {
                     setMetamaskAddress(null)
@@ -60,7 +61,7 @@ const LoadConfiguration = () => {
         }
     }
 
-    const fetchUserProfile = async (address: string) => {
+    const fetchUserProfile = async () => {
 
         const url = `${backend_url}/explorer_fetch_user_profile`;
         console.log("url is ", url);
@@ -68,7 +69,7 @@ const LoadConfiguration = () => {
         const response = await axios.get(url, {
             headers: {
                 'Content-Type': 'application/x-www-form-urlencoded',
-                'metamask_address': address
+                'nonce': getCookie(SESSION_COOKIE_NAME) ?? ''
             }
         });
 
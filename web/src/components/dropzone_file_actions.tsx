@@ -6,7 +6,8 @@ import appStore from "../store";
 import { useEffect, useState } from "react";
 import { ApiFileInfo } from "../models/FileInfo";
 import { toaster } from "./ui/toaster";
-import { formatCryptoAddress, readJsonFile } from "../utils/functions";
+import { formatCryptoAddress, getCookie, readJsonFile } from "../utils/functions";
+import { SESSION_COOKIE_NAME } from "../utils/constants";
 import { ChainDetailsBtn } from "./ui/navigation/CustomDrawer";
 import { Container, DialogCloseTrigger, Group, List, Text } from "@chakra-ui/react";
 import { Alert } from "./ui/alert";
@@ -64,7 +65,7 @@ export const UploadFile = ({ file, uploadedIndexes, fileIndex, updateUploadedInd
             const response = await axios.post(url, formData, {
                 headers: {
                     'Content-Type': 'multipart/form-data',
-                    "metamask_address": metamaskAddress
+                    "nonce": getCookie(SESSION_COOKIE_NAME) ?? ""
                 },
             });
 
@@ -195,7 +196,7 @@ export const ImportAquaChainFromFile = ({ file, uploadedIndexes, fileIndex, upda
             const response = await axios.post(url, formData, {
                 headers: {
                     'Content-Type': 'multipart/form-data',
-                    "metamask_address": metamaskAddress
+                    "nonce": getCookie(SESSION_COOKIE_NAME) ?? ""
                 },
             });
 
@@ -367,7 +368,7 @@ export const ImportAquaChainFromChain = ({ fileInfo, isVerificationSuccessful }:
             const response = await axios.post(url, formData, {
                 headers: {
                     'Content-Type': 'multipart/form-data',
-                    "metamask_address": metamaskAddress
+                    "nonce": getCookie(SESSION_COOKIE_NAME) ?? ""
                 },
             });
 
@@ -417,6 +418,10 @@ export const ImportAquaChainFromChain = ({ fileInfo, isVerificationSuccessful }:
                 file_id: existingFileId,
                 last_identical_revision_hash: lastIdenticalRevisionHash,
                 revisions_to_import: revisionsToImport
+            }, {
+                headers: {
+                    "nonce": getCookie(SESSION_COOKIE_NAME) ?? ""
+                }
             })
             if (response.status === 200) {
                 toaster.create({
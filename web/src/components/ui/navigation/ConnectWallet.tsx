@@ -5,7 +5,7 @@ import { Center, Dialog, Text, VStack } from "@chakra-ui/react";
 import { LuCircleCheck, LuCircleX, LuLogOut, LuWallet } from "react-icons/lu";
 import ReactLoading from "react-loading";
 import { fetchFiles, formatCryptoAddress, generateAvatar, getCookie, remove0xPrefix, setCookie } from "../../../utils/functions";
-import { SiweMessage, generateNonce } from "siwe";
+import { SiweMessage } from "siwe";
 import {  SESSION_COOKIE_NAME } from "../../../utils/constants";
 import axios from "axios";
 import { useStore } from "zustand";
@@ -31,7 +31,7 @@ export default function ConnectWallet() {
     setProgress(0);
   };
 
-  function createSiweMessage(address: string, statement: string) {
+  function createSiweMessage(address: string, statement: string, nonce: string) {
     // const scheme = window.location.protocol.slice(0, -1);
     const domain = window.location.host;
     const origin = window.location.origin;
@@ -45,7 +45,7 @@ export default function ConnectWallet() {
       uri: origin,
       version: "1",
       chainId: 2,
-      nonce: generateNonce(),
+      nonce,
       expirationTime: expiry,
       issuedAt: new Date(Date.now()).toISOString(),
     });
@@ -62,9 +62,13 @@ export default function ConnectWallet() {
         await window.ethereum.request({ method: "eth_requestAccounts" });
         const signer = await provider.getSigner();
 
+        // The server only accepts nonces it issued itself
+        const nonceResponse = await axios.get(`${backend_url}/siwe_nonce`);
+        const nonce: string = nonceResponse.data.nonce;
+
         // Create a SIWE msg for signing
         const domain = window.location.host;
-        const message = createSiweMessage(signer.address, "Sign in with Ethereum to the app.");
+        const message = createSiweMessage(signer.address, "Sign in with Ethereum to the app.", nonce);
 
         const signature = await signer.signMessage(message);
 
@@ -103,7 +107,7 @@ export default function ConnectWallet() {
             const url = `${backend_url}/explorer_files`;
             console.log("url is ", url);
             
-            let files = await fetchFiles(walletAddress, url);
+            let files = await fetchFiles(url);
             setFiles(files);
           }
         }
@@ -16,7 +16,7 @@ import { LuCheck, LuChevronDown, LuChevronUp, LuExternalLink, LuEye, LuX } from
 import { Box, Card, Collapsible, For, Group, Icon, IconButton, Link, Spacer, Span, Text, VStack } from "@chakra-ui/react"
 import { TimelineConnector, TimelineContent, TimelineDescription, TimelineItem, TimelineRoot, TimelineTitle } from "../timeline"
 import { PageData, Revision } from "../../../models/PageData"
-import { formatCryptoAddress, getLastRevisionVerificationHash, timeToHumanFriendly } from "../../../utils/functions"
+import { formatCryptoAddress, getLastRevisionVerificationHash, timeToHumanFriendly, withFileData } from "../../../utils/functions"
 import { Alert } from "../alert"
 import { ClipboardIconButton, ClipboardRoot } from "../clipboard"
 import AquaVerifier, { RevisionAquaChainResult, RevisionVerificationResult } from "aqua-verifier";
@@ -460,6 +460,7 @@ export const ChainDetailsBtn = ({ fileInfo }: IPageDataDetails) => {
     const pageData: PageData = JSON.parse(fileInfo.page_data)
     const [isVerificationSuccessful, setIsVerificationSuccessful] = useState<boolean>(false)
     const [lastVerificationHash, setLastVerificationHash] = useState<string | null>(null)
+    const [fileWithData, setFileWithData] = useState<ApiFileInfo>(fileInfo)
 
 
     const updateVerificationStatus = (result: boolean) => {
@@ -472,6 +473,14 @@ export const ChainDetailsBtn = ({ fileInfo }: IPageDataDetails) => {
         console.log("ChainDetailsBtn == > " + JSON.stringify(fileInfo))
     }, [fileInfo])
 
+    useEffect(() => {
+        if (isOpen) {
+            withFileData(fileInfo, backend_url)
+                .then(setFileWithData)
+                .catch((error: any) => console.error("Failed to fetch file content: ", error))
+        }
+    }, [isOpen, fileInfo])
+
     useEffect(() => {
         if (isOpen) {
             const modalElement = document.getElementById('aqua-chain-details-modal');
@@ -506,7 +515,7 @@ export const ChainDetailsBtn = ({ fileInfo }: IPageDataDetails) => {
                     <DrawerBody py={'lg'} px={1}>
                         <Card.Root border={'none'} shadow={'md'} borderRadius={'xl'}>
                             <Card.Body>
-                                <FilePreview fileInfo={fileInfo} />
+                                <FilePreview fileInfo={fileWithData} />
                             </Card.Body>
                         </Card.Root>
                         <Spacer height={'20px'} />
@@ -523,7 +532,7 @@ export const ChainDetailsBtn = ({ fileInfo }: IPageDataDetails) => {
                                                 <Alert w={'100%'} status={"info"} textAlign={'start'} title={showMoreDetails ? `Show less Details` : `Show more Details`} icon={showMoreDetails ? <LuChevronUp /> : <LuChevronDown />} />
                                             </Collapsible.Trigger>
                                             <Collapsible.Content py={'4'}>
-                                                <ChainDetails fileInfo={fileInfo} callBack={updateVerificationStatus} />
+                                                <ChainDetails fileInfo={fileWithData} callBack={updateVerificationStatus} />
                                             </Collapsible.Content>
                                         </Collapsible.Root>
                                     </Box>
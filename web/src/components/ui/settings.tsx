@@ -10,6 +10,8 @@ import { useStore } from "zustand"
 import appStore from "../../store"
 import { toaster } from "./toaster"
 import { Button } from "./button"
+import { getCookie } from "../../utils/functions"
+import { SESSION_COOKIE_NAME } from "../../utils/constants"
 
 const networks = createListCollection({
     items: [
@@ -48,6 +50,7 @@ const SettingsForm = () => {
         const response = await axios.post(url, formData, {
             headers: {
                 // 'Content-Type': 'application/x-www-form-urlencoded'
+                'nonce': getCookie(SESSION_COOKIE_NAME) ?? ''
             }
         });
 
@@ -121,7 +124,7 @@ const SettingsForm = () => {
 
 const DeleteFiles = () => {
     const [deleting, setDeleting] = useState(false)
-    const { setFiles, backend_url, metamaskAddress } = useStore(appStore)
+    const { setFiles, backend_url } = useStore(appStore)
 
     const deleteFile = async () => {
         try {
@@ -131,7 +134,7 @@ const DeleteFiles = () => {
             const response = await axios.get(url, {
                 headers: {
                     'Content-Type': 'application/x-www-form-urlencoded',
-                    'metamask_address':metamaskAddress ?? ''
+                    'nonce': getCookie(SESSION_COOKIE_NAME) ?? ''
                 }
             });
 
@@ -12,6 +12,8 @@ import FilePreview from '../components/FilePreview'
 import { ImportAquaChainFromChain } from '../components/dropzone_file_actions'
 import { Alert } from '../components/ui/alert'
 import { LuChevronUp, LuChevronDown } from 'react-icons/lu'
+import { getCookie } from '../utils/functions'
+import { SESSION_COOKIE_NAME } from '../utils/constants'
 
 const SharePage = () => {
     const { backend_url, metamaskAddress } = useStore(appStore)
@@ -32,7 +34,8 @@ const SharePage = () => {
                 console.log("url is ", url)
                 const response = await axios.get(url, {
                     headers: {
-                        'Content-Type': 'application/x-www-form-urlencoded'
+                        'Content-Type': 'application/x-www-form-urlencoded',
+                        'nonce': getCookie(SESSION_COOKIE_NAME) ?? ''
                     }
                 });
                 console.log(response)
@@ -1,7 +1,7 @@
 import { ethers } from "ethers";
 import { ApiFileInfo } from "../models/FileInfo";
 import { PageData } from "../models/PageData";
-import { documentTypes,  imageTypes, musicTypes, videoTypes } from "./constants";
+import { documentTypes,  imageTypes, musicTypes, SESSION_COOKIE_NAME, videoTypes } from "./constants";
 import { AvatarGenerator } from 'random-avatar-generator';
 
 export function formatCryptoAddress(address?: string, start: number = 10, end: number = 4, message?: string): string {
@@ -74,13 +74,13 @@ export async function switchNetwork(chainId: string) {
 }
 
 
-export async function fetchFiles(publicMetaMaskAddress: string, url: string): Promise<Array<ApiFileInfo>> {
+export async function fetchFiles(url: string): Promise<Array<ApiFileInfo>> {
     try {
       
         const query = await fetch(url, {
             method: 'GET',
             headers: {
-                'metamask_address': publicMetaMaskAddress
+                'nonce': getCookie(SESSION_COOKIE_NAME) ?? ''
             },
         });
         const response = await query.json()
@@ -118,6 +118,36 @@ export async function fetchFiles(publicMetaMaskAddress: string, url: string): Pr
 }
 
 
+// File listings return chains without the file bytes, this puts them back into the genesis
+// revision from `/files/{id}/content` for previews, verification and downloads.
+export async function withFileData(fileInfo: ApiFileInfo, backend_url: string): Promise<ApiFileInfo> {
+    const pageData: PageData = JSON.parse(fileInfo.page_data);
+    const chain = pageData?.pages?.[0];
+    const file = chain?.revisions?.[chain.genesis_hash]?.content?.file;
+    if (!file || file.data) {
+        return fileInfo;
+    }
+
+    const response = await fetch(`${backend_url}/files/${fileInfo.id}/content`, {
+        method: 'GET',
+        headers: {
+            'nonce': getCookie(SESSION_COOKIE_NAME) ?? ''
+        },
+    });
+    if (!response.ok) {
+        throw new Error(`HTTP error! status: ${response.status}`);
+    }
+
+    const bytes = new Uint8Array(await response.arrayBuffer());
+    let binary = '';
+    for (let i = 0; i < bytes.length; i++) {
+        binary += String.fromCharCode(bytes[i]);
+    }
+    file.data = btoa(binary);
+
+    return { ...fileInfo, page_data: JSON.stringify(pageData) };
+}
+
 export function getFileCategory(extension: string): string | null {
     // Remove the leading dot if present (e.g., ".png" becomes "png")
     // const ext = extension.startsWith('.') ? extension.slice(1).toLowerCase() : extension.toLowerCase();